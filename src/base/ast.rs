@@ -64,18 +64,27 @@ pub enum StatementKind {
         right: Box<Expression>,
     },
 
-    // 变量声明 let|const a = b
+    // 变量声明 let|const a = b，没有标注类型时typ为None
     VarDef {
         mutable: bool,
         name: String,
-        typ: NewTypeKind,
+        typ: Option<NewTypeKind>,
         assign: Option<Box<Expression>>,
     },
 
-    // 类型别名 type a = b
+    // 解构声明 let (a, b) = c，模式中绑定的变量都是新定义的变量，不匹配时运行时报错
+    Destruct {
+        mutable: bool,
+        pattern: Pattern,
+        value: Box<Expression>,
+    },
+
+    // 类型别名 type a = b，结构体和枚举也转换成类型定义
+    // 泛型结构体 struct Pair<A, B> {} 的泛型参数放在generics中
     TypeDef {
         left: String,
         right: NewTypeKind,
+        generics: Vec<String>,
     },
 
     // 结构体定义 struct {}
@@ -91,13 +100,28 @@ pub enum StatementKind {
     //     rettyp: TypeToken,
     //     block: StmtList,
     // },
+    // 泛型函数 fn f<T>() 的泛型参数放在generics中
     FuncDef {
         name: String,
+        generics: Vec<String>,
         args: Vec<(String, NewTypeKind)>,
         rettyp: NewTypeKind,
         block: StmtList,
     },
 
+    // 为结构体定义方法 impl T { fn ... } 或者实现trait impl Trait for T { fn ... }
+    Impl {
+        name: String,
+        trait_name: Option<String>,
+        methods: StmtList,
+    },
+
+    // trait定义 trait T { fn m(self) -> type; }，方法的类型不包含self参数
+    Trait {
+        name: String,
+        methods: Vec<(String, NewTypeKind)>,
+    },
+
     // single expression
     Expression {
         expr: Box<Expression>,
@@ -116,15 +140,57 @@ pub enum StatementKind {
         then: StmtList,
     },
 
-    // for语句
+    // for语句，for k, v in x 的多个名称组成元组模式
     For {
-        name: String,
+        pattern: Pattern,
         iter: Box<Expression>,
         then: StmtList,
     },
+
+    // try语句，then中出错时执行catch，name绑定捕获的错误
+    Try {
+        then: StmtList,
+        name: Option<String>,
+        catch: StmtList,
+    },
+
+    // 抛出错误 throw e
+    Throw {
+        value: Box<Expression>,
+    },
+
+    // 导入模块 import "path" as name
+    Import {
+        path: String,
+        name: String,
+    },
+
+    // 导出的定义 export fn ...
+    Export {
+        stmt: Box<Statement>,
+    },
 }
 
 impl Statement {
+    // 导出语句中的定义，其他语句返回自身
+    pub fn definition(&self) -> &Statement {
+        match &self.node {
+            StatementKind::Export { stmt } => stmt,
+            _ => self,
+        }
+    }
+
+    // 定义的名称，impl和其他语句没有名称
+    pub fn defined_name(&self) -> Option<&String> {
+        match &self.definition().node {
+            StatementKind::FuncDef { name, .. }
+            | StatementKind::VarDef { name, .. }
+            | StatementKind::Trait { name, .. } => Some(name),
+            StatementKind::TypeDef { left, .. } => Some(left),
+            _ => None,
+        }
+    }
+
     // lalrpop中使用
     pub fn _set_elif(&mut self, stmts: StmtList) {
         match &mut self.node {
@@ -147,12 +213,18 @@ pub enum ExpressionKind {
         value: String,
     },
 
-    // 属性访问 a.b 或者 a[c]
+    // 属性访问 a.b
     Dot {
         left: Box<Expression>,
         right: Box<Expression>,
     },
 
+    // 下标访问 a[c]，c的值作为下标
+    Index {
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+
     // 函数调用 a()
     Call {
         callee: Box<Expression>,
@@ -182,16 +254,173 @@ pub enum ExpressionKind {
         args: Vec<(String, Box<Expression>)>,
     },
 
+    // 匿名函数 fn(a: i32) -> i32 {}
+    Function {
+        args: Vec<(String, NewTypeKind)>,
+        rettyp: NewTypeKind,
+        block: StmtList,
+    },
+
     // 三元运算 ?:
     Question {
         test: Box<Expression>,
         then: Box<Expression>,
         orelse: Box<Expression>,
     },
+
+    // 模式匹配 match a { p => b, }
+    Match {
+        value: Box<Expression>,
+        arms: Vec<Located<MatchArm>>,
+    },
+
+    // match分支中的语句块，值为unit
+    Block {
+        block: StmtList,
+    },
     // 类型转换 as // 应该属于一个表达式
     // TypeMark {},
 }
 
+// match的分支 pattern if guard => body
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Box<Expression>>,
+    pub body: Box<Expression>,
+}
+
+// match的模式
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    // 通配符 _
+    Wildcard,
+
+    // 匹配任意值并绑定到变量
+    Binding(String),
+
+    // 字面量，数值可以是负数
+    Literal(Literal),
+
+    // 数值范围 a..b 或者 a..=b
+    Range {
+        start: Literal,
+        end: Literal,
+        inclusive: bool,
+    },
+
+    Tuple(Vec<Pattern>),
+
+    // 数组，rest为true时 [a, ..] 只匹配开头的元素
+    Array {
+        items: Vec<Pattern>,
+        rest: bool,
+    },
+
+    // 结构体 T @ {a, b: p} 或者结构体形式的枚举变体，没有列出的字段不检查
+    // 省略名称的 {a, b} 匹配值本身的结构体类型，name为空
+    Struct {
+        name: String,
+        fields: Vec<(String, Pattern)>,
+    },
+
+    // 枚举变体 E::V 或者 E::V(a, b)
+    Variant {
+        name: String,
+        items: Option<Vec<Pattern>>,
+    },
+
+    // 匹配其中任意一个模式 a | b
+    Or(Vec<Pattern>),
+}
+
+impl Pattern {
+    // 模式中绑定的变量
+    pub fn bindings(&self) -> Vec<String> {
+        let mut names = vec![];
+        self.collect_bindings(&mut names);
+        names
+    }
+
+    fn collect_bindings(&self, names: &mut Vec<String>) {
+        match self {
+            Pattern::Binding(name) => names.push(name.clone()),
+            Pattern::Tuple(items)
+            | Pattern::Array { items, .. }
+            | Pattern::Variant {
+                items: Some(items), ..
+            }
+            | Pattern::Or(items) => {
+                for item in items {
+                    item.collect_bindings(names);
+                }
+            }
+            Pattern::Struct { fields, .. } => {
+                for (_, item) in fields {
+                    item.collect_bindings(names);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// 模式按照源码的形式输出
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |items: &[Pattern]| {
+            items
+                .iter()
+                .map(|item| item.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        match self {
+            Pattern::Wildcard => f.write_str("_"),
+            Pattern::Binding(name) => f.write_str(name),
+            Pattern::Literal(value) => write!(f, "{}", value.to_operand()),
+            Pattern::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let op = if *inclusive { "..=" } else { ".." };
+                write!(f, "{}{}{}", start.to_operand(), op, end.to_operand())
+            }
+            Pattern::Tuple(items) if items.len() == 1 => write!(f, "({},)", items[0]),
+            Pattern::Tuple(items) => write!(f, "({})", join(items)),
+            Pattern::Array { items, rest: false } => write!(f, "[{}]", join(items)),
+            Pattern::Array { items, rest: true } if items.is_empty() => f.write_str("[..]"),
+            Pattern::Array { items, rest: true } => write!(f, "[{}, ..]", join(items)),
+            Pattern::Struct { name, fields } => {
+                let fields = fields
+                    .iter()
+                    .map(|(key, item)| format!("{}: {}", key, item))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                if name.is_empty() {
+                    write!(f, "{{{}}}", fields)
+                } else {
+                    write!(f, "{} @ {{{}}}", name, fields)
+                }
+            }
+            Pattern::Variant { name, items: None } => f.write_str(name),
+            Pattern::Variant {
+                name,
+                items: Some(items),
+            } => write!(f, "{}({})", name, join(items)),
+            Pattern::Or(items) => write!(
+                f,
+                "{}",
+                items
+                    .iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" | ")
+            ),
+        }
+    }
+}
+
 impl Expression {
     pub fn is_simple(&self) -> bool {
         match &self.node {
@@ -205,6 +434,16 @@ impl Expression {
         !self.is_simple()
     }
 
+    // 形如 a.f 的表达式，调用时作为方法调用
+    pub fn is_method(&self) -> bool {
+        match &self.node {
+            ExpressionKind::Dot { left: _, right } => {
+                matches!(right.node, ExpressionKind::Ident { value: _ })
+            }
+            _ => false,
+        }
+    }
+
     pub fn to_operand(&self) -> Operand {
         match &self.node {
             ExpressionKind::Liter { value } => value.to_operand(),
@@ -46,6 +46,22 @@ impl FuncManager {
     }
 }
 
+// 从可以调用的重载函数中选出最匹配的一个，costs是每个候选函数各个参数的转换代价
+// 最匹配的函数每个参数的代价都不大于其他函数，并且至少有一个参数更小
+// 无法选出时返回代价最小、互相无法比较的候选函数
+pub fn select_overload(costs: &[Vec<u8>]) -> Result<usize, Vec<usize>> {
+    // a的每个参数都不比b差，并且至少有一个参数更好
+    let better = |a: &Vec<u8>, b: &Vec<u8>| a.iter().zip(b.iter()).all(|(x, y)| x <= y) && a != b;
+    let best = (0..costs.len())
+        .find(|i| (0..costs.len()).all(|j| *i == j || better(&costs[*i], &costs[j])));
+    match best {
+        Some(i) => Ok(i),
+        None => Err((0..costs.len())
+            .filter(|i| !(0..costs.len()).any(|j| better(&costs[j], &costs[*i])))
+            .collect()),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Function {
     // pub name: String,
@@ -53,3 +69,13 @@ pub struct Function {
     pub rettyp: NewTypeKind,
     pub codes: Vec<Instruction>,
 }
+
+impl Function {
+    // 函数值的类型 fn(args) -> rettyp
+    pub fn typ(&self) -> NewTypeKind {
+        NewTypeKind::Function(
+            self.args.iter().map(|item| item.1.clone()).collect(),
+            Box::new(self.rettyp.clone()),
+        )
+    }
+}
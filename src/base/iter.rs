@@ -0,0 +1,132 @@
+use crate::base::number::{self, Number};
+use crate::base::object;
+use crate::base::object::{ObjectValue, WesoObject};
+use crate::base::range::WesoRange;
+use crate::base::types::NewTypeKind;
+
+// for循环使用的迭代器
+pub enum WesoIter {
+    // 数组和元组按下标逐个取值，每次都重新检查长度
+    Items { value: WesoObject, index: usize },
+
+    // 字符串按字符迭代
+    Chars { value: Vec<char>, index: usize },
+
+    // map按照插入的顺序迭代，每一项是 (key, value) 元组，每次都重新检查长度
+    Entries { value: WesoObject, index: usize },
+
+    // 范围按照步长逐个计算，typ为范围中整数的类型
+    Range {
+        value: WesoRange,
+        typ: NewTypeKind,
+        index: i128,
+    },
+
+    // 结构体按照定义字段的顺序迭代，每一项是 (name, value) 元组
+    Fields {
+        value: Vec<(String, WesoObject)>,
+        index: usize,
+    },
+}
+
+impl WesoIter {
+    // order为结构体定义的字段顺序，不在其中的字段按名称排在最后
+    pub fn new(obj: &WesoObject, order: &[String]) -> Result<Self, String> {
+        match obj.get_value() {
+            ObjectValue::Array { value: _ } | ObjectValue::Tuple { value: _ } => {
+                Ok(WesoIter::Items {
+                    value: obj.clone(),
+                    index: 0,
+                })
+            }
+            ObjectValue::Map { value: _ } => Ok(WesoIter::Entries {
+                value: obj.clone(),
+                index: 0,
+            }),
+            // 省略开始的范围没有第一个值
+            ObjectValue::Range { value } if value.start.is_none() => Err(format!(
+                "Type Error: range {} without start is not iterable.",
+                value
+            )),
+            ObjectValue::Range { value } => {
+                let typ = obj.get_typ().range_item().cloned();
+                Ok(WesoIter::Range {
+                    value: value.clone(),
+                    typ: typ.unwrap_or_else(|| NewTypeKind::name("i32")),
+                    index: 0,
+                })
+            }
+            ObjectValue::String { value } => Ok(WesoIter::Chars {
+                value: value.chars().collect(),
+                index: 0,
+            }),
+            ObjectValue::Struct { value } => {
+                let mut fields = value
+                    .borrow()
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect::<Vec<(String, WesoObject)>>();
+                fields.sort_by_key(|(key, _)| {
+                    let index = order.iter().position(|k| k == key);
+                    (index.unwrap_or(order.len()), key.clone())
+                });
+                Ok(WesoIter::Fields {
+                    value: fields,
+                    index: 0,
+                })
+            }
+            _ => Err(format!("Type Error: {} is not iterable.", obj.get_typ())),
+        }
+    }
+}
+
+impl Iterator for WesoIter {
+    type Item = WesoObject;
+
+    fn next(&mut self) -> Option<WesoObject> {
+        match self {
+            WesoIter::Items { value, index } => {
+                let item = match value.get_value() {
+                    ObjectValue::Array { value } | ObjectValue::Tuple { value } => {
+                        value.borrow().get(*index).cloned()
+                    }
+                    _ => None,
+                };
+                *index += 1;
+                item
+            }
+            WesoIter::Entries { value, index } => {
+                let item = match value.get_value() {
+                    ObjectValue::Map { value } => value
+                        .borrow()
+                        .entries()
+                        .get(*index)
+                        .map(|(k, v)| object::create_tuple(vec![k.clone(), v.clone()])),
+                    _ => None,
+                };
+                *index += 1;
+                item
+            }
+            WesoIter::Range { value, typ, index } => {
+                // 超出整数类型的范围时结束
+                let item = value
+                    .nth(*index)
+                    .and_then(|v| number::create_number(typ, Number::Int(v)));
+                *index += 1;
+                item
+            }
+            WesoIter::Chars { value, index } => {
+                let item = value.get(*index).map(|c| object::create_string(c.to_string()));
+                *index += 1;
+                item
+            }
+            WesoIter::Fields { value, index } => {
+                let item = value.get(*index).map(|(k, v)| {
+                    object::create_tuple(vec![object::create_string(k.clone()), v.clone()])
+                });
+                *index += 1;
+                item
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::base::object::{ObjectValue, WesoObject};
+use std::collections::HashMap;
+
+// map的键，按照值计算哈希，不同位数的整数相等时是同一个键
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Integer(i128),
+    Unsigned(u128),
+    String(String),
+    Boolean(bool),
+    Tuple(Vec<MapKey>),
+}
+
+impl MapKey {
+    pub fn of(obj: &WesoObject) -> Result<MapKey, String> {
+        match obj.get_value() {
+            ObjectValue::Integer { value } => Ok(MapKey::Integer(*value)),
+            ObjectValue::Unsigned { value } if *value <= i128::MAX as u128 => {
+                Ok(MapKey::Integer(*value as i128))
+            }
+            ObjectValue::Unsigned { value } => Ok(MapKey::Unsigned(*value)),
+            ObjectValue::String { value } => Ok(MapKey::String(value.clone())),
+            ObjectValue::Boolean { value } => Ok(MapKey::Boolean(*value)),
+            ObjectValue::Tuple { value } => value
+                .borrow()
+                .iter()
+                .map(MapKey::of)
+                .collect::<Result<Vec<MapKey>, String>>()
+                .map(MapKey::Tuple),
+            _ => Err(format!(
+                "Type Error: {} cannot be used as a map key.",
+                obj.get_typ()
+            )),
+        }
+    }
+}
+
+// 按照插入顺序保存的map，index记录键在entries中的位置
+#[derive(Debug, Clone, Default)]
+pub struct WesoMap {
+    index: HashMap<MapKey, usize>,
+    entries: Vec<(WesoObject, WesoObject)>,
+}
+
+impl WesoMap {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn entries(&self) -> &[(WesoObject, WesoObject)] {
+        &self.entries
+    }
+
+    pub fn get(&self, key: &WesoObject) -> Result<Option<WesoObject>, String> {
+        let key = MapKey::of(key)?;
+        Ok(self.index.get(&key).map(|i| self.entries[*i].1.clone()))
+    }
+
+    pub fn contains(&self, key: &WesoObject) -> Result<bool, String> {
+        Ok(self.index.contains_key(&MapKey::of(key)?))
+    }
+
+    // 已有的键只替换值，保持原来的位置
+    pub fn insert(&mut self, key: WesoObject, value: WesoObject) -> Result<(), String> {
+        match self.index.get(&MapKey::of(&key)?) {
+            Some(i) => self.entries[*i].1 = value,
+            None => {
+                self.index.insert(MapKey::of(&key)?, self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+        Ok(())
+    }
+
+    // 删除键，之后的键位置前移
+    pub fn remove(&mut self, key: &WesoObject) -> Result<Option<WesoObject>, String> {
+        let i = match self.index.remove(&MapKey::of(key)?) {
+            Some(i) => i,
+            None => return Ok(None),
+        };
+        let (_, value) = self.entries.remove(i);
+        for pos in self.index.values_mut() {
+            if *pos > i {
+                *pos -= 1;
+            }
+        }
+        Ok(Some(value))
+    }
+}
@@ -1,7 +1,11 @@
 pub mod ast;
 pub mod func;
+pub mod iter;
+pub mod map;
+pub mod number;
 pub mod object;
 pub mod opcode;
+pub mod range;
 pub mod scope;
 pub mod types;
 pub mod utils;
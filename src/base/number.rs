@@ -0,0 +1,277 @@
+// 数值类型的运算，整数运算都会检查溢出
+use crate::base::object;
+use crate::base::object::{ObjectValue, WesoObject};
+use crate::base::types::NewTypeKind;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+// 运行时的数值，u128单独保存，其余的整数都用i128保存
+#[derive(Debug, Clone, Copy)]
+pub enum Number {
+    Int(i128),
+    UInt(u128),
+    Float(f64),
+}
+
+// 整数运算，操作数的类型相同
+macro_rules! checked_op {
+    ($op:expr, $x:expr, $y:expr) => {
+        match $op {
+            "add" => $x.checked_add($y),
+            "sub" => $x.checked_sub($y),
+            "mul" => $x.checked_mul($y),
+            "div" => $x.checked_div($y),
+            "mod" => $x.checked_rem($y),
+            "bitand" => Some($x & $y),
+            "bitor" => Some($x | $y),
+            "bitxor" => Some($x ^ $y),
+            "pow" => u32::try_from($y).ok().and_then(|e| $x.checked_pow(e)),
+            "lshift" => u32::try_from($y).ok().and_then(|s| $x.checked_shl(s)),
+            "rshift" => u32::try_from($y).ok().and_then(|s| $x.checked_shr(s)),
+            _ => None,
+        }
+    };
+}
+
+impl Number {
+    pub fn of(obj: &WesoObject) -> Option<Number> {
+        match obj.get_value() {
+            ObjectValue::Integer { value } => Some(Number::Int(*value)),
+            ObjectValue::Unsigned { value } => Some(Number::UInt(*value)),
+            ObjectValue::Float { value } => Some(Number::Float(*value)),
+            _ => None,
+        }
+    }
+
+    pub fn to_i128(self) -> Option<i128> {
+        match self {
+            Number::Int(v) => Some(v),
+            Number::UInt(v) => i128::try_from(v).ok(),
+            Number::Float(_) => None,
+        }
+    }
+
+    pub fn to_u128(self) -> Option<u128> {
+        match self {
+            Number::Int(v) => u128::try_from(v).ok(),
+            Number::UInt(v) => Some(v),
+            Number::Float(_) => None,
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        match self {
+            Number::Int(v) => v as f64,
+            Number::UInt(v) => v as f64,
+            Number::Float(v) => v,
+        }
+    }
+
+    fn is_zero(self) -> bool {
+        match self {
+            Number::Int(v) => v == 0,
+            Number::UInt(v) => v == 0,
+            Number::Float(v) => v == 0.0,
+        }
+    }
+}
+
+// 按照类型构造数值对象，整数超出范围时返回None
+pub fn create_number(typ: &NewTypeKind, value: Number) -> Option<WesoObject> {
+    if typ.is_float() {
+        let value = value.to_f64();
+        // f32只保留单精度
+        let value = if typ == &NewTypeKind::name("f32") {
+            value as f32 as f64
+        } else {
+            value
+        };
+        return Some(object::create_float(typ, value));
+    }
+    let (signed, bits) = typ.int_kind()?;
+    if !signed && bits == 128 {
+        return value.to_u128().map(object::create_unsigned);
+    }
+    let value = value.to_i128()?;
+    let fits = if bits == 128 {
+        true
+    } else if signed {
+        let max = (1i128 << (bits - 1)) - 1;
+        value >= -max - 1 && value <= max
+    } else {
+        value >= 0 && value < (1i128 << bits)
+    };
+    if fits {
+        Some(object::create_integer(typ, value))
+    } else {
+        None
+    }
+}
+
+// 二元运算，None表示操作数的类型不支持该运算
+pub fn binary(op: &str, lhs: &WesoObject, rhs: &WesoObject) -> Option<Result<WesoObject, String>> {
+    let (x, y) = (Number::of(lhs)?, Number::of(rhs)?);
+    let (ltyp, rtyp) = (lhs.get_typ(), rhs.get_typ());
+    // 乘方和移位的结果类型与左值相同
+    let typ = match op {
+        "pow" | "lshift" | "rshift" if ltyp.is_integer() && rtyp.is_integer() => ltyp.clone(),
+        _ => NewTypeKind::widen(ltyp, rtyp)?,
+    };
+    if typ.is_float() {
+        let (x, y) = (x.to_f64(), y.to_f64());
+        let value = match op {
+            "add" => x + y,
+            "sub" => x - y,
+            "mul" => x * y,
+            "div" => x / y,
+            "mod" => x % y,
+            "pow" => x.powf(y),
+            _ => return None,
+        };
+        return create_number(&typ, Number::Float(value)).map(Ok);
+    }
+
+    if (op == "div" || op == "mod") && y.is_zero() {
+        return Some(Err(String::from("Runtime Error: division by zero.")));
+    }
+    if op == "pow" && y.to_i128().is_some_and(|v| v < 0) {
+        return Some(Err(format!(
+            "Runtime Error: negative exponent {} for integer pow.",
+            rhs.to_string()
+        )));
+    }
+    let (_, bits) = typ.int_kind()?;
+    if (op == "lshift" || op == "rshift") && y.to_u128().is_none_or(|v| v >= bits as u128) {
+        return Some(Err(format!(
+            "Runtime Error: shift amount {} is out of range.",
+            rhs.to_string()
+        )));
+    }
+
+    let overflow = || String::from("Runtime Error: integer overflow.");
+    let value = if typ == NewTypeKind::name("u128") {
+        match (x.to_u128(), y.to_u128()) {
+            (Some(x), Some(y)) => checked_op!(op, x, y).map(Number::UInt),
+            _ => None,
+        }
+    } else {
+        match (x.to_i128(), y.to_i128()) {
+            (Some(x), Some(y)) => checked_op!(op, x, y).map(Number::Int),
+            _ => None,
+        }
+    };
+    Some(
+        value
+            .and_then(|v| create_number(&typ, v))
+            .ok_or_else(overflow),
+    )
+}
+
+// 一元运算 -x 和 ~x
+pub fn unary(op: &str, obj: &WesoObject) -> Option<Result<WesoObject, String>> {
+    let typ = obj.get_typ();
+    let value = match (op, Number::of(obj)?) {
+        ("neg", Number::Float(v)) => Some(Number::Float(-v)),
+        ("neg", Number::Int(v)) => v.checked_neg().map(Number::Int),
+        ("neg", Number::UInt(v)) => {
+            if v == 0 {
+                Some(Number::UInt(v))
+            } else {
+                None
+            }
+        }
+        ("bitnot", Number::UInt(v)) => Some(Number::UInt(!v)),
+        ("bitnot", Number::Int(v)) => match typ.int_kind()? {
+            // 无符号整数按位取反后仍然在范围内
+            (false, bits) => Some(Number::Int(((1i128 << bits) - 1) ^ v)),
+            (true, _) => Some(Number::Int(!v)),
+        },
+        _ => return None,
+    };
+    Some(
+        value
+            .and_then(|v| create_number(typ, v))
+            .ok_or_else(|| String::from("Runtime Error: integer overflow.")),
+    )
+}
+
+// 比较两个数值的大小，不同类型的整数按照数学意义比较
+pub fn compare(lhs: &WesoObject, rhs: &WesoObject) -> Option<Ordering> {
+    match (Number::of(lhs)?, Number::of(rhs)?) {
+        (Number::Int(x), Number::Int(y)) => Some(x.cmp(&y)),
+        (Number::UInt(x), Number::UInt(y)) => Some(x.cmp(&y)),
+        (Number::Int(x), Number::UInt(y)) => Some(match u128::try_from(x) {
+            Ok(x) => x.cmp(&y),
+            Err(_) => Ordering::Less,
+        }),
+        (Number::UInt(x), Number::Int(y)) => Some(match u128::try_from(y) {
+            Ok(y) => x.cmp(&y),
+            Err(_) => Ordering::Greater,
+        }),
+        (x, y) => x.to_f64().partial_cmp(&y.to_f64()),
+    }
+}
+
+// 赋值时将数值转换成声明的类型，整数之间、浮点数之间可以转换
+pub fn cast(obj: &WesoObject, typ: &NewTypeKind) -> Result<WesoObject, String> {
+    let source = obj.get_typ();
+    let same_kind =
+        (typ.is_integer() && source.is_integer()) || (typ.is_float() && source.is_float());
+    if source == typ || !same_kind {
+        return Ok(obj.clone());
+    }
+    match Number::of(obj).and_then(|v| create_number(typ, v)) {
+        Some(v) => Ok(v),
+        None => Err(format!(
+            "Type Error: {} is out of range for {}.",
+            obj.to_string(),
+            typ
+        )),
+    }
+}
+
+// 拆分字面量的数字和类型后缀 10u8 -> ("10", "u8")
+fn split_suffix(text: &str) -> (&str, &str) {
+    match text.find(['i', 'u', 'f']) {
+        Some(i) => (&text[..i], &text[i..]),
+        None => (text, ""),
+    }
+}
+
+// 整数字面量，没有后缀时依次尝试i32 i64 i128 u128
+// 负数只出现在match的模式中，例如 -1
+pub fn int_literal(text: &str) -> Result<WesoObject, String> {
+    let (digits, suffix) = split_suffix(text);
+    let out_of_range = || format!("Type Error: literal {} is out of range.", text);
+    let value = match (digits.strip_prefix('-'), digits.parse::<u128>()) {
+        (Some(digits), _) => match digits.parse::<u128>().map(i128::try_from) {
+            Ok(Ok(v)) => Number::Int(-v),
+            _ => return Err(out_of_range()),
+        },
+        (None, Ok(v)) => Number::UInt(v),
+        (None, Err(_)) => return Err(out_of_range()),
+    };
+    if suffix.is_empty() {
+        return ["i32", "i64", "i128", "u128"]
+            .iter()
+            .find_map(|t| create_number(&NewTypeKind::name(t), value))
+            .ok_or_else(out_of_range);
+    }
+    create_number(&NewTypeKind::name(suffix), value).ok_or_else(|| {
+        format!(
+            "Type Error: literal {} is out of range for {}.",
+            text, suffix
+        )
+    })
+}
+
+// 浮点数字面量，默认为f64
+pub fn float_literal(text: &str) -> Result<WesoObject, String> {
+    let (digits, suffix) = split_suffix(text);
+    let typ = NewTypeKind::name(if suffix.is_empty() { "f64" } else { suffix });
+    match digits.parse::<f64>() {
+        Ok(v) => create_number(&typ, Number::Float(v))
+            .ok_or_else(|| format!("Type Error: invalid literal {}.", text)),
+        Err(_) => Err(format!("Type Error: invalid literal {}.", text)),
+    }
+}
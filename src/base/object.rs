@@ -1,9 +1,14 @@
+use crate::base::func::FuncManager;
+use crate::base::map::WesoMap;
+use crate::base::number;
 use crate::base::opcode::Operand;
+use crate::base::range::WesoRange;
+use crate::base::scope::Scope;
 use crate::base::types::NewTypeKind;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::str::FromStr;
+use std::rc::Rc;
 use std::sync::Arc;
 
 pub type WesoObject = Arc<InnerObject>;
@@ -22,7 +27,7 @@ lazy_static! {
     pub static ref OBJ_NULL: WesoObject = Arc::new(InnerObject {
         mutable: false,
         value: ObjectValue::Null,
-        typ: NewTypeKind::name("any"),
+        typ: NewTypeKind::name("null"),
     });
     pub static ref OBJ_UNIT: WesoObject = Arc::new(InnerObject {
         mutable: false,
@@ -59,6 +64,10 @@ impl InnerObject {
         }
     }
 
+    pub fn is_module(&self) -> bool {
+        matches!(self.value, ObjectValue::Module { .. })
+    }
+
     // 如果是结构体，则根据传入参数获取对应的对象
     pub fn get_attr(&self, key: &String) -> Option<WesoObject> {
         match &self.value {
@@ -66,6 +75,10 @@ impl InnerObject {
                 Some(obj) => Some(obj.clone()),
                 None => None,
             },
+            // 模块只能访问导出的变量和函数
+            ObjectValue::Module { scope, exports, .. } if exports.contains(key) => {
+                Scope::get_object(scope, key).ok()
+            }
             _ => None,
         }
     }
@@ -91,10 +104,113 @@ impl InnerObject {
         }
     }
 
+    pub fn is_array(&self) -> bool {
+        matches!(self.value, ObjectValue::Array { .. })
+    }
+
+    pub fn is_tuple(&self) -> bool {
+        matches!(self.value, ObjectValue::Tuple { .. })
+    }
+
+    pub fn is_map(&self) -> bool {
+        matches!(self.value, ObjectValue::Map { .. })
+    }
+
+    pub fn as_range(&self) -> Option<&WesoRange> {
+        match &self.value {
+            ObjectValue::Range { value } => Some(value),
+            _ => None,
+        }
+    }
+
+    // 数组和字符串的切片，结果是新的数组或者字符串
+    pub fn get_slice(&self, range: &WesoRange) -> Result<WesoObject, String> {
+        match &self.value {
+            ObjectValue::Array { value } => {
+                let value = value.borrow();
+                let (start, end, step) = range.slice(value.len())?;
+                let items = value[start..end].iter().step_by(step).cloned().collect();
+                Ok(Arc::new(InnerObject {
+                    mutable: false,
+                    typ: self.typ.clone(),
+                    value: ObjectValue::Array {
+                        value: RefCell::new(items),
+                    },
+                }))
+            }
+            // 字符串按照字符切片
+            ObjectValue::String { value } => {
+                let chars = value.chars().collect::<Vec<char>>();
+                let (start, end, step) = range.slice(chars.len())?;
+                Ok(create_string(
+                    chars[start..end].iter().step_by(step).collect(),
+                ))
+            }
+            _ => Err(format!("Type Error: {} cannot be sliced.", self.typ)),
+        }
+    }
+
+    // 根据键获取map中的值，键不存在时报错
+    pub fn get_entry(&self, key: &WesoObject) -> Result<WesoObject, String> {
+        match &self.value {
+            ObjectValue::Map { value } => match value.borrow().get(key)? {
+                Some(obj) => Ok(obj),
+                None => Err(format!("Key Error: key {} is not found.", key.to_string())),
+            },
+            _ => Err(format!("Type Error: {} is not a map.", self.typ)),
+        }
+    }
+
+    // 设置map中键对应的值，键不存在时插入
+    pub fn set_entry(&self, key: WesoObject, item: WesoObject) -> Result<(), String> {
+        match &self.value {
+            ObjectValue::Map { value } => value.borrow_mut().insert(key, item),
+            _ => Err(format!("Type Error: {} is not a map.", self.typ)),
+        }
+    }
+
+    // 数组和元组的下标需要在范围内
+    fn index_of(key: &str, len: usize) -> Result<usize, String> {
+        match key.parse::<i64>() {
+            Ok(i) if i >= 0 && (i as usize) < len => Ok(i as usize),
+            Ok(i) => Err(format!(
+                "Index Error: index {} is out of range for length {}.",
+                i, len
+            )),
+            Err(_) => Err(format!("Index Error: {} is not a valid index.", key)),
+        }
+    }
+
+    // 根据下标获取数组或者元组中的元素
+    pub fn get_item(&self, key: &str) -> Result<WesoObject, String> {
+        match &self.value {
+            ObjectValue::Array { value } | ObjectValue::Tuple { value } => {
+                let value = value.borrow();
+                let index = Self::index_of(key, value.len())?;
+                Ok(value[index].clone())
+            }
+            _ => Err(format!("Type Error: {} cannot be indexed.", self.typ)),
+        }
+    }
+
+    // 根据下标修改数组中的元素，元组不能修改
+    pub fn set_item(&self, key: &str, item: WesoObject) -> Result<(), String> {
+        match &self.value {
+            ObjectValue::Array { value } => {
+                let mut value = value.borrow_mut();
+                let index = Self::index_of(key, value.len())?;
+                value[index] = item;
+                Ok(())
+            }
+            ObjectValue::Tuple { value: _ } => Err("Type Error: tuple is immutable.".to_string()),
+            _ => Err(format!("Type Error: {} cannot be indexed.", self.typ)),
+        }
+    }
+
     //
     pub fn is_integer(&self) -> bool {
         match &self.value {
-            ObjectValue::Integer { value: _ } => true,
+            ObjectValue::Integer { value: _ } | ObjectValue::Unsigned { value: _ } => true,
             _ => false,
         }
     }
@@ -120,17 +236,34 @@ impl InnerObject {
         }
     }
 
+    pub fn is_function(&self) -> bool {
+        matches!(self.value, ObjectValue::Function { .. })
+    }
+
     pub fn to_string(&self) -> String {
         match &self.value {
             ObjectValue::Null => String::from("null"),
             ObjectValue::Unit => String::from("unit"),
             ObjectValue::Integer { value } => format!("{}", value),
+            ObjectValue::Unsigned { value } => format!("{}", value),
             ObjectValue::Float { value } => format!("{}", value),
             ObjectValue::String { value } => value.clone(),
             ObjectValue::Boolean { value } => format!("{}", value),
-            ObjectValue::Array { value: _ } => format!("array[]"),
-            ObjectValue::Tuple { value: _ } => format!("tuple()"),
-            ObjectValue::Struct { value: _ } => format!("struct{{}}"),
+            // 错误打印成运行时错误信息的形式
+            ObjectValue::Struct { value } if self.typ == NewTypeKind::name("error") => {
+                let value = value.borrow();
+                let attr = |key: &str| value.get(key).map(|o| o.to_string()).unwrap_or_default();
+                format!("{} Error: {}.", attr("kind"), attr("message"))
+            }
+            ObjectValue::Array { value: _ }
+            | ObjectValue::Tuple { value: _ }
+            | ObjectValue::Map { value: _ }
+            | ObjectValue::Range { value: _ }
+            | ObjectValue::Struct { value: _ }
+            | ObjectValue::Function { .. }
+            | ObjectValue::Module { .. } => format!("{}", self.value),
+            // 枚举的值带上类型名称 Shape::Circle(1)
+            ObjectValue::Enum { .. } => format!("{}::{}", self.typ, self.value),
         }
     }
 }
@@ -141,32 +274,12 @@ pub fn create_literal(op: &Operand) -> Option<WesoObject> {
         Operand::False => Some(OBJ_FALSE.clone()),
         Operand::Null => Some(OBJ_NULL.clone()),
         Operand::Unit => Some(OBJ_UNIT.clone()),
-        Operand::Integer(s) => {
-            let tmp = Arc::new(InnerObject {
-                mutable: false,
-                value: ObjectValue::Integer {
-                    value: i32::from_str(&*s).ok().unwrap_or(0_i32),
-                },
-                typ: NewTypeKind::Named("i32".to_string()),
-            });
-            Some(tmp)
-        }
-        Operand::Float(s) => {
-            let tmp = Arc::new(InnerObject {
-                mutable: false,
-                value: ObjectValue::Float {
-                    value: f64::from_str(&*s).ok().unwrap_or(0_f64),
-                },
-                typ: NewTypeKind::Named("f64".to_string()),
-            });
-            Some(tmp)
-        }
+        Operand::Integer(s) => number::int_literal(s).ok(),
+        Operand::Float(s) => number::float_literal(s).ok(),
         Operand::String(s) => {
             let tmp = Arc::new(InnerObject {
                 mutable: false,
-                value: ObjectValue::String {
-                    value: String::from(&s[1..s.len() - 1]),
-                },
+                value: ObjectValue::String { value: s.clone() },
                 typ: NewTypeKind::Named("str".to_string()),
             });
             Some(tmp)
@@ -184,7 +297,7 @@ pub fn create_object(mutable: bool, typ: &NewTypeKind) -> WesoObject {
     })
 }
 
-pub fn create_integer(typ: &NewTypeKind, value: i32) -> WesoObject {
+pub fn create_integer(typ: &NewTypeKind, value: i128) -> WesoObject {
     Arc::new(InnerObject {
         mutable: false,
         typ: typ.clone(),
@@ -192,6 +305,14 @@ pub fn create_integer(typ: &NewTypeKind, value: i32) -> WesoObject {
     })
 }
 
+pub fn create_unsigned(value: u128) -> WesoObject {
+    Arc::new(InnerObject {
+        mutable: false,
+        typ: NewTypeKind::name("u128"),
+        value: ObjectValue::Unsigned { value },
+    })
+}
+
 pub fn create_float(typ: &NewTypeKind, value: f64) -> WesoObject {
     Arc::new(InnerObject {
         mutable: false,
@@ -208,14 +329,167 @@ pub fn create_string(value: String) -> WesoObject {
     })
 }
 
+// 构造数组，元素类型不一致时为any
+pub fn create_array(value: Vec<WesoObject>) -> WesoObject {
+    let types = value
+        .iter()
+        .map(|o| o.get_typ().clone())
+        .collect::<Vec<NewTypeKind>>();
+    Arc::new(InnerObject {
+        mutable: false,
+        typ: NewTypeKind::Array(Box::new(NewTypeKind::common(&types))),
+        value: ObjectValue::Array {
+            value: RefCell::new(value),
+        },
+    })
+}
+
+// 构造map，键和值的类型分别取公共类型
+pub fn create_map(entries: Vec<(WesoObject, WesoObject)>) -> Result<WesoObject, String> {
+    let keys = entries
+        .iter()
+        .map(|(k, _)| k.get_typ().clone())
+        .collect::<Vec<NewTypeKind>>();
+    let values = entries
+        .iter()
+        .map(|(_, v)| v.get_typ().clone())
+        .collect::<Vec<NewTypeKind>>();
+    let mut map = WesoMap::default();
+    for (key, value) in entries {
+        map.insert(key, value)?;
+    }
+    Ok(Arc::new(InnerObject {
+        mutable: false,
+        typ: NewTypeKind::Map(
+            Box::new(NewTypeKind::common(&keys)),
+            Box::new(NewTypeKind::common(&values)),
+        ),
+        value: ObjectValue::Map {
+            value: RefCell::new(map),
+        },
+    }))
+}
+
+// 构造范围，类型为 range<T>，T为范围中整数的类型
+pub fn create_range(value: WesoRange, item: NewTypeKind) -> WesoObject {
+    Arc::new(InnerObject {
+        mutable: false,
+        typ: NewTypeKind::Generic("range".to_string(), vec![item]),
+        value: ObjectValue::Range { value },
+    })
+}
+
+pub fn create_tuple(value: Vec<WesoObject>) -> WesoObject {
+    Arc::new(InnerObject {
+        mutable: false,
+        typ: NewTypeKind::Tuple(value.iter().map(|o| o.get_typ().clone()).collect()),
+        value: ObjectValue::Tuple {
+            value: RefCell::new(value),
+        },
+    })
+}
+
+// 构造结构体，类型为结构体名称
+pub fn create_struct(name: &str, value: HashMap<String, WesoObject>) -> WesoObject {
+    Arc::new(InnerObject {
+        mutable: false,
+        typ: NewTypeKind::name(name),
+        value: ObjectValue::Struct {
+            value: RefCell::new(value),
+        },
+    })
+}
+
+// 模块对象，模块的类型为module
+pub fn create_module(
+    path: &str,
+    scope: Rc<RefCell<Scope>>,
+    exports: HashSet<String>,
+) -> WesoObject {
+    Arc::new(InnerObject {
+        mutable: false,
+        typ: NewTypeKind::name("module"),
+        value: ObjectValue::Module {
+            path: path.to_string(),
+            scope,
+            exports,
+        },
+    })
+}
+
+// 构造错误，kind为错误的种类，location为空表示还没有抛出
+pub fn create_error(kind: &str, message: &str, location: &str) -> WesoObject {
+    let mut value = HashMap::new();
+    value.insert("kind".to_string(), create_string(kind.to_string()));
+    value.insert("message".to_string(), create_string(message.to_string()));
+    value.insert("location".to_string(), create_string(location.to_string()));
+    create_struct("error", value)
+}
+
+// 将运行时的错误信息 "Type Error: msg. (line 1 colomn 2)" 拆分成错误对象
+pub fn parse_error(e: &str) -> WesoObject {
+    let (text, location) = match e.rfind(" (") {
+        Some(i) if e.ends_with(')') => (&e[..i], &e[i + 2..e.len() - 1]),
+        _ => (e, ""),
+    };
+    let (kind, message) = text.split_once(" Error: ").unwrap_or(("Runtime", text));
+    create_error(kind, message.strip_suffix('.').unwrap_or(message), location)
+}
+
+// 错误对象对应的错误信息，还没有抛出过的错误使用抛出的位置
+pub fn error_message(obj: &WesoObject, location: &str) -> String {
+    let attr = |key: &str| {
+        obj.get_attr(&key.to_string())
+            .map(|o| o.to_string())
+            .unwrap_or_default()
+    };
+    let location = match attr("location") {
+        s if s.is_empty() => location.to_string(),
+        s => s,
+    };
+    format!(
+        "{} Error: {}. ({})",
+        attr("kind"),
+        attr("message"),
+        location
+    )
+}
+
+// 构造枚举的变体，类型为枚举名称，data为元组或者结构体
+pub fn create_enum(name: &str, variant: &str, data: Option<WesoObject>) -> WesoObject {
+    Arc::new(InnerObject {
+        mutable: false,
+        typ: NewTypeKind::name(name),
+        value: ObjectValue::Enum {
+            variant: variant.to_string(),
+            data,
+        },
+    })
+}
+
+// 函数值，类型由函数的参数和返回值决定，scope为定义函数时所在的作用域
+pub fn create_function(id: usize, scope: Rc<RefCell<Scope>>) -> Option<WesoObject> {
+    let func = FuncManager::get_ins().get_func(id)?;
+    Some(Arc::new(InnerObject {
+        mutable: false,
+        typ: func.typ(),
+        value: ObjectValue::Function { id, scope },
+    }))
+}
+
 #[derive(Debug, Clone)]
 pub enum ObjectValue {
     Null,
 
     Unit,
 
+    // 除了u128以外的整数
     Integer {
-        value: i32,
+        value: i128,
+    },
+
+    Unsigned {
+        value: u128,
     },
 
     Float {
@@ -238,9 +512,38 @@ pub enum ObjectValue {
         value: RefCell<Vec<WesoObject>>,
     },
 
+    // 按照插入顺序保存的键值对
+    Map {
+        value: RefCell<WesoMap>,
+    },
+
+    // 整数范围，迭代时才计算每一项
+    Range {
+        value: WesoRange,
+    },
+
     Struct {
         value: RefCell<HashMap<String, WesoObject>>,
     },
+
+    // 枚举的变体以及变体携带的数据
+    Enum {
+        variant: String,
+        data: Option<WesoObject>,
+    },
+
+    // 函数管理器中的函数编号，以及捕获的作用域
+    Function {
+        id: usize,
+        scope: Rc<RefCell<Scope>>,
+    },
+
+    // 运行后的模块，scope为模块最外层的作用域，exports为导出的名称
+    Module {
+        path: String,
+        scope: Rc<RefCell<Scope>>,
+        exports: HashSet<String>,
+    },
 }
 
 impl PartialEq for ObjectValue {
@@ -249,9 +552,62 @@ impl PartialEq for ObjectValue {
             (ObjectValue::Null, ObjectValue::Null) => true,
             (ObjectValue::Unit, ObjectValue::Unit) => true,
             (ObjectValue::Integer { value: v1 }, ObjectValue::Integer { value: v2 }) => v1 == v2,
+            (ObjectValue::Unsigned { value: v1 }, ObjectValue::Unsigned { value: v2 }) => v1 == v2,
             (ObjectValue::Float { value: v1 }, ObjectValue::Float { value: v2 }) => v1 == v2,
             (ObjectValue::String { value: v1 }, ObjectValue::String { value: v2 }) => v1 == v2,
             (ObjectValue::Boolean { value: v1 }, ObjectValue::Boolean { value: v2 }) => v1 == v2,
+            (
+                ObjectValue::Function { id: v1, scope: s1 },
+                ObjectValue::Function { id: v2, scope: s2 },
+            ) => v1 == v2 && Rc::ptr_eq(s1, s2),
+            (ObjectValue::Module { scope: s1, .. }, ObjectValue::Module { scope: s2, .. }) => {
+                Rc::ptr_eq(s1, s2)
+            }
+            (ObjectValue::Range { value: v1 }, ObjectValue::Range { value: v2 }) => v1 == v2,
+            // map与顺序无关，键值对都相同即相等
+            (ObjectValue::Map { value: v1 }, ObjectValue::Map { value: v2 }) => {
+                let (v1, v2) = (v1.borrow(), v2.borrow());
+                v1.len() == v2.len()
+                    && v1.entries().iter().all(|(key, a)| match v2.get(key) {
+                        Ok(Some(b)) => a.get_value() == b.get_value(),
+                        _ => false,
+                    })
+            }
+            // 结构体和枚举按照内容比较
+            (ObjectValue::Struct { value: v1 }, ObjectValue::Struct { value: v2 }) => {
+                let (v1, v2) = (v1.borrow(), v2.borrow());
+                v1.len() == v2.len()
+                    && v1.iter().all(|(key, a)| match v2.get(key) {
+                        Some(b) => a.get_value() == b.get_value(),
+                        None => false,
+                    })
+            }
+            (
+                ObjectValue::Enum {
+                    variant: v1,
+                    data: d1,
+                },
+                ObjectValue::Enum {
+                    variant: v2,
+                    data: d2,
+                },
+            ) => {
+                v1 == v2
+                    && match (d1, d2) {
+                        (Some(a), Some(b)) => a.get_value() == b.get_value(),
+                        (None, None) => true,
+                        _ => false,
+                    }
+            }
+            (ObjectValue::Array { value: v1 }, ObjectValue::Array { value: v2 })
+            | (ObjectValue::Tuple { value: v1 }, ObjectValue::Tuple { value: v2 }) => {
+                let (v1, v2) = (v1.borrow(), v2.borrow());
+                v1.len() == v2.len()
+                    && v1
+                        .iter()
+                        .zip(v2.iter())
+                        .all(|(a, b)| a.get_value() == b.get_value())
+            }
             (_, _) => false,
         }
     }
@@ -263,6 +619,7 @@ impl fmt::Display for ObjectValue {
             ObjectValue::Null => f.write_str("null"),
             ObjectValue::Unit => f.write_str("unit"),
             ObjectValue::Integer { value } => write!(f, "{}", value),
+            ObjectValue::Unsigned { value } => write!(f, "{}", value),
             ObjectValue::Float { value } => write!(f, "{}", value),
             ObjectValue::String { value } => write!(f, "{}", value),
             ObjectValue::Boolean { value } => write!(f, "{}", value),
@@ -286,16 +643,37 @@ impl fmt::Display for ObjectValue {
                     .collect::<Vec<String>>()
                     .join(",")
             ),
-            ObjectValue::Struct { value } => write!(
+            ObjectValue::Range { value } => write!(f, "{}", value),
+            ObjectValue::Map { value } => write!(
                 f,
-                "[{}]",
+                "{{{}}}",
                 value
                     .borrow()
+                    .entries()
                     .iter()
-                    .map(|(k, v)| format!("{}:{}", k, v.to_string()))
+                    .map(|(k, v)| format!("{}:{}", k.to_string(), v.to_string()))
                     .collect::<Vec<String>>()
                     .join(",")
             ),
+            ObjectValue::Struct { value } => {
+                let value = value.borrow();
+                let mut keys = value.keys().collect::<Vec<&String>>();
+                keys.sort();
+                write!(
+                    f,
+                    "{{{}}}",
+                    keys.iter()
+                        .map(|k| format!("{}:{}", k, value[*k].to_string()))
+                        .collect::<Vec<String>>()
+                        .join(",")
+                )
+            }
+            ObjectValue::Function { id, scope: _ } => write!(f, "fn&{}", id),
+            ObjectValue::Module { path, .. } => write!(f, "module {}", path),
+            ObjectValue::Enum { variant, data } => match data {
+                Some(data) => write!(f, "{}{}", variant, data.to_string()),
+                None => f.write_str(variant),
+            },
         }
     }
 }
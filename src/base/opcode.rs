@@ -1,5 +1,5 @@
 // 列举vm执行的操作符和操作数
-use crate::base::ast::Located;
+use crate::base::ast::{Located, Pattern};
 use crate::base::types::NewTypeKind;
 // use crate::parser::token::TypeToken;
 use std::fmt;
@@ -23,6 +23,12 @@ pub enum InnerInstruction {
         num: usize,
     },
 
+    // 方法调用，接收者在参数的下面
+    Method {
+        name: String,
+        num: usize,
+    },
+
     Dot {
         lhs: Operand,
         rhs: Operand,
@@ -50,14 +56,27 @@ pub enum InnerInstruction {
         addr: usize,
     },
 
+    // 进入新的块作用域
+    Enter,
+
+    // 离开当前的块作用域，块内定义的变量随之销毁
+    Leave,
+
+    // 离开若干层块作用域后跳转，用于break和continue
+    Exit {
+        addr: usize,
+        depth: usize,
+    },
+
     Return {
         value: Operand,
     },
 
+    // 没有标注类型的变量typ为None，第一次赋值时确定数值的类型
     DefVar {
         mutable: bool,
         name: Operand,
-        typ: NewTypeKind,
+        typ: Option<NewTypeKind>,
     },
 
     DefFunc {
@@ -66,20 +85,85 @@ pub enum InnerInstruction {
         id: usize,
     },
 
-    Destroy {
-        value: String,
+    DefType {
+        name: String,
+        typ: NewTypeKind,
     },
 
+    // 登记类型typ实现了trait
+    DefImpl {
+        typ: String,
+        name: String,
+    },
+
+    // 创建匿名函数的函数值并放入栈中
+    Lambda {
+        id: usize,
+    },
+
+    // 按字段名依次从栈中取值构造结构体
     Struct {
         value: String,
-        num: usize,
+        keys: Vec<String>,
     },
 
     Repeat, // 将栈顶的值拷贝一遍再弹入栈
 
-    Break,
+    // 检查对象是否匹配模式，匹配时在当前作用域中绑定变量，结果放入栈中
+    Match {
+        value: Operand,
+        pattern: Pattern,
+    },
+
+    // 解构对象，在当前作用域中定义模式绑定的变量，不匹配时报错
+    Destruct {
+        mutable: bool,
+        value: Operand,
+        pattern: Pattern,
+    },
 
-    Continue,
+    // 运行时报错，例如match没有匹配的分支
+    Fail {
+        message: String,
+    },
+
+    // 登记错误处理，当前块作用域中出错时离开该作用域并跳转到addr，错误放入栈中
+    Try {
+        addr: usize,
+    },
+
+    // 抛出错误对象
+    Throw {
+        value: Operand,
+    },
+
+    // 将已经运行的模块绑定到name，并引入模块导出的类型
+    Import {
+        path: String,
+        name: String,
+    },
+
+    // 根据操作数创建迭代器
+    Iter {
+        value: Operand,
+    },
+
+    // 迭代器取下一个值放入栈中，迭代结束时跳转
+    Next {
+        addr: usize,
+    },
+
+    // 销毁当前的迭代器
+    EndIter,
+
+    // depth为所在循环体内嵌套的块作用域层数
+    Break {
+        depth: usize,
+    },
+
+    Continue {
+        depth: usize,
+    },
     // End,
 }
 
@@ -102,6 +186,7 @@ impl fmt::Display for InnerInstruction {
                 rhs.to_string()
             ),
             Call { value, num } => write!(f, "{:<10} {:<10} {}", "call", value.to_string(), num),
+            Method { name, num } => write!(f, "{:<10} {:<10} {}", "method", name, num),
             Dot { lhs, rhs } => write!(
                 f,
                 "{:<10} {:<10} {}",
@@ -116,6 +201,9 @@ impl fmt::Display for InnerInstruction {
             Not { value } => write!(f, "{:<10} {}", "not", value.to_string()),
             Push { value } => write!(f, "{:<10} {}", "push", value.to_string()),
             Goto { addr } => write!(f, "{:<10} #{}", "goto", addr),
+            Enter => write!(f, "enter"),
+            Leave => write!(f, "leave"),
+            Exit { addr, depth } => write!(f, "{:<10} {:<10} #{}", "exit", depth, addr),
             Return { value } => write!(f, "{:<10} {}", "return", value.to_string()),
             DefVar {
                 mutable,
@@ -128,14 +216,32 @@ impl fmt::Display for InnerInstruction {
             DefFunc { name, sign: _, id } => {
                 write!(f, "{:<10} {:<10} &{}", "def", name.to_string(), id)
             }
-            Struct { value, num } => {
-                write!(f, "{:<10} {:<10} {}", "struct", value.to_string(), num)
+            DefType { name, typ } => write!(f, "{:<10} {:<10} {}", "type", name, typ),
+            DefImpl { typ, name } => write!(f, "{:<10} {:<10} {}", "impl", typ, name),
+            Lambda { id } => write!(f, "{:<10} &{}", "lambda", id),
+            Struct { value, keys } => {
+                write!(f, "{:<10} {:<10} {}", "struct", value, keys.join(","))
+            }
+            Match { value, pattern } => write!(f, "{:<10} {:<10} {}", "match", value, pattern),
+            Destruct {
+                mutable,
+                value,
+                pattern,
+            } => {
+                let prefix = if *mutable { "let" } else { "const" };
+                write!(f, "{:<10} {:<10} {}", prefix, pattern, value)
             }
-            Destroy { value } => write!(f, "{:<10} {}", "destroy", value.to_string()),
+            Fail { message } => write!(f, "{:<10} {}", "fail", message),
+            Try { addr } => write!(f, "{:<10} #{}", "try", addr),
+            Throw { value } => write!(f, "{:<10} {}", "throw", value),
+            Import { path, name } => write!(f, "{:<10} {:<10} {}", "import", name, path),
+            Iter { value } => write!(f, "{:<10} {}", "iter", value),
+            Next { addr } => write!(f, "{:<10} #{}", "next", addr),
+            EndIter => write!(f, "enditer"),
             // End => write!(f, "end"),
-            Break => write!(f, "break"),
+            Break { depth } => write!(f, "{:<10} {}", "break", depth),
             Repeat => write!(f, "repeat"),
-            Continue => write!(f, "continue"),
+            Continue { depth } => write!(f, "{:<10} {}", "continue", depth),
         }
     }
 }
@@ -163,7 +269,7 @@ impl fmt::Display for Operand {
             Operand::Stack => f.write_str("$0"),
             Operand::Integer(s) => write!(f, "{}", s),
             Operand::Float(s) => write!(f, "{}", s),
-            Operand::String(s) => write!(f, "{}", s),
+            Operand::String(s) => write!(f, "{:?}", s),
             Operand::Var(s) => write!(f, "{}", s),
         }
     }
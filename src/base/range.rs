@@ -0,0 +1,94 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+// 整数范围，只保存边界和步长，迭代时逐个计算，不会生成整个序列
+// 省略的边界为None，step为负数时从start递减到end
+#[derive(Debug, Clone, PartialEq)]
+pub struct WesoRange {
+    pub start: Option<i128>,
+    pub end: Option<i128>,
+    pub inclusive: bool,
+    pub step: i128,
+}
+
+impl WesoRange {
+    pub fn new(start: Option<i128>, end: Option<i128>, inclusive: bool) -> Self {
+        WesoRange {
+            start,
+            end,
+            inclusive,
+            step: 1,
+        }
+    }
+
+    // 按照步长前进时value是否还没有越过end
+    fn before_end(&self, value: i128) -> bool {
+        match self.end {
+            None => true,
+            Some(end) if self.inclusive && value == end => true,
+            Some(end) if self.step > 0 => value < end,
+            Some(end) => value > end,
+        }
+    }
+
+    // 第index个值，超出范围时为None
+    pub fn nth(&self, index: i128) -> Option<i128> {
+        let value = self.start?.checked_add(self.step.checked_mul(index)?)?;
+        if self.before_end(value) {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    // 判断value是否是范围中的值，需要与start相差整数个步长
+    pub fn contains(&self, value: i128) -> bool {
+        if !self.before_end(value) {
+            return false;
+        }
+        match self.start {
+            None => true,
+            Some(start) if self.step > 0 => {
+                matches!(value.checked_sub(start), Some(d) if d >= 0 && d % self.step == 0)
+            }
+            Some(start) => {
+                matches!(start.checked_sub(value), Some(d) if d >= 0 && d % self.step == 0)
+            }
+        }
+    }
+
+    // 切片的下标 (start, end, step)，省略的边界为0和长度
+    pub fn slice(&self, len: usize) -> Result<(usize, usize, usize), String> {
+        if self.step <= 0 {
+            return Err("Index Error: slice step should be positive.".to_string());
+        }
+        let start = self.start.unwrap_or(0);
+        let end = match self.end {
+            Some(end) if self.inclusive => end.saturating_add(1),
+            Some(end) => end,
+            None => len as i128,
+        };
+        // 步长超出usize时同样属于越界
+        let step = usize::try_from(self.step);
+        if start < 0 || end > len as i128 || start > end || step.is_err() {
+            return Err(format!(
+                "Index Error: slice {} is out of range for length {}.",
+                self, len
+            ));
+        }
+        Ok((start as usize, end as usize, step.unwrap()))
+    }
+}
+
+impl fmt::Display for WesoRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bound = |v: Option<i128>| v.map(|v| v.to_string()).unwrap_or_default();
+        let op = if self.inclusive { "..=" } else { ".." };
+        let range = format!("{}{}{}", bound(self.start), op, bound(self.end));
+        if self.step == 1 {
+            f.write_str(&range)
+        } else {
+            write!(f, "step({},{})", range, self.step)
+        }
+    }
+}
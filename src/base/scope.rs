@@ -1,20 +1,51 @@
+use crate::base::func;
+use crate::base::func::FuncManager;
+use crate::base::number;
 use crate::base::object;
 use crate::base::object::WesoObject;
+use crate::base::types;
 use crate::base::types::NewTypeKind;
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::rc::Rc;
 
+// 函数的编号和定义函数的作用域
+type FuncRef = (usize, Rc<RefCell<Scope>>);
+
+// 作用域中的变量
+struct Variable {
+    mutable: bool,  // 是否可以重新赋值
+    assigned: bool, // 常量只允许赋值一次
+    moved: bool,    // 所有权已经被转移，重新赋值前不能使用
+    typ: NewTypeKind,
+    value: WesoObject,
+    untyped: bool, // 没有标注类型，第一次赋值为数值时确定数值的类型
+    param: bool,   // 函数参数，不能重新赋值，但是可以修改参数的内容
+}
+
 pub struct Scope {
     // 标记父作用域
     parent: Option<Rc<RefCell<Scope>>>,
 
     // 符号表 保存变量名
-    symbol: HashMap<String, WesoObject>,
+    symbol: HashMap<String, Variable>,
 
     // 记录作用域内所有定义的函数 (name,sign)->id
     funcs: HashMap<(String, String), usize>,
+
+    // 记录作用域内定义的类型 name->type
+    types: HashMap<String, NewTypeKind>,
+
+    // 记录类型实现的trait (类型名,trait名)
+    impls: HashSet<(String, String)>,
+
+    // 已经运行的模块 path->module，只保存在最外层的作用域中
+    modules: HashMap<String, WesoObject>,
+
+    // 从模块导入的类型 name->模块的作用域，类型的方法在模块中查找
+    origins: HashMap<String, Rc<RefCell<Scope>>>,
 }
 
 impl Scope {
@@ -23,25 +54,119 @@ impl Scope {
             parent: parent.clone(),
             funcs: HashMap::new(),
             symbol: HashMap::new(),
+            types: HashMap::new(),
+            impls: HashSet::new(),
+            modules: HashMap::new(),
+            origins: HashMap::new(),
         }
     }
 
-    // 减少构造次数
-    fn _get_func(&self, key: &(String, String)) -> Option<usize> {
-        if let Some(id) = self.funcs.get(key) {
-            Some(*id)
-        } else {
-            match self.parent.clone() {
+    pub fn parent(&self) -> Option<Rc<RefCell<Scope>>> {
+        self.parent.clone()
+    }
+
+    // 获取函数 - 根据'函数签名'获取函数，同时返回定义函数的作用域
+    pub fn get_func(
+        scope: &Rc<RefCell<Scope>>,
+        name: &str,
+        sign: &str,
+    ) -> Option<(usize, Rc<RefCell<Scope>>)> {
+        let this = scope.borrow();
+        match this.funcs.get(&(name.to_string(), sign.to_string())) {
+            Some(id) => Some((*id, scope.clone())),
+            None => match &this.parent {
+                Some(parent) => Scope::get_func(parent, name, sign),
                 None => None,
-                Some(p) => p.borrow()._get_func(key),
-            }
+            },
         }
     }
 
-    // 获取函数 - 根据'函数签名'获取函数
-    pub fn get_func(&self, name: &String, sign: &String) -> Option<usize> {
-        let key = &(name.to_string(), sign.to_string());
-        self._get_func(key)
+    // 没有签名完全一致的函数时，按照参数的转换代价选择能够接受实参的函数
+    // 内层作用域的函数覆盖外层参数类型相同的函数，site是调用处的作用域，用于判断类型是否实现了trait
+    // 没有同名函数时返回None，有多个同名函数但是无法选出时返回错误
+    pub fn match_func(
+        scope: &Rc<RefCell<Scope>>,
+        name: &str,
+        args: &[WesoObject],
+        site: &Scope,
+    ) -> Result<Option<FuncRef>, String> {
+        let mut candidates: Vec<(String, usize, Rc<RefCell<Scope>>)> = vec![];
+        let mut current = Some(scope.clone());
+        while let Some(this) = current {
+            for ((n, sign), id) in this.borrow().funcs.iter() {
+                if n == name && !candidates.iter().any(|c| c.0 == *sign) {
+                    candidates.push((sign.clone(), *id, this.clone()));
+                }
+            }
+            current = this.borrow().parent();
+        }
+        candidates.sort_by_key(|c| c.1);
+        let funcs = candidates
+            .iter()
+            .filter_map(|c| FuncManager::get_ins().get_func(c.1))
+            .collect::<Vec<_>>();
+        let mut viable = vec![];
+        let mut costs = vec![];
+        for (i, func) in funcs.iter().enumerate() {
+            if func.args.len() != args.len() {
+                continue;
+            }
+            let cost = func
+                .args
+                .iter()
+                .zip(args.iter())
+                .map(
+                    |((_, typ), arg)| match NewTypeKind::conversion_cost(typ, arg.get_typ()) {
+                        Some(cost) => Some(cost),
+                        None if NewTypeKind::is_castable(typ, arg.get_typ()) => Some(2),
+                        None if site.implements(arg.get_typ(), typ) => Some(2),
+                        None => None,
+                    },
+                )
+                .collect::<Option<Vec<u8>>>();
+            if let Some(cost) = cost {
+                viable.push(i);
+                costs.push(cost);
+            }
+        }
+        let show = |indexes: &mut dyn Iterator<Item = usize>| {
+            indexes
+                .map(|i| {
+                    let params = funcs[i]
+                        .args
+                        .iter()
+                        .map(|(_, typ)| typ.to_string())
+                        .collect::<Vec<String>>();
+                    format!("{}({})", name, params.join(", "))
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        let found = args
+            .iter()
+            .map(|arg| arg.get_typ().to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        match func::select_overload(&costs) {
+            Ok(i) => {
+                let (_, id, scope) = &candidates[viable[i]];
+                Ok(Some((*id, scope.clone())))
+            }
+            // 只有一个同名函数时由调用函数值时检查参数
+            Err(_) if viable.is_empty() && funcs.len() <= 1 => Ok(None),
+            Err(_) if viable.is_empty() => Err(format!(
+                "Type Error: no overload of {} accepts ({}); candidates: {}.",
+                name,
+                found,
+                show(&mut (0..funcs.len()))
+            )),
+            Err(tied) => Err(format!(
+                "Type Error: call to {} with ({}) is ambiguous; candidates: {}.",
+                name,
+                found,
+                show(&mut tied.into_iter().map(|i| viable[i]))
+            )),
+        }
     }
 
     // 在作用域中定义函数
@@ -49,33 +174,216 @@ impl Scope {
         self.funcs.insert((name, sign), id);
     }
 
+    // 在作用域中定义类型
+    pub fn define_type(&mut self, name: &str, typ: &NewTypeKind) {
+        self.types.insert(name.to_string(), typ.clone());
+    }
+
+    // 本作用域中定义的所有类型名称
+    pub fn type_names(&self) -> Vec<String> {
+        self.types.keys().cloned().collect()
+    }
+
+    // 根据名称获取类型，找不到时在父作用域中寻找
+    pub fn get_type(&self, name: &str) -> Option<NewTypeKind> {
+        match self.types.get(name) {
+            Some(typ) => Some(typ.clone()),
+            None => match &self.parent {
+                Some(parent) => parent.borrow().get_type(name),
+                None if name == "error" => Some(NewTypeKind::Struct(types::error_fields())),
+                None => None,
+            },
+        }
+    }
+
+    // 登记已经运行的模块
+    pub fn define_module(&mut self, path: &str, module: WesoObject) {
+        self.modules.insert(path.to_string(), module);
+    }
+
+    pub fn get_module(&self, path: &str) -> Option<WesoObject> {
+        match self.modules.get(path) {
+            Some(module) => Some(module.clone()),
+            None => self.parent.as_ref()?.borrow().get_module(path),
+        }
+    }
+
+    // 登记从模块导入的类型定义在哪个作用域中
+    pub fn define_origin(&mut self, name: &str, scope: Rc<RefCell<Scope>>) {
+        self.origins.insert(name.to_string(), scope);
+    }
+
+    pub fn get_origin(&self, name: &str) -> Option<Rc<RefCell<Scope>>> {
+        match self.origins.get(name) {
+            Some(scope) => Some(scope.clone()),
+            None => self.parent.as_ref()?.borrow().get_origin(name),
+        }
+    }
+
+    // 当前作用域中登记的类型typ实现的trait
+    pub fn impls_of(&self, typ: &str) -> Vec<String> {
+        self.impls
+            .iter()
+            .filter(|(t, _)| t == typ)
+            .map(|(_, name)| name.clone())
+            .collect()
+    }
+
+    // 根据 E::V 的名称获取枚举的名称和变体的数据类型
+    pub fn get_variant(&self, name: &str) -> Option<(String, NewTypeKind)> {
+        let (typ, variant) = name.split_once("::")?;
+        match self.get_type(typ)? {
+            NewTypeKind::Enum(variants) => variants
+                .into_iter()
+                .find(|(v, _)| v == variant)
+                .map(|(_, data)| (typ.to_string(), data)),
+            _ => None,
+        }
+    }
+
+    // 登记类型typ实现了trait
+    pub fn define_impl(&mut self, typ: &str, name: &str) {
+        self.impls.insert((typ.to_string(), name.to_string()));
+    }
+
+    // 判断类型typ是否实现了trait，找不到时在父作用域中寻找
+    pub fn implements(&self, typ: &NewTypeKind, target: &NewTypeKind) -> bool {
+        if self.impls.contains(&(typ.to_string(), target.to_string())) {
+            return true;
+        }
+        match &self.parent {
+            Some(parent) => parent.borrow().implements(typ, target),
+            None => false,
+        }
+    }
+
+    // 判断对象能否作为类型typ的参数，trait类型可以接受实现了它的类型
+    pub fn accepts(&self, typ: &NewTypeKind, obj: &WesoObject) -> bool {
+        let typ = match typ {
+            NewTypeKind::Optional(_) if obj.get_typ().is_null() => return true,
+            _ => typ.non_null(),
+        };
+        NewTypeKind::is_castable(typ, obj.get_typ()) || self.implements(obj.get_typ(), typ)
+    }
+
     // 定义变量
     pub fn define_variable(&mut self, name: &String, mutable: bool, typ: &NewTypeKind) {
-        self.symbol
-            .insert(name.clone(), object::create_object(mutable, typ));
+        self.symbol.insert(
+            name.clone(),
+            Variable {
+                mutable,
+                assigned: false,
+                moved: false,
+                typ: typ.clone(),
+                value: object::create_object(mutable, typ),
+                untyped: false,
+                param: false,
+            },
+        );
+    }
+
+    // 定义没有标注类型的变量，类型为any
+    pub fn define_untyped(&mut self, name: &String, mutable: bool) {
+        self.define_variable(name, mutable, &NewTypeKind::name("any"));
+        if let Some(var) = self.symbol.get_mut(name) {
+            var.untyped = true;
+        }
+    }
+
+    // 定义函数参数
+    pub fn define_param(&mut self, name: &String, typ: &NewTypeKind) {
+        self.define_variable(name, false, typ);
+        if let Some(var) = self.symbol.get_mut(name) {
+            var.param = true;
+        }
     }
 
     // 变量赋值
-    pub fn set_variable(&mut self, name: &String, value: WesoObject) -> bool {
-        if let Some(_) = self.symbol.get(name) {
-            self.symbol.insert(name.clone(), value);
-            true
+    pub fn set_variable(&mut self, name: &String, value: WesoObject) -> Result<(), String> {
+        if let Some(var) = self.symbol.get_mut(name) {
+            if !var.mutable && var.assigned {
+                return Err(format!(
+                    "Variable Error: {} is const and cannot be modified.",
+                    name
+                ));
+            }
+            // 没有声明类型的变量在第一次赋值为数值时确定数值的类型
+            if var.untyped && !var.assigned && (value.is_integer() || value.is_float()) {
+                var.typ = value.get_typ().clone();
+            }
+            // 数值按照声明的类型转换，超出范围时报错
+            var.value = number::cast(&value, var.typ.non_null())?;
+            var.assigned = true;
+            var.moved = false;
+            Ok(())
         } else {
             match self.parent.clone() {
-                None => false,
+                None => Err(format!("Variable Error: {} is not defined.", name)),
                 Some(p) => p.borrow_mut().set_variable(name, value),
             }
         }
     }
 
-    // 根据名称获取作用域中的对象
-    pub fn get_object(&self, name: &String) -> Result<WesoObject, String> {
-        match self.symbol.get(name) {
-            Some(obj) => Ok(obj.clone()),
-            None => match self.parent.clone() {
-                Some(parent) => parent.borrow().get_object(name),
+    // 转移变量的所有权，之后该变量不能再被读取
+    pub fn move_variable(&mut self, name: &String) -> Result<(), String> {
+        if let Some(var) = self.symbol.get_mut(name) {
+            var.moved = true;
+            Ok(())
+        } else {
+            match self.parent.clone() {
                 None => Err(format!("Variable Error: {} is not defined.", name)),
+                Some(p) => p.borrow_mut().move_variable(name),
+            }
+        }
+    }
+
+    // 判断能否通过变量修改其属性或元素，常量不能修改，参数可以修改
+    pub fn is_mutable(&self, name: &String) -> bool {
+        match self.symbol.get(name) {
+            Some(var) => var.mutable || var.param,
+            None => match &self.parent {
+                Some(parent) => parent.borrow().is_mutable(name),
+                None => true,
             },
         }
     }
+
+    // 根据名称获取作用域中的对象，函数值会捕获定义函数的作用域
+    pub fn get_object(scope: &Rc<RefCell<Scope>>, name: &String) -> Result<WesoObject, String> {
+        let this = scope.borrow();
+        match this.symbol.get(name) {
+            Some(var) if var.moved => Err(format!("Variable Error: use after move of {}.", name)),
+            Some(var) if !var.assigned => Err(format!(
+                "Variable Error: {} is used before being assigned.",
+                name
+            )),
+            Some(var) => Ok(var.value.clone()),
+            None => {
+                // 没有同名变量时，函数名可以作为函数值使用
+                let ids = this
+                    .funcs
+                    .iter()
+                    .filter(|((n, _), _)| n == name)
+                    .map(|(_, id)| *id)
+                    .collect::<Vec<usize>>();
+                match (ids.len(), &this.parent) {
+                    (0, Some(parent)) => Scope::get_object(parent, name),
+                    (0, None) => Err(format!("Variable Error: {} is not defined.", name)),
+                    (1, _) => object::create_function(ids[0], scope.clone())
+                        .ok_or_else(|| format!("Variable Error: {} is not defined.", name)),
+                    (_, _) => Err(format!(
+                        "Type Error: function {} is overloaded and cannot be used as a value.",
+                        name
+                    )),
+                }
+            }
+        }
+    }
+}
+
+// 作用域可能通过函数值循环引用，调试输出时不展开
+impl fmt::Debug for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Scope")
+    }
 }
@@ -1,45 +1,21 @@
-// use crate::parser::token::TypeToken;
-// use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-// use std::sync::Arc;
+use std::rc::Rc;
 
-// #[derive(Debug, Clone)]
-// pub struct StructManager {
-//     v: RefCell<Vec<(String, Vec<(String, TypeToken)>)>>,
-// }
+// 内建的基础类型
+const BUILTIN_TYPES: [&str; 17] = [
+    "i8", "i16", "i32", "i64", "i128", "u8", "u16", "u32", "u64", "u128", "f32", "f64", "bool",
+    "str", "unit", "any", "error",
+];
 
-// impl StructManager {
-//     pub fn get_ins() -> &'static StructManager {
-//         static mut _SM: Option<Arc<StructManager>> = None;
-
-//         unsafe {
-//             _SM.get_or_insert_with(|| {
-//                 Arc::new(StructManager {
-//                     v: RefCell::new(Vec::new()),
-//                 })
-//             });
-//             _SM.as_ref().unwrap()
-//         }
-//     }
-
-//     pub fn register(&self, name: String, fields: Vec<(String, TypeToken)>) {
-//         self.v.borrow_mut().push((name, fields));
-//     }
-// }
-
-// #[derive(Debug, Clone, PartialEq)]
-// pub enum NewTypeKind<'input> {
-//     Named(&'input str),
-
-//     Array(Box<NewTypeKind<'input>>),
-
-//     Tuple(Vec<NewTypeKind<'input>>),
-
-//     Struct(HashMap<String, NewTypeKind<'input>>),
-
-//     Function(Vec<NewTypeKind<'input>>, Box<NewTypeKind<'input>>),
-// }
+// 内建的error类型，与结构体一样可以访问字段
+pub fn error_fields() -> Vec<(String, NewTypeKind)> {
+    ["kind", "message", "location"]
+        .iter()
+        .map(|key| (key.to_string(), NewTypeKind::name("str")))
+        .collect()
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum NewTypeKind {
@@ -47,12 +23,32 @@ pub enum NewTypeKind {
 
     Array(Box<NewTypeKind>),
 
+    // 键和值的类型 {str: i32}
+    Map(Box<NewTypeKind>, Box<NewTypeKind>),
+
     Tuple(Vec<NewTypeKind>),
 
-    Struct(HashMap<String, NewTypeKind>),
+    // 结构体的字段，按照定义的顺序保存
+    Struct(Vec<(String, NewTypeKind)>),
 
     // 参数和返回值的类型
     Function(Vec<NewTypeKind>, Box<NewTypeKind>),
+
+    // trait中声明的方法，方法的类型不包含self参数
+    Trait(HashMap<String, NewTypeKind>),
+
+    // 枚举的变体，按照定义的顺序保存
+    // 元组变体的数据为Tuple，结构体变体为Struct，没有数据的为unit
+    Enum(Vec<(String, NewTypeKind)>),
+
+    // 泛型参数 T，只在检查泛型函数和泛型结构体时出现
+    Param(String),
+
+    // 泛型结构体的实例 Pair<i32,str>
+    Generic(String, Vec<NewTypeKind>),
+
+    // 可以为null的类型 i32?，其余的类型都不能为null
+    Optional(Box<NewTypeKind>),
 }
 
 impl fmt::Display for NewTypeKind {
@@ -62,6 +58,8 @@ impl fmt::Display for NewTypeKind {
 
             NewTypeKind::Array(value) => write!(f, "[{}]", value.to_string()),
 
+            NewTypeKind::Map(key, value) => write!(f, "{{{}:{}}}", key, value),
+
             NewTypeKind::Tuple(value) => write!(
                 f,
                 "({})",
@@ -74,7 +72,33 @@ impl fmt::Display for NewTypeKind {
 
             NewTypeKind::Struct(_value) => f.write_str("struct"),
 
-            NewTypeKind::Function(_arr, _typ) => f.write_str("function"),
+            NewTypeKind::Function(args, rettyp) => write!(
+                f,
+                "fn({}) -> {}",
+                args.iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<String>>()
+                    .join(","),
+                rettyp
+            ),
+
+            NewTypeKind::Trait(_value) => f.write_str("trait"),
+
+            NewTypeKind::Enum(_value) => f.write_str("enum"),
+
+            NewTypeKind::Param(value) => f.write_str(value.as_str()),
+
+            NewTypeKind::Generic(name, args) => write!(
+                f,
+                "{}<{}>",
+                name,
+                args.iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+
+            NewTypeKind::Optional(value) => write!(f, "{}?", value),
         }
     }
 }
@@ -83,95 +107,562 @@ impl NewTypeKind {
     pub fn name(value: &str) -> NewTypeKind {
         NewTypeKind::Named(value.to_string())
     }
+
+    // 内建的基础类型，其余的名称都是结构体
+    pub fn is_builtin(&self) -> bool {
+        matches!(self, NewTypeKind::Named(s) if BUILTIN_TYPES.contains(&s.as_str()))
+    }
+
+    pub fn is_any(&self) -> bool {
+        matches!(self, NewTypeKind::Named(s) if s == "any")
+    }
+
+    // null字面量的类型
+    pub fn is_null(&self) -> bool {
+        matches!(self, NewTypeKind::Named(s) if s == "null")
+    }
+
+    pub fn is_optional(&self) -> bool {
+        matches!(self, NewTypeKind::Optional(_))
+    }
+
+    // 去掉可选类型的?，其余的类型不变
+    pub fn non_null(&self) -> &NewTypeKind {
+        match self {
+            NewTypeKind::Optional(typ) => typ,
+            _ => self,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        match self {
+            NewTypeKind::Named(s) => matches!(
+                s.as_str(),
+                "i8" | "i16" | "i32" | "i64" | "i128" | "u8" | "u16" | "u32" | "u64" | "u128"
+            ),
+            _ => false,
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, NewTypeKind::Named(s) if s == "f32" || s == "f64")
+    }
+
+    // 范围 range<T> 中整数的类型
+    pub fn range_item(&self) -> Option<&NewTypeKind> {
+        match self {
+            NewTypeKind::Generic(name, items) if name == "range" && items.len() == 1 => {
+                Some(&items[0])
+            }
+            _ => None,
+        }
+    }
+
+    // 可以作为map的键的类型：整数、字符串、布尔值以及由它们组成的元组
+    pub fn is_hashable(&self) -> bool {
+        match self {
+            NewTypeKind::Tuple(items) => items.iter().all(|item| item.is_hashable()),
+            NewTypeKind::Param(_) => true,
+            NewTypeKind::Named(s) => {
+                self.is_integer() || self.is_any() || s == "str" || s == "bool"
+            }
+            _ => false,
+        }
+    }
+
+    // 整数类型的符号和位数，i16 -> (true, 16)
+    pub fn int_kind(&self) -> Option<(bool, u32)> {
+        if !self.is_integer() {
+            return None;
+        }
+        let name = self.to_string();
+        let bits = name[1..].parse::<u32>().ok()?;
+        Some((name.starts_with('i'), bits))
+    }
+
+    // 不同数值类型运算时的结果类型
+    // 整数与整数：符号相同取位数大的，符号不同取能容纳两者的有符号整数，最大为i128
+    // 整数与浮点数：取浮点数的类型；浮点数之间：取位数大的
+    pub fn widen(lhs: &Self, rhs: &Self) -> Option<NewTypeKind> {
+        if lhs == rhs && (lhs.is_integer() || lhs.is_float()) {
+            return Some(lhs.clone());
+        }
+        match (lhs.int_kind(), rhs.int_kind()) {
+            (Some((s1, b1)), Some((s2, b2))) if s1 == s2 => {
+                Some(if b1 >= b2 { lhs.clone() } else { rhs.clone() })
+            }
+            (Some((s1, b1)), Some((_, b2))) => {
+                let (signed, unsigned) = if s1 { (b1, b2) } else { (b2, b1) };
+                let bits = if signed > unsigned {
+                    signed
+                } else {
+                    (unsigned * 2).min(128)
+                };
+                Some(NewTypeKind::Named(format!("i{}", bits)))
+            }
+            (Some(_), None) if rhs.is_float() => Some(rhs.clone()),
+            (None, Some(_)) if lhs.is_float() => Some(lhs.clone()),
+            (None, None) if lhs.is_float() && rhs.is_float() => Some(NewTypeKind::name("f64")),
+            _ => None,
+        }
+    }
+
+    // 整数之间或者浮点数之间，运行时可以按值转换
+    pub fn is_same_kind(&self, other: &Self) -> bool {
+        (self.is_integer() && other.is_integer()) || (self.is_float() && other.is_float())
+    }
+
+    // 数值的隐式转换只能放宽，例如u8可以放入i16，i64不能放入i32，f64不能放入f32
+    pub fn is_widening(target: &Self, source: &Self) -> bool {
+        target.is_same_kind(source) && Self::widen(target, source).as_ref() == Some(target)
+    }
+
+    // 判断类型source的值能否放入类型target中
+    pub fn is_compatible(target: &Self, source: &Self) -> bool {
+        Self::relates(target, source, false)
+    }
+
+    // 运行时的值按照声明的类型转换，整数之间、浮点数之间都可以转换，范围由number::cast检查
+    // 检查器只允许字面量收窄，所以运行时收窄的值都来自字面量或者any
+    pub fn is_castable(target: &Self, source: &Self) -> bool {
+        Self::relates(target, source, true)
+    }
+
+    fn relates(target: &Self, source: &Self, cast: bool) -> bool {
+        if target == source
+            || target.is_any()
+            || source.is_any()
+            || Self::is_widening(target, source)
+            || (cast && target.is_same_kind(source))
+        {
+            return true;
+        }
+        match (target, source) {
+            // 可选类型可以接受null和内部类型的值，反过来不行
+            (NewTypeKind::Optional(t), NewTypeKind::Optional(s)) => Self::relates(t, s, cast),
+            (NewTypeKind::Optional(_), _) if source.is_null() => true,
+            (NewTypeKind::Optional(t), _) => Self::relates(t, source, cast),
+            _ => Self::nested(target, source, cast),
+        }
+    }
+
+    // 数组、map等内部的类型需要一致，否则通过别名可以放入不符合声明的值
+    // 例如 [u8] 和 [i32] 共享同一个数组时，push到 [i32] 的值不一定在u8的范围内
+    pub fn is_same(target: &Self, source: &Self) -> bool {
+        Self::nested(target, source, false)
+    }
+
+    fn nested(target: &Self, source: &Self, cast: bool) -> bool {
+        if target == source || target.is_any() || source.is_any() {
+            return true;
+        }
+        let same = |t: &NewTypeKind, s: &NewTypeKind| {
+            if cast {
+                Self::relates(t, s, true)
+            } else {
+                Self::nested(t, s, false)
+            }
+        };
+        let all_same = |t: &[NewTypeKind], s: &[NewTypeKind]| {
+            t.len() == s.len() && t.iter().zip(s.iter()).all(|(a, b)| same(a, b))
+        };
+        match (target, source) {
+            (NewTypeKind::Optional(t), NewTypeKind::Optional(s)) => same(t, s),
+            (NewTypeKind::Array(t), NewTypeKind::Array(s)) => same(t, s),
+            (NewTypeKind::Map(k1, v1), NewTypeKind::Map(k2, v2)) => same(k1, k2) && same(v1, v2),
+            // 函数的参数和返回值都需要一致
+            (NewTypeKind::Function(t, r1), NewTypeKind::Function(s, r2)) => {
+                all_same(t, s) && same(r1, r2)
+            }
+            (NewTypeKind::Tuple(t), NewTypeKind::Tuple(s)) => all_same(t, s),
+            // 泛型结构体的实例需要每个泛型参数都一致
+            (NewTypeKind::Generic(n1, t), NewTypeKind::Generic(n2, s)) => {
+                n1 == n2 && all_same(t, s)
+            }
+            // 不带泛型参数的名称可以表示任意的实例，例如impl中的self
+            (NewTypeKind::Generic(name, _), NewTypeKind::Named(s))
+            | (NewTypeKind::Named(s), NewTypeKind::Generic(name, _)) => name == s,
+            _ => false,
+        }
+    }
+
+    // 重载函数排序时实参转换成形参的代价，不能转换时为None
+    // 0 类型完全一致，1 数值放宽，2 其他转换，例如放入可选类型，3 any
+    // 数值不能隐式收窄，所以不会成为候选
+    pub fn conversion_cost(target: &Self, source: &Self) -> Option<u8> {
+        if target == source {
+            Some(0)
+        } else if target.is_any() || source.is_any() {
+            Some(3)
+        } else if !Self::is_compatible(target, source) {
+            None
+        } else if Self::is_widening(target, source) {
+            Some(1)
+        } else {
+            Some(2)
+        }
+    }
+
+    // 泛型结构体实例的名称，其余的类型按照输出的形式
+    pub fn base_name(&self) -> String {
+        match self {
+            NewTypeKind::Generic(name, _) => name.clone(),
+            _ => self.to_string(),
+        }
+    }
+
+    // 将泛型参数替换成推断出的类型，没有推断出的作为any
+    pub fn substitute(&self, bound: &HashMap<String, NewTypeKind>) -> NewTypeKind {
+        self.map(&|typ| match typ {
+            NewTypeKind::Param(name) => Some(
+                bound
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_else(|| NewTypeKind::name("any")),
+            ),
+            _ => None,
+        })
+    }
+
+    // 运行时不区分泛型参数，泛型参数作为any，泛型结构体的实例只保留名称
+    pub fn erase(&self) -> NewTypeKind {
+        self.map(&|typ| match typ {
+            NewTypeKind::Param(_) => Some(NewTypeKind::name("any")),
+            NewTypeKind::Generic(name, _) => Some(NewTypeKind::name(name)),
+            _ => None,
+        })
+    }
+
+    // 按照names替换结构体、枚举、trait和别名的名称，泛型结构体的名称也一起替换
+    pub fn rename(&self, names: &HashMap<String, String>) -> NewTypeKind {
+        self.map(&|typ| match typ {
+            NewTypeKind::Named(name) => names.get(name).map(|name| NewTypeKind::name(name)),
+            NewTypeKind::Generic(name, args) => names.get(name).map(|name| {
+                let args = args.iter().map(|arg| arg.rename(names)).collect();
+                NewTypeKind::Generic(name.clone(), args)
+            }),
+            _ => None,
+        })
+    }
+
+    // 从外向内替换类型，f返回None时继续替换内部的类型
+    fn map(&self, f: &dyn Fn(&NewTypeKind) -> Option<NewTypeKind>) -> NewTypeKind {
+        if let Some(typ) = f(self) {
+            return typ;
+        }
+        let map_all = |items: &[NewTypeKind]| items.iter().map(|item| item.map(f)).collect();
+        match self {
+            NewTypeKind::Array(item) => NewTypeKind::Array(Box::new(item.map(f))),
+            NewTypeKind::Map(key, value) => {
+                NewTypeKind::Map(Box::new(key.map(f)), Box::new(value.map(f)))
+            }
+            NewTypeKind::Tuple(items) => NewTypeKind::Tuple(map_all(items)),
+            NewTypeKind::Struct(fields) => NewTypeKind::Struct(
+                fields
+                    .iter()
+                    .map(|(key, item)| (key.clone(), item.map(f)))
+                    .collect(),
+            ),
+            NewTypeKind::Function(args, rettyp) => {
+                NewTypeKind::Function(map_all(args), Box::new(rettyp.map(f)))
+            }
+            NewTypeKind::Trait(methods) => NewTypeKind::Trait(
+                methods
+                    .iter()
+                    .map(|(key, item)| (key.clone(), item.map(f)))
+                    .collect(),
+            ),
+            NewTypeKind::Enum(variants) => NewTypeKind::Enum(
+                variants
+                    .iter()
+                    .map(|(key, item)| (key.clone(), item.map(f)))
+                    .collect(),
+            ),
+            NewTypeKind::Generic(name, args) => NewTypeKind::Generic(name.clone(), map_all(args)),
+            NewTypeKind::Optional(item) => NewTypeKind::Optional(Box::new(item.map(f))),
+            NewTypeKind::Named(_) | NewTypeKind::Param(_) => self.clone(),
+        }
+    }
+
+    // 数组元素的公共类型，不一致或者为空时为any
+    pub fn common(types: &[NewTypeKind]) -> NewTypeKind {
+        match types.first() {
+            Some(first) if types.iter().all(|t| t == first) => first.clone(),
+            _ => NewTypeKind::name("any"),
+        }
+    }
+}
+
+// 编译期的类型作用域，记录类型别名、结构体、枚举和trait的定义
+pub struct TypeScope {
+    parent: Option<Rc<RefCell<TypeScope>>>,
+
+    // 别名保存解析后的类型，结构体保存解析后的字段
+    types: HashMap<String, NewTypeKind>,
+
+    // 记录类型实现的trait (类型名,trait名)
+    impls: HashSet<(String, String)>,
+
+    // 泛型结构体的泛型参数
+    generics: HashMap<String, Vec<String>>,
 }
 
-// #[derive(Debug, Clone, PartialEq)]
-// pub enum TypeKind {
-//     Token(TypeToken),
-
-//     Struct(Vec<(String, TypeToken)>),
-// }
-
-// #[derive(Debug)]
-// pub struct TypeTable {
-//     types: Vec<TypeKind>,
-
-//     // 根据名称记录到types具体值的映射
-//     // 包括记录别名，结构体
-//     // type xx = xx; struct name {}
-//     hashs: HashMap<String, usize>,
-// }
-
-// impl TypeTable {
-//     pub fn new() -> TypeTable {
-//         TypeTable {
-//             types: vec![],
-//             hashs: HashMap::new(),
-//         }
-//     }
-
-//     // 根据TypeToken获得一个usize数据
-//     pub fn insert(&mut self, typ: &TypeToken) -> Option<usize> {
-//         match typ {
-//             TypeToken::Alias(s) => match self.hashs.get(s) {
-//                 // 可能找不到值
-//                 Some(a) => Some(*a),
-//                 None => None,
-//             },
-//             TypeToken::Tuple(_) | TypeToken::Array(_) => {
-//                 self.types.push(TypeKind::Token(typ.clone()));
-//                 Some(self.types.len() - 1)
-//             }
-//             _ => match self.hashs.get(&typ.to_string()) {
-//                 Some(num) => Some(*num),
-//                 None => {
-//                     self.types.push(TypeKind::Token(typ.clone()));
-//                     self.hashs.insert(typ.to_string(), self.types.len() - 1);
-//                     Some(self.types.len() - 1)
-//                 }
-//             },
-//         }
-//     }
-
-//     // type a = b; 其中先执行insert(b)再insert_alias
-//     pub fn insert_alias(&mut self, key: &String, value: usize) -> Option<usize> {
-//         if value >= self.types.len() {
-//             return None;
-//         }
-//         self.hashs.insert(key.clone(), value);
-//         Some(value)
-//     }
-
-//     // 当遇到struct定义的时候
-//     pub fn insert_struct(&mut self, name: &String, fields: Vec<(String, TypeToken)>) {
-//         self.types.push(TypeKind::Struct(fields));
-//         self.hashs.insert(name.clone(), self.types.len() - 1);
-//     }
-
-//     pub fn insert_kind(&mut self, item: &TypeKind) -> usize {
-//         self.types.push(item.clone());
-//         self.types.len() - 1
-//     }
-
-//     // 通过key获取具体的类型值
-//     pub fn get_item_by_key(&self, key: &String) -> Option<&TypeKind> {
-//         println!("get_item_by_key {}", key);
-//         if let Some(id) = self.hashs.get(key) {
-//             println!("get_item_by_key {}", id);
-//             self.get_item_by_id(*id)
-//         } else {
-//             println!("get_item_by_key {:?}", self.hashs);
-//             None
-//         }
-//     }
-
-//     // 通过id获取具体的类型值
-//     pub fn get_item_by_id(&self, id: usize) -> Option<&TypeKind> {
-//         println!("get_item_by_id {}", id);
-//         if id >= self.types.len() {
-//             None
-//         } else {
-//             Some(&self.types[id])
-//         }
-//     }
-// }
+impl TypeScope {
+    pub fn new(parent: Option<Rc<RefCell<TypeScope>>>) -> Self {
+        TypeScope {
+            parent,
+            types: HashMap::new(),
+            impls: HashSet::new(),
+            generics: HashMap::new(),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<NewTypeKind> {
+        match self.types.get(name) {
+            Some(typ) => Some(typ.clone()),
+            None => match &self.parent {
+                Some(parent) => parent.borrow().lookup(name),
+                None => None,
+            },
+        }
+    }
+
+    // 判断当前作用域是否已经定义了该类型
+    pub fn is_defined(&self, name: &str) -> bool {
+        BUILTIN_TYPES.contains(&name) || self.types.contains_key(name)
+    }
+
+    // 预先登记结构体的名称，使结构体可以引用自身或者之后定义的结构体
+    pub fn declare_struct(&mut self, name: &str) {
+        self.types
+            .insert(name.to_string(), NewTypeKind::Struct(vec![]));
+    }
+
+    // 定义结构体，fields需要是解析后的类型
+    pub fn define_struct(&mut self, name: &str, fields: Vec<(String, NewTypeKind)>) {
+        self.types
+            .insert(name.to_string(), NewTypeKind::Struct(fields));
+    }
+
+    // 定义枚举，variants需要是解析后的类型
+    pub fn define_enum(&mut self, name: &str, variants: Vec<(String, NewTypeKind)>) {
+        self.types
+            .insert(name.to_string(), NewTypeKind::Enum(variants));
+    }
+
+    // 根据 E::V 的名称获取枚举的名称和变体的数据类型
+    pub fn get_variant(&self, name: &str) -> Option<(String, NewTypeKind)> {
+        let (typ, variant) = name.split_once("::")?;
+        match self.lookup(typ)? {
+            NewTypeKind::Enum(variants) => variants
+                .into_iter()
+                .find(|(v, _)| v == variant)
+                .map(|(_, data)| (typ.to_string(), data)),
+            _ => None,
+        }
+    }
+
+    // 登记泛型结构体的泛型参数
+    pub fn define_generics(&mut self, name: &str, params: &[String]) {
+        self.generics.insert(name.to_string(), params.to_vec());
+    }
+
+    // 获取结构体的泛型参数，不是泛型结构体时为空
+    pub fn get_generics(&self, name: &str) -> Vec<String> {
+        match self.generics.get(name) {
+            Some(params) => params.clone(),
+            None => match &self.parent {
+                Some(parent) => parent.borrow().get_generics(name),
+                None => vec![],
+            },
+        }
+    }
+
+    // 在当前作用域中定义泛型参数
+    pub fn define_params(&mut self, params: &[String]) {
+        for param in params {
+            self.types
+                .insert(param.clone(), NewTypeKind::Param(param.clone()));
+        }
+    }
+
+    // 获取枚举的所有变体
+    pub fn get_enum(&self, name: &str) -> Option<Vec<(String, NewTypeKind)>> {
+        match self.lookup(name) {
+            Some(NewTypeKind::Enum(variants)) => Some(variants),
+            _ => None,
+        }
+    }
+
+    // 定义别名，typ需要是解析后的类型
+    pub fn define_alias(&mut self, name: &str, typ: NewTypeKind) {
+        self.types.insert(name.to_string(), typ);
+    }
+
+    // 获取结构体的字段定义
+    pub fn get_struct(&self, name: &str) -> Option<Vec<(String, NewTypeKind)>> {
+        match self.lookup(name) {
+            Some(NewTypeKind::Struct(fields)) => Some(fields),
+            None if name == "error" => Some(error_fields()),
+            _ => None,
+        }
+    }
+
+    // 当前作用域中定义的所有类型名称
+    pub fn names(&self) -> Vec<String> {
+        self.types.keys().cloned().collect()
+    }
+
+    // 引入模块中定义的类型，泛型参数和实现的trait一起引入
+    pub fn import(&mut self, module: &TypeScope, name: &str) {
+        if let Some(typ) = module.types.get(name) {
+            self.types.insert(name.to_string(), typ.clone());
+        }
+        if let Some(params) = module.generics.get(name) {
+            self.generics.insert(name.to_string(), params.clone());
+        }
+        for (typ, trait_name) in module.impls.iter().filter(|(typ, _)| typ == name) {
+            self.impls.insert((typ.clone(), trait_name.clone()));
+        }
+    }
+
+    // 定义trait，methods需要是解析后的类型
+    pub fn define_trait(&mut self, name: &str, methods: HashMap<String, NewTypeKind>) {
+        self.types
+            .insert(name.to_string(), NewTypeKind::Trait(methods));
+    }
+
+    // 获取trait中声明的方法
+    pub fn get_trait(&self, name: &str) -> Option<HashMap<String, NewTypeKind>> {
+        match self.lookup(name) {
+            Some(NewTypeKind::Trait(methods)) => Some(methods),
+            _ => None,
+        }
+    }
+
+    // 登记类型typ实现了trait
+    pub fn define_impl(&mut self, typ: &str, name: &str) {
+        self.impls.insert((typ.to_string(), name.to_string()));
+    }
+
+    // 判断类型typ是否实现了trait，找不到时在父作用域中寻找
+    pub fn implements(&self, typ: &NewTypeKind, target: &NewTypeKind) -> bool {
+        if self.impls.contains(&(typ.to_string(), target.to_string())) {
+            return true;
+        }
+        match &self.parent {
+            Some(parent) => parent.borrow().implements(typ, target),
+            None => false,
+        }
+    }
+
+    // 判断类型source的值能否放入类型target中，trait类型可以接受实现了它的类型
+    pub fn is_compatible(&self, target: &NewTypeKind, source: &NewTypeKind) -> bool {
+        match (target, source) {
+            (NewTypeKind::Optional(t), NewTypeKind::Optional(s)) => self.is_compatible(t, s),
+            (NewTypeKind::Optional(t), _) if !source.is_null() => self.is_compatible(t, source),
+            _ => NewTypeKind::is_compatible(target, source) || self.implements(source, target),
+        }
+    }
+
+    // 将类型解析成只包含内建类型和结构体名称的形式
+    pub fn resolve(&self, typ: &NewTypeKind) -> Result<NewTypeKind, String> {
+        match typ {
+            NewTypeKind::Named(name) => {
+                if BUILTIN_TYPES.contains(&name.as_str()) {
+                    return Ok(typ.clone());
+                }
+                match self.lookup(name) {
+                    // 泛型参数
+                    Some(NewTypeKind::Param(param)) => Ok(NewTypeKind::Param(param)),
+                    // 结构体、枚举和trait按名称区分，避免递归展开
+                    Some(NewTypeKind::Struct(_))
+                    | Some(NewTypeKind::Enum(_))
+                    | Some(NewTypeKind::Trait(_)) => Ok(typ.clone()),
+                    Some(alias) => Ok(alias),
+                    None => Err(format!("Type Error: {} is undefined.", name)),
+                }
+            }
+            NewTypeKind::Array(item) => Ok(NewTypeKind::Array(Box::new(self.resolve(item)?))),
+            // map的键需要能够哈希
+            NewTypeKind::Map(key, value) => match self.resolve(key)? {
+                key if key.is_hashable() => Ok(NewTypeKind::Map(
+                    Box::new(key),
+                    Box::new(self.resolve(value)?),
+                )),
+                key => Err(format!("Type Error: {} cannot be used as a map key.", key)),
+            },
+            // 别名本身是可选类型时不再重复添加?
+            NewTypeKind::Optional(item) => match self.resolve(item)? {
+                NewTypeKind::Optional(item) => Ok(NewTypeKind::Optional(item)),
+                item => Ok(NewTypeKind::Optional(Box::new(item))),
+            },
+            NewTypeKind::Tuple(items) => {
+                let mut v = vec![];
+                for item in items {
+                    v.push(self.resolve(item)?);
+                }
+                Ok(NewTypeKind::Tuple(v))
+            }
+            NewTypeKind::Struct(fields) => {
+                let mut v = vec![];
+                for (key, item) in fields {
+                    v.push((key.clone(), self.resolve(item)?));
+                }
+                Ok(NewTypeKind::Struct(v))
+            }
+            NewTypeKind::Function(args, rettyp) => {
+                let mut v = vec![];
+                for item in args {
+                    v.push(self.resolve(item)?);
+                }
+                Ok(NewTypeKind::Function(v, Box::new(self.resolve(rettyp)?)))
+            }
+            NewTypeKind::Trait(methods) => {
+                let mut hm = HashMap::new();
+                for (key, item) in methods {
+                    hm.insert(key.clone(), self.resolve(item)?);
+                }
+                Ok(NewTypeKind::Trait(hm))
+            }
+            NewTypeKind::Enum(variants) => {
+                let mut v = vec![];
+                for (key, item) in variants {
+                    v.push((key.clone(), self.resolve(item)?));
+                }
+                Ok(NewTypeKind::Enum(v))
+            }
+            NewTypeKind::Param(_) => Ok(typ.clone()),
+            // 内建的范围类型 range<T>，T为整数类型
+            NewTypeKind::Generic(name, args) if name == "range" => match args.as_slice() {
+                [item] if item.is_integer() => Ok(typ.clone()),
+                _ => Err(format!("Type Error: {} is not a valid range type.", typ)),
+            },
+            // 泛型参数的数量需要与定义一致
+            NewTypeKind::Generic(name, args) => {
+                let params = self.get_generics(name);
+                if params.is_empty() {
+                    return match self.lookup(name) {
+                        None if !BUILTIN_TYPES.contains(&name.as_str()) => {
+                            Err(format!("Type Error: {} is undefined.", name))
+                        }
+                        _ => Err(format!("Type Error: {} is not generic.", name)),
+                    };
+                }
+                if params.len() != args.len() {
+                    return Err(format!(
+                        "Type Error: {} expects {} generic arguments, found {}.",
+                        name,
+                        params.len(),
+                        args.len()
+                    ));
+                }
+                let mut v = vec![];
+                for item in args {
+                    v.push(self.resolve(item)?);
+                }
+                Ok(NewTypeKind::Generic(name.clone(), v))
+            }
+        }
+    }
+}
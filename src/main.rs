@@ -16,6 +16,678 @@ fn test1() {
     }
 }
 
+#[cfg(test)]
+fn run_example(path: &str) -> Result<(), String> {
+    vm::weso_run_file(path)
+}
+
+// 每一段代码都应该出错，并且错误信息包含对应的内容
+#[cfg(test)]
+fn assert_fails(cases: &[(&str, &str)]) {
+    assert_fails_with("", cases);
+}
+
+// 代码前面加上共同的定义prefix再运行
+#[cfg(test)]
+fn assert_fails_with(prefix: &str, cases: &[(&str, &str)]) {
+    for (code, msg) in cases {
+        let code = format!("{}{}", prefix, code);
+        match vm::weso_run(&code) {
+            Ok(_) => panic!("{} should fail", code),
+            Err(why) => assert!(why.contains(msg), "{}", why),
+        }
+    }
+}
+
+#[test]
+fn test_for() {
+    if let Err(why) = run_example("examples/for.weso") {
+        panic!("{}", why);
+    }
+}
+
+#[test]
+fn test_struct() {
+    if let Err(why) = run_example("examples/struct.weso") {
+        panic!("{}", why);
+    }
+
+    let decl = "struct Point { x: i32, y: i32 }\n";
+    let cases = [
+        ("let p = Point @ {x: 1};", "missing field y. (line 2"),
+        ("let p = Point @ {x: 1, y: 2, z: 3};", "has no field z. (line 2"),
+        ("let p = Point @ {x: 1, y: \"2\"};", "expects i32, found str. (line 2"),
+        ("let p = Line @ {x: 1};", "Line is undefined. (line 2"),
+    ];
+    assert_fails_with(decl, &cases);
+}
+
+#[test]
+fn test_types() {
+    if let Err(why) = run_example("examples/types.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        ("type A = B;", "B is undefined"),
+        ("if true { type T = str; }\nlet s: T = 1;", "T is undefined"),
+        ("type A = i32;\ntype A = str;", "A has been defined"),
+        ("struct A { x: i32 }\nstruct A { y: i32 }", "A has been defined"),
+        ("struct A { x: Foo }", "Foo is undefined"),
+        ("type A = i32;\nlet a = A @ {x: 1};", "A is not a struct"),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_checker() {
+    let code = "
+        let a: i32 = \"x\";
+        fn f(x: i32) -> str { return x; }
+        f(\"s\");
+        if 1 { }
+        let b = true + 1;
+        for c in 5 { }
+        struct P { x: i32 }
+        let p = P @ {x: 1.5};
+        p.y = 2;
+        type Int = i32;
+        fn g(x: Int) -> Int { return x; }
+        let z: str = g(2);
+    ";
+    let expected = [
+        "cannot assign str to i32. (line 2",
+        "function should return str, found i32. (line 3",
+        "function f does not accept (str). (line 4",
+        "condition should be bool, found i32. (line 5",
+        "function add does not accept type bool and i32. (line 6",
+        "i32 is not iterable. (line 7",
+        "field P.x expects i32, found f64. (line 9",
+        "struct P has no field y. (line 10",
+        "cannot assign i32 to str. (line 13",
+    ];
+    match vm::weso_run(code) {
+        Ok(_) => panic!("type errors should be reported"),
+        Err(why) => {
+            let errors = why.lines().collect::<Vec<&str>>();
+            assert_eq!(errors.len(), expected.len(), "{}", why);
+            for (error, msg) in errors.iter().zip(expected.iter()) {
+                assert!(error.contains(msg), "{}", error);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_const() {
+    if let Err(why) = run_example("examples/const.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        // 编译期
+        ("const a = 1;\na = 2;", "a is const and cannot be modified. (line 2"),
+        ("const a = 1;\na += 2;", "a is const and cannot be modified. (line 2"),
+        (
+            "struct P { x: i32 }\nconst p = P @ {x: 1};\np.x = 2;",
+            "p is const and cannot be modified. (line 3",
+        ),
+        ("fn f(x: i32) {\n x = 2;\n}", "x is const and cannot be modified. (line 2"),
+        ("fn f(a: [i32]) {\n a = [2];\n}", "a is const and cannot be modified. (line 2"),
+        ("const a = [1];\npush(a, 2);", "a is const and cannot be modified. (line 2"),
+        ("const m = {\"a\": 1};\nremove(m, \"a\");", "m is const and cannot be modified. (line 2"),
+        (
+            "let m = {\"k\": [1]};\nconst (n, _) = (m, 0);\nn[\"k\"][0] = 2;",
+            "n is const and cannot be modified. (line 3",
+        ),
+        // 运行时
+        (
+            "const a: i32;\nlet i = 0;\nwhile i < 2 {\n a = i;\n i += 1;\n}",
+            "a is const and cannot be modified. (line 4",
+        ),
+        (
+            "struct P { x: i32 }\nfn f() {\n p.x = 3;\n}\nconst p = P @ {x: 1};\nf();",
+            "x belongs to a const and cannot be modified. (line 3",
+        ),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_move() {
+    if let Err(why) = run_example("examples/move.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        // 编译期
+        ("let a = 1;\nlet b = 0;\nb := a;\nb := a;", "use after move of a. (line 4"),
+        ("let a = 1;\nlet b = 0;\nb := a;\nprintln(a);", "use after move of a. (line 4"),
+        (
+            "struct P { x: i32 }\nlet p = P @ {x: 1};\nlet a = 0;\na := p.x;",
+            "cannot move out of a field. (line 4",
+        ),
+        // 运行时
+        (
+            "let a = 1;\nlet b = 0;\nif true {\n b := a;\n}\nprintln(a);",
+            "use after move of a. (line 6",
+        ),
+        (
+            "let a = 1;\nlet b = 0;\nlet i = 0;\nwhile i < 2 {\n b := a;\n i += 1;\n}",
+            "use after move of a. (line 5",
+        ),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_scope() {
+    if let Err(why) = run_example("examples/scope.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        ("if true {\n let y = 1;\n}\nprintln(y);", "y is not defined. (line 4"),
+        (
+            "let i = 0;\nwhile i < 1 {\n let y = i;\n i += 1;\n}\nprintln(y);",
+            "y is not defined. (line 6",
+        ),
+        ("for c in \"ab\" {\n let y = c;\n}\nprintln(c);", "c is not defined. (line 4"),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_array() {
+    if let Err(why) = run_example("examples/array.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        // 编译期
+        ("let t = (1, 2);\nt[0] = 3;", "tuple is immutable. (line 2"),
+        ("let a = [1];\nlet b = a[\"x\"];", "index should be an integer, found str. (line 2"),
+        ("let a = [1];\npush(a, \"x\");", "cannot push str into [i32]. (line 2"),
+        ("const a = [1];\na[0] = 2;", "a is const and cannot be modified. (line 2"),
+        // 运行时
+        ("let a = [1, 2];\nlet i = 2;\nprintln(a[i]);", "index 2 is out of range for length 2. (line 3"),
+        ("let a = [1];\nlet i = 0 - 1;\na[i] = 0;", "index -1 is out of range for length 1. (line 3"),
+        ("let a = [1];\npop(a);\npop(a);", "pop from empty array. (line 3"),
+        ("let a = [1];\nremove(a, 1);", "index 1 is out of range for length 1. (line 2"),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_map() {
+    if let Err(why) = run_example("examples/map.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        // 编译期
+        ("let m = {\"a\": 1};\nm[\"b\"] = \"x\";", "cannot assign str to i32. (line 2"),
+        ("let m = {\"a\": 1};\nlet v = m[1];", "key should be str, found i32. (line 2"),
+        ("let m = {1.5: 1};", "f64 cannot be used as a map key. (line 1"),
+        ("let m: {[i32]: i32} = {:};", "[i32] cannot be used as a map key. (line 1"),
+        ("let m = {\"a\": 1};\nlet b = 1 in m;", "function inside does not accept type i32 and {str:i32}. (line 2"),
+        ("let m = {\"a\": 1};\nlet v = m.a;", "{str:i32} has no field a. (line 2"),
+        ("const m = {\"a\": 1};\nm[\"a\"] = 2;", "m is const and cannot be modified. (line 2"),
+        // 运行时
+        ("let m = {\"a\": 1};\nprintln(m[\"b\"]);", "key b is not found. (line 2"),
+        ("let m: {str: i32} = {:};\nm[\"a\"] += 1;", "key a is not found. (line 2"),
+        ("let m = {\"a\": 1};\nremove(m, \"b\");", "key b is not found. (line 2"),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_range() {
+    if let Err(why) = run_example("examples/range.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        // 编译期
+        ("let r = 1.5..3;", "range bound should be an integer, found f64. (line 1"),
+        ("let r = step(0..3, \"a\");", "step should be an integer, found str. (line 1"),
+        ("let t = (1, 2);\nlet u = t[0..1];", "(i32,i32) cannot be sliced. (line 2"),
+        ("let a = [1, 2];\na[0..1] = [3];", "slice cannot be assigned. (line 2"),
+        ("let b = \"a\" in 0..3;", "function inside does not accept type str and range<i32>. (line 1"),
+        ("let r: range<str> = 0..1;", "range<str> is not a valid range type. (line 1"),
+        // 运行时
+        ("let a = [1];\nlet i = 5;\nprintln(a[0..i]);", "slice 0..5 is out of range for length 1. (line 3"),
+        ("let s = \"abc\";\nprintln(s[2..1]);", "slice 2..1 is out of range for length 3. (line 2"),
+        ("let a = [1, 2];\nprintln(a[step(0..2, 18446744073709551616i128)]);", "slice step(0..2,18446744073709551616) is out of range for length 2. (line 2"),
+        ("let a = [1];\nprintln(a[step(0..1, -1)]);", "slice step should be positive. (line 2"),
+        ("let n = 0;\nlet r = step(0..3, n);", "step cannot be zero. (line 2"),
+        ("for i in ..3 {\n}", "range ..3 without start is not iterable. (line 1"),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_destruct() {
+    if let Err(why) = run_example("examples/destruct.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        // 编译期
+        ("let (a, b) = (1, 2, 3);", "pattern (a, b) does not match type (i32,i32,i32). (line 1"),
+        ("let p = 1;\nlet {x} = p;", "pattern {x: x} does not match type i32. (line 2"),
+        ("for i, x in [1, 2] {\n}", "pattern (i, x) does not match type i32. (line 1"),
+        ("const (a, b) = (1, 2);\na = 3;", "a is const and cannot be modified. (line 2"),
+        ("let (a, a) = (1, 2);", "a is bound more than once in the pattern. (line 1"),
+        // 运行时
+        ("let a = [1, 2, 3];\nlet [x, y] = a;", "pattern [x, y] does not match value [1,2,3]. (line 2"),
+        ("let t: [any] = [(1, 2)];\nlet (x, y, z) = t[0];", "pattern (x, y, z) does not match value (1,2). (line 2"),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_operators() {
+    if let Err(why) = run_example("examples/operators.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        // 编译期
+        ("let a = -\"x\";", "function neg does not accept type str. (line 1"),
+        ("let a = 1 in 2;", "function inside does not accept type i32 and i32. (line 1"),
+        ("let a = 1.5 << 1;", "function lshift does not accept type f64 and i32. (line 1"),
+        // 运行时
+        ("let a = 0;\nprintln(1 % a);", "division by zero. (line 2"),
+        ("let a = 0;\nprintln(1 / a);", "division by zero. (line 2"),
+        ("let a = 2;\nprintln(a ** 40);", "integer overflow. (line 2"),
+        ("let a = 40;\nprintln(1 << a);", "shift amount 40 is out of range. (line 2"),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_numbers() {
+    if let Err(why) = run_example("examples/numbers.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        // 编译期
+        ("let a = 300u8;", "literal 300u8 is out of range for u8. (line 1"),
+        ("let a = 99999999999999999999999999999999999999999;", "is out of range. (line 1"),
+        ("let a = 1u8 << 1.5;", "function lshift does not accept type u8 and f64."),
+        ("let a: u8 = 300;", "300 is out of range for u8. (line 1"),
+        ("let a = 1u8;\na = 300;", "300 is out of range for u8. (line 2"),
+        ("let (a, b) = (1i8, 2);\na = 128;", "128 is out of range for i8. (line 2"),
+        ("struct P {\n    x: i8\n}\nlet p = P @ {x: 200};", "200 is out of range for i8. (line 4"),
+        ("fn f() -> u8 {\n    return 256;\n}\nf();", "256 is out of range for u8. (line 2"),
+        ("let b: [u8] = [1000];", "1000 is out of range for u8. (line 1"),
+        // 只能隐式放宽，数组等内部的类型需要一致
+        ("let a = 1i64;\nlet b: i32 = a;", "cannot assign i64 to i32. (line 2"),
+        ("let d = 1.5;\nlet f: f32 = d;", "cannot assign f64 to f32. (line 2"),
+        ("let a = 255u8;\na = a + 1;", "cannot assign i32 to u8. (line 2"),
+        ("let a = [1];\nlet b: [u8] = a;", "cannot assign [i32] to [u8]. (line 2"),
+        ("let a = [1u8];\nlet b: [u16] = a;", "cannot assign [u8] to [u16]. (line 2"),
+        ("fn g(a: [u16]) {}\ng([1u8]);", "function g does not accept ([u8]). (line 2"),
+        ("fn f(x: u8) {}\nf(256);", "function f does not accept (i32). (line 2"),
+        // 运行时
+        ("let a = 255u8;\nprintln(a + 1u8);", "integer overflow. (line 2"),
+        ("let a = 1u8;\na = a - 2u8;", "integer overflow. (line 2"),
+        ("let a = 255u8;\na += 1;", "256 is out of range for u8. (line 2"),
+        ("for i in [1u8] {\n    i += 255;\n}", "256 is out of range for u8. (line 2"),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_function() {
+    if let Err(why) = run_example("examples/function.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        // 编译期
+        ("let a = 1;\na(2);", "i32 is not callable. (line 2"),
+        ("let f = fn(x: i32) -> i32 { return x; };\nf(\"a\");", "does not accept (str). (line 2"),
+        ("let f: fn(i32) -> i32 = fn() {};", "cannot assign fn() -> unit to fn(i32) -> i32."),
+        ("fn g(x: i32) {}\nfn g(x: str) {}\nlet f = g;", "function g is overloaded"),
+        ("fn f() -> i32 {\n}", "function may not return i32. (line 1"),
+        (
+            "fn f(x: i32) -> i32 {\n    if x > 0 {\n        return 1;\n    }\n}",
+            "function may not return i32. (line 1",
+        ),
+        ("let a = 1;\nprintln(zz);", "zz is not defined. (line 2"),
+        ("let a = 1;\nfoo(1);", "foo is not defined. (line 2"),
+        // 运行时
+        ("let fs = [1, fn() {}];\nfs[0]();", "i32 is not callable. (line 2"),
+        ("let fs = [fn(x: u8) {}, 1];\nfs[0](300);", "300 is out of range for u8. (line 2"),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_closure() {
+    if let Err(why) = run_example("examples/closure.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        // 调用者的局部变量对被调用的函数不可见
+        (
+            "fn f() -> i32 {\n    return secret;\n}\nfn g() -> i32 {\n    let secret = 1;\n    return f();\n}\ng();",
+            "secret is not defined. (line 2",
+        ),
+        (
+            "let f = fn() -> i32 {\n    return y;\n};\nif true {\n    let y = 1;\n    f();\n}",
+            "y is not defined. (line 2",
+        ),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_impl() {
+    if let Err(why) = run_example("examples/impl.weso") {
+        panic!("{}", why);
+    }
+
+    let point = "struct P {\n    x: i32,\n    y: i32,\n}\nimpl P {\n    fn get(self) -> i32 {\n        return self.x;\n    }\n}\n";
+    let cases = [
+        // 编译期
+        ("let p = P @ {x: 1, y: 2};\np.foo();", "P has no method foo. (line 11"),
+        ("let p = P @ {x: 1, y: 2};\np.get(1);", "function P::get does not accept (P, i32)."),
+        ("P::new();", "P::new is not defined. (line 10"),
+        ("impl Q {}", "Q is not a struct. (line 10"),
+        (
+            "impl P {\n    fn set(self) {\n        self = P @ {x: 1, y: 2};\n    }\n}",
+            "self is const and cannot be modified. (line 12",
+        ),
+        // 运行时
+        ("let a: any = 1;\na.get();", "i32 has no method get. (line 11"),
+    ];
+    assert_fails_with(point, &cases);
+}
+
+#[test]
+fn test_trait() {
+    if let Err(why) = run_example("examples/trait.weso") {
+        panic!("{}", why);
+    }
+
+    let shape = "trait Shape {\n    fn area(self) -> f64;\n}\nstruct P {\n    x: f64\n}\nfn double(s: Shape) -> f64 {\n    return s.area() * 2.0;\n}\n";
+    let cases = [
+        // 编译期
+        ("impl Shape for P {}", "P does not implement Shape.area. (line 10"),
+        ("impl Shape for P {\n    fn area(self) -> i32 {\n        return 1;\n    }\n}", "P::area should be fn() -> f64, found fn() -> i32."),
+        ("impl Q for P {}", "Q is not a trait. (line 10"),
+        ("trait Shape {}", "Shape has been defined. (line 10"),
+        ("double(P @ {x: 1.0});", "function double does not accept (P). (line 10"),
+        ("fn f(s: Shape) {\n    s.foo();\n}", "Shape has no method foo. (line 11"),
+        // 运行时
+        ("let a: [any] = [P @ {x: 1.0}];\ndouble(a[0]);", "function fn(Shape) -> f64 does not accept (P). (line 11"),
+    ];
+    assert_fails_with(shape, &cases);
+}
+
+#[test]
+fn test_enum() {
+    if let Err(why) = run_example("examples/enum.weso") {
+        panic!("{}", why);
+    }
+
+    let shape = "enum Shape {\n    Circle(f64),\n    Rect { w: f64, h: f64 },\n    Empty\n}\n";
+    let cases = [
+        // 编译期
+        ("Shape::Circle(\"a\");", "variant Shape::Circle does not accept (str). (line 6"),
+        ("let a = Shape::Circle;", "variant Shape::Circle expects (f64). (line 6"),
+        ("let a = Shape::Rect;", "variant Shape::Rect expects fields h,w. (line 6"),
+        ("Shape::Empty();", "Shape::Empty is not callable. (line 6"),
+        ("Shape::Rect @ {w: 1.0};", "struct Shape::Rect is missing field h. (line 6"),
+        ("Shape::Circle @ {w: 1.0};", "Shape::Circle is not a struct. (line 6"),
+        ("enum Shape { A }", "Shape has been defined. (line 6"),
+        ("let x: i32 = Shape::Empty;", "cannot assign Shape to i32. (line 6"),
+        // 运行时
+        ("let a: [any] = [\"a\"];\nShape::Circle(a[0]);", "variant Shape::Circle does not accept (str). (line 7"),
+    ];
+    assert_fails_with(shape, &cases);
+}
+
+#[test]
+fn test_match() {
+    if let Err(why) = run_example("examples/match.weso") {
+        panic!("{}", why);
+    }
+
+    let shape = "enum Shape {\n    Circle(f64),\n    Rect { w: f64, h: f64 },\n    Empty\n}\n";
+    let cases = [
+        // 编译期
+        ("match Shape::Empty { Shape::Circle(_) => 1, Shape::Empty => 2, };", "match is not exhaustive, Shape::Rect @ {h: _, w: _} is not covered. (line 6"),
+        ("match (1, true) { (_, true) => 1, (0, false) => 2, };", "match is not exhaustive, (-2147483648..=-1, false) is not covered. (line 6"),
+        ("match [1] { [] => 1, [x] => 2, };", "match is not exhaustive, [_, _, ..] is not covered. (line 6"),
+        ("match 1u8 { 0..=127 => 1, 129..=255 => 2, };", "match is not exhaustive, 128 is not covered. (line 6"),
+        ("match 1i8 { -128..0 => 1, 1..=100 => 2, };", "match is not exhaustive, 0 is not covered. (line 6"),
+        ("match 3 { 1 => 1, _ => 2, 2 => 3, };", "unreachable match arm. (line 6"),
+        ("match 1u8 { 0..=255 => 1, _ => 2, };", "unreachable match arm. (line 6"),
+        ("match [1] { [] => 1, [x, ..] => 2, [y] => 3, };", "unreachable match arm. (line 6"),
+        ("match 3 { \"a\" => 1, _ => 2, };", "pattern \"a\" does not match type i32. (line 6"),
+        ("match 1 { Shape::Empty => 1, _ => 2, };", "pattern Shape::Empty does not match type i32. (line 6"),
+        ("match Shape::Empty { Shape::Nope => 1, _ => 2, };", "Shape::Nope is not an enum variant. (line 6"),
+        ("match Shape::Empty { Shape::Circle => 1, _ => 2, };", "variant Shape::Circle expects (f64). (line 6"),
+        ("match 1 { x | 2 => 1, };", "cannot bind variables in an or-pattern. (line 6"),
+        ("match (1, 2) { (x, x) => 1, };", "x is bound more than once in the pattern. (line 6"),
+        ("match 1 { x if x => 1, _ => 2, };", "condition should be bool, found i32. (line 6"),
+        // 运行时
+        ("let a: [any] = [3];\nmatch a[0] { 1 => 1, 2 => 2, };", "no match arm matches the value. (line 7"),
+    ];
+    assert_fails_with(shape, &cases);
+}
+
+#[test]
+fn test_generic() {
+    if let Err(why) = run_example("examples/generic.weso") {
+        panic!("{}", why);
+    }
+
+    let prelude = "fn first<T>(xs: [T]) -> T {\n    return xs[0];\n}\nfn same<T>(a: T, b: T) -> T {\n    return a;\n}\nstruct Pair<A, B> {\n    first: A,\n    second: B\n}\n";
+    let cases = [
+        ("let x: str = first([1]);", "cannot assign i32 to str. (line 11"),
+        ("same(1, \"a\");", "generic argument T cannot be both i32 and str. (line 11"),
+        ("first(3);", "function first does not accept (i32). (line 11"),
+        ("let p: Pair<i32> = Pair @ {first: 1, second: 2};", "Pair expects 2 generic arguments, found 1. (line 11"),
+        ("let p: Pair<i32, str> = Pair @ {first: 1, second: 2};", "cannot assign Pair<i32,i32> to Pair<i32,str>. (line 11"),
+        ("let p = Pair @ {first: 1, second: 2};\nlet s: str = p.first;", "cannot assign i32 to str. (line 12"),
+        ("fn bad<T>(x: T) -> i32 { return x; }", "function should return i32, found T. (line 11"),
+        ("fn bad<T>(x: T) -> T { return x + 1; }", "function add does not accept type T and i32. (line 11"),
+        ("let y: i32<str> = 1;", "i32 is not generic. (line 11"),
+    ];
+    assert_fails_with(prelude, &cases);
+}
+
+#[test]
+fn test_overload() {
+    if let Err(why) = run_example("examples/overload.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        // 编译期
+        ("fn f(x: i32) {}\nfn f(x: i64, y: str) {}\nf(\"a\", 1);", "no overload of f accepts (str, i32); candidates: f(i32), f(i64, str). (line 3"),
+        ("fn f(x: i64, y: i32) {}\nfn f(x: i32, y: i64) {}\nf(1, 2);", "call to f with (i32, i32) is ambiguous; candidates: f(i64, i32), f(i32, i64). (line 3"),
+        ("fn f(x: i64) {}\nfn f(x: i32) {}\nf(1u8);", "call to f with (u8) is ambiguous; candidates: f(i64), f(i32). (line 3"),
+        ("fn f(x: i32) {}\nfn f(x: str) {}\nf(1i64);", "no overload of f accepts (i64); candidates: f(i32), f(str). (line 3"),
+        ("fn f(x: i32) {}\nf(1i64);", "function f does not accept (i64). (line 2"),
+        ("fn f(x: f32) {}\nfn f(x: str) {}\nlet d = 1.5;\nf(d);", "no overload of f accepts (f64); candidates: f(f32), f(str). (line 4"),
+        // 运行时
+        ("fn f(x: i32) {}\nfn f(x: i64, y: str) {}\nlet a: [any] = [\"a\"];\nf(a[0]);", "no overload of f accepts (str); candidates: f(i32), f(i64, str). (line 4"),
+        ("fn f(x: i64, y: i32) {}\nfn f(x: i32, y: i64) {}\nlet a: [any] = [1];\nf(a[0], a[0]);", "call to f with (i32, i32) is ambiguous; candidates: f(i64, i32), f(i32, i64). (line 4"),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_try() {
+    if let Err(why) = run_example("examples/try.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        ("throw \"x\";", "throw expects error, found str. (line 1"),
+        ("let e = error(1);", "function error does not accept type i32. (line 1"),
+        ("try {} catch e {\n    e.message = \"x\";\n}", "e is const and cannot be modified. (line 2"),
+        ("try {} catch e {\n    println(e.code);\n}", "struct error has no field code. (line 2"),
+        // 没有被捕获的错误
+        ("fn f() {\n    throw error(\"Io\", \"closed\");\n}\nf();", "Io Error: closed. (line 2"),
+        ("try {\n    throw error(\"a\");\n} catch e {\n    throw error(\"b\");\n}", "User Error: b. (line 4"),
+        ("let v: any = 1;\nthrow v;", "throw expects error, found i32. (line 2"),
+    ];
+    assert_fails(&cases);
+
+    // 递归过深时报错而不是栈溢出，debug构建中每层调用占用的栈较多，在更大的栈中运行
+    let deep = std::thread::Builder::new()
+        .stack_size(32 << 20)
+        .spawn(|| {
+            let f = "fn f(n: i32) -> i32 {\n    return 1 + f(n + 1);\n}\n";
+            let caught = "try {\n    f(0);\n} catch e {\n    assert(e.kind == \"Runtime\");\n}";
+            let result = vm::weso_run(&format!("{}{}", f, caught));
+            (result, vm::weso_run(&format!("{}f(0);", f)))
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    if let Err(why) = deep.0 {
+        panic!("{}", why);
+    }
+    match deep.1 {
+        Ok(_) => panic!("unbounded recursion should fail"),
+        Err(why) => assert!(why.contains("maximum call depth 1000 exceeded. (line 2"), "{}", why),
+    }
+}
+
+#[test]
+fn test_module() {
+    if let Err(why) = run_example("examples/module.weso") {
+        panic!("{}", why);
+    }
+
+    let geo = "import \"examples/lib/geometry.weso\" as geo;\n";
+    let cases = [
+        ("geo.square(1.0);", "module geo does not export square. (line 2"),
+        ("let x = geo.hits;", "module geo does not export hits. (line 2"),
+        ("let f = geo.area;", "function geo.area is overloaded"),
+        ("geo.area(1);", "no overload of geo.area accepts (i32)"),
+        // 导入的类型只能通过模块的别名使用
+        ("let c = Circle @ {r: 1.0};", "Circle is undefined. (line 2"),
+        ("struct Circle {\n    r: f64\n}\nlet c: Circle = geo.Circle @ {r: 1.0};", "cannot assign examples/lib/geometry.weso:Circle to Circle. (line 5"),
+        ("geo = geo;", "geo is const and cannot be modified. (line 2"),
+        ("import \"missing.weso\" as m;", "Import Error: cannot load module missing.weso. (line 2"),
+    ];
+    assert_fails_with(geo, &cases);
+
+    // 循环导入和模块中的错误
+    let mut loader = vm::module::MemoryLoader::default();
+    loader.insert("a.weso", "import \"b.weso\" as b;");
+    loader.insert("b.weso", "import \"a.weso\" as a;");
+    loader.insert("c.weso", "export let x: i32 = \"c\";");
+    loader.insert("p.weso", "export struct Pt {\n    x: i32\n}\nstruct Hidden {\n    v: i32\n}\nexport fn hidden() -> Hidden {\n    return Hidden @ {v: 1};\n}");
+    loader.insert("q.weso", "export struct Pt {\n    y: str\n}");
+    loader.insert("r.weso", "struct Pt {\n    x: i32\n}");
+    let cases = [
+        ("import \"a.weso\" as a;", "cyclic import a.weso -> b.weso -> a.weso. (line 1 colomn 0) in b.weso"),
+        ("import \"c.weso\" as c;", "(line 1 colomn 7) in c.weso"),
+        // 不同模块中同名的类型是不同的类型
+        ("import \"p.weso\" as p;\nimport \"q.weso\" as q;\nlet a: q.Pt = p.Pt @ {x: 1};", "cannot assign p.weso:Pt to q.weso:Pt. (line 3"),
+        ("import \"p.weso\" as p;\nstruct Hidden {\n    w: str\n}\nlet h: Hidden = p.hidden();", "cannot assign p.weso:Hidden to Hidden. (line 5"),
+        ("import \"p.weso\" as p;\nlet h: p.Hidden = p.hidden();", "p.Hidden is undefined. (line 2"),
+        ("import \"p.weso\" as p;\nlet h = p.Hidden @ {v: 2};", "module p does not export Hidden. (line 2"),
+        ("import \"r.weso\" as a;\nlet q = a.Pt @ {x: 2};", "module a does not export Pt. (line 2"),
+        ("import \"p.weso\" as a;\nlet q = a.(1);", "expected a name after module a. (line 2"),
+    ];
+    for (code, msg) in cases.iter() {
+        match vm::weso_run_with(code, &loader) {
+            Ok(_) => panic!("{} should fail", code),
+            Err(why) => assert!(why.contains(msg), "{}", why),
+        }
+    }
+    let code = "import \"p.weso\" as p;\nimport \"q.weso\" as q;\nlet a = p.Pt @ {x: 1};\nlet b: q.Pt = q.Pt @ {y: \"b\"};\nassert(a.x == 1 && b.y == \"b\" && p.hidden().v == 1);";
+    if let Err(why) = vm::weso_run_with(code, &loader) {
+        panic!("{}", why);
+    }
+}
+
+#[test]
+fn test_optional() {
+    if let Err(why) = run_example("examples/optional.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        ("let x: i32 = null;", "cannot assign null to i32. (line 1"),
+        ("let x: i32? = 1;\nx = null;\nprintln(x + 1);", "x may be null. (line 3"),
+        ("fn f(x: i32?) -> i32 {\n    return x;\n}", "function should return i32, found i32?. (line 2"),
+        ("struct P {\n    a: i32\n}\nfn f(p: P?) -> i32 {\n    return p.a;\n}", "p may be null. (line 5"),
+        ("let v: [i32]? = null;\nprintln(v[0]);", "v may be null. (line 2"),
+        ("fn f(x: i32?) {\n    if x == null {\n        println(x);\n    } else {\n        x = null;\n    }\n    println(-x);\n}", "x may be null. (line 7"),
+        // 循环中赋值为null的变量在下一次循环时可能为null
+        ("let x: i32? = 1;\nwhile true {\n    println(x + 1);\n    x = null;\n}", "x may be null. (line 3"),
+        // 函数体中不能依赖外层的检查
+        ("let x: i32? = 1;\nlet f = fn() -> i32 {\n    return x;\n};", "function should return i32, found i32?. (line 3"),
+        // 属性的检查在重新赋值后失效
+        ("struct N {\n    next: N?\n}\nlet n = N @ {next: null};\nprintln(n.next.next);", "n.next may be null. (line 5"),
+        (
+            "struct N {\n    next: N?\n}\nlet n = N @ {next: null};\nif n.next != null {\n    n.next = null;\n    println(n.next.next);\n}",
+            "n.next may be null. (line 7",
+        ),
+        (
+            "struct N {\n    next: N?\n}\nlet n = N @ {next: null};\nlet m = n;\nif n.next != null {\n    n = m;\n    println(n.next.next);\n}",
+            "n.next may be null. (line 8",
+        ),
+        // 通过其他别名修改属性，或者调用的函数修改属性和外层变量
+        (
+            "struct N {\n    next: N?\n}\nlet n = N @ {next: null};\nlet m = n;\nif n.next != null {\n    m.next = null;\n    println(n.next.next);\n}",
+            "n.next may be null. (line 8",
+        ),
+        (
+            "struct N {\n    next: N?\n}\nlet n = N @ {next: null};\nlet clear = fn() {\n    n.next = null;\n};\nif n.next != null {\n    clear();\n    println(n.next.next);\n}",
+            "n.next may be null. (line 10",
+        ),
+        (
+            "let x: i32? = 1;\nlet g = fn() {\n    x = null;\n};\nif x != null {\n    g();\n    println(x + 1);\n}",
+            "x may be null. (line 7",
+        ),
+        ("let x: i32;\nprintln(x);", "x is used before being assigned. (line 2"),
+        ("let x: i32;\nif true {\n    x = 1;\n}\nprintln(x);", "x is used before being assigned. (line 5"),
+        ("let x: i32;\nwhile false {\n    x = 1;\n}\nprintln(x);", "x is used before being assigned. (line 5"),
+        ("let x: i32;\ntry {\n    x = 1;\n} catch {\n}\nprintln(x);", "x is used before being assigned. (line 6"),
+        ("let x: i32;\nx += 1;", "x is used before being assigned. (line 2"),
+    ];
+    assert_fails(&cases);
+}
+
+#[test]
+fn test_string() {
+    if let Err(why) = run_example("examples/string.weso") {
+        panic!("{}", why);
+    }
+
+    let cases = [
+        ("let s = \"abc;", "unterminated string. (line 1 colomn 8)"),
+        ("let x = 1;\n  let s = 'ab\ncd';", "unterminated string. (line 2 colomn 11)"),
+        ("let s = \"a\\qb\";", "invalid escape \\q. (line 1 colomn 10)"),
+        ("let s = \"\\u{110000}\";", "invalid unicode escape. (line 1 colomn 9)"),
+        ("let x = 1;\n  let s = \"a {x +}\";", "invalid expression in string. (line 2 colomn 15)"),
+        ("let s = \"a } b\";", "unmatched } in string. (line 1 colomn 11)"),
+        ("let s = \"a { b\";", "unterminated interpolation. (line 1 colomn 11)"),
+        ("let s = \"{\"a}\";", "unterminated interpolation. (line 1 colomn 9)"),
+        ("let s = r#\"a\"\";", "unterminated string. (line 1 colomn 8)"),
+        ("import \"m{1}\" as m;", "interpolation is not allowed here. (line 1 colomn 7)"),
+        // 原始字符串跨行之后的行号
+        ("let s = r\"a\nb\nc\";\nlet x: i32 = s;", "(line 4"),
+        ("let x = 1;\nlet s = \"{x + y}\";", "y is not defined. (line 2 colomn 15)"),
+    ];
+    assert_fails(&cases);
+}
+
 fn main() {
     use std::fs;
 
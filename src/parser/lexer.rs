@@ -29,22 +29,39 @@ impl Location {
     pub fn short_show(&self) -> String {
         format!("{}:{}", self.row, self.column)
     }
+
+    // 同一行中向后n列的位置
+    pub fn shift(&self, n: usize) -> Location {
+        Location {
+            row: self.row,
+            column: self.column + n,
+        }
+    }
 }
 
 pub struct LexerError {
-    // pub error: String,
-// pub location: Location,
+    pub error: String,
+    pub location: Location,
+}
+
+impl LexerError {
+    pub fn new(error: &str, location: Location) -> Self {
+        LexerError {
+            error: error.to_string(),
+            location,
+        }
+    }
 }
 
 impl fmt::Display for LexerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "LexerError")
+        write!(f, "Lexer Error: {}. ({})", self.error, self.location)
     }
 }
 
 impl fmt::Debug for LexerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "LexerError")
+        write!(f, "{}", self)
     }
 }
 
@@ -52,6 +69,8 @@ pub struct Lexer<'input> {
     lex: LogosLexer<'input, LogosToken<'input>>,
     curcol: usize,
     currow: usize,
+    // 第一行的起始列，用于字符串中插值的表达式
+    base: usize,
 }
 
 impl<'input> Lexer<'input> {
@@ -60,6 +79,24 @@ impl<'input> Lexer<'input> {
             lex: LogosToken::lexer(instr),
             curcol: 0,
             currow: 1,
+            base: 0,
+        }
+    }
+
+    // 从location开始分析，位置按照所在的源码计算
+    pub fn at(instr: &'input str, location: Location) -> Lexer<'input> {
+        Lexer {
+            lex: LogosToken::lexer(instr),
+            curcol: 0,
+            currow: location.row,
+            base: location.column,
+        }
+    }
+
+    fn location(&self, offset: usize) -> Location {
+        Location {
+            row: self.currow,
+            column: offset - self.curcol + self.base,
         }
     }
 }
@@ -87,20 +124,28 @@ impl<'input> Iterator for Lexer<'input> {
                 LogosToken::Newline => {
                     self.currow += 1;
                     self.curcol = span.start;
+                    self.base = 0;
                     continue;
                 }
+                // 错误的位置是字符串开始的引号，没有结束的双引号字符串和原始字符串 r#" 得到Error
+                LogosToken::UnterminatedString | LogosToken::Error
+                    if tok == LogosToken::UnterminatedString
+                        || matches!(self.lex.slice(), "\"" | "r#\"") =>
+                {
+                    let location = self.location(span.start);
+                    return Some(Err(LexerError::new("unterminated string", location)));
+                }
                 _ => {
-                    let spanned: Spanned<'input> = (
-                        Location {
-                            row: self.currow,
-                            column: span.start - self.curcol,
-                        },
-                        tok,
-                        Location {
-                            row: self.currow,
-                            column: span.end - self.curcol,
-                        },
-                    );
+                    let start = self.location(span.start);
+                    // 原始字符串可以跨行
+                    if let LogosToken::String(s) = tok {
+                        if let Some(i) = s.rfind('\n') {
+                            self.currow += s.matches('\n').count();
+                            self.curcol = span.start + i;
+                            self.base = 0;
+                        }
+                    }
+                    let spanned: Spanned<'input> = (start, tok, self.location(span.end));
                     return Some(LexResult::Ok(spanned));
                 }
             };
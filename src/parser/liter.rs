@@ -1,4 +1,10 @@
+use crate::base::ast::{Expression, ExpressionKind};
 use crate::base::opcode::Operand;
+use crate::parser::lexer::{Lexer, LexerError, Location};
+use crate::parser::weso::TestExprParser;
+use lalrpop_util::ParseError;
+use std::iter::Peekable;
+use std::str::CharIndices;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
@@ -36,3 +42,147 @@ impl Literal {
     //     }
     // }
 }
+
+// 字符串字面量的组成部分，插值的表达式记录在字面量中的偏移
+#[derive(Debug, Clone, PartialEq)]
+pub enum StrPart {
+    Text(String),
+    Expr(String, usize),
+}
+
+// 解析字符串字面量，处理转义字符和双引号字符串中的插值 {expr}
+// 出错时返回错误信息和在字面量中的偏移
+pub fn parse_string(token: &str) -> Result<Vec<StrPart>, (String, usize)> {
+    // 原始字符串 r"..." r#"..."# 不处理转义和插值
+    if let Some(raw) = token.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let text = &raw[hashes + 1..raw.len() - hashes - 1];
+        return Ok(vec![StrPart::Text(text.to_string())]);
+    }
+    let interpolate = token.starts_with('"');
+    let body = &token[1..token.len() - 1];
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut chars = body.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                let escape = chars.next().map_or(' ', |(_, e)| e);
+                text.push(match escape {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    '0' => '\0',
+                    '\\' | '\'' | '"' | '{' | '}' => escape,
+                    'u' => unicode(&mut chars)
+                        .ok_or_else(|| ("invalid unicode escape".to_string(), i + 1))?,
+                    _ => return Err((format!("invalid escape \\{}", escape), i + 1)),
+                });
+            }
+            // {{ 和 }} 表示花括号本身
+            '{' | '}' if interpolate && chars.peek().map(|p| p.1) == Some(c) => {
+                chars.next();
+                text.push(c);
+            }
+            '}' if interpolate => return Err(("unmatched } in string".to_string(), i + 1)),
+            '{' if interpolate => {
+                let mut depth = 1;
+                let end = loop {
+                    match chars.next() {
+                        // 表达式中的字符串可以包含花括号
+                        Some((_, '"')) => loop {
+                            match chars.next() {
+                                Some((_, '\\')) => {
+                                    chars.next();
+                                }
+                                Some((_, '"')) | None => break,
+                                Some(_) => (),
+                            }
+                        },
+                        Some((_, '{')) => depth += 1,
+                        Some((j, '}')) if depth == 1 => break j,
+                        Some((_, '}')) => depth -= 1,
+                        Some(_) => (),
+                        None => return Err(("unterminated interpolation".to_string(), i + 1)),
+                    }
+                };
+                if !text.is_empty() {
+                    parts.push(StrPart::Text(std::mem::take(&mut text)));
+                }
+                parts.push(StrPart::Expr(body[i + 1..end].to_string(), i + 2));
+            }
+            _ => text.push(c),
+        }
+    }
+    if !text.is_empty() || parts.is_empty() {
+        parts.push(StrPart::Text(text));
+    }
+    Ok(parts)
+}
+
+// \u{XXXX} 中的unicode码点，最多6位十六进制数
+fn unicode(chars: &mut Peekable<CharIndices>) -> Option<char> {
+    if chars.next()?.1 != '{' {
+        return None;
+    }
+    let mut code = String::new();
+    loop {
+        match chars.next()?.1 {
+            '}' => break,
+            c if c.is_ascii_hexdigit() && code.len() < 6 => code.push(c),
+            _ => return None,
+        }
+    }
+    char::from_u32(u32::from_str_radix(&code, 16).ok()?)
+}
+
+// 不允许插值的字符串，例如模式和导入的路径
+pub fn plain_string(token: &str, location: Location) -> Result<String, LexerError> {
+    let parts = parse_string(token).map_err(|(e, i)| LexerError::new(&e, location.shift(i)))?;
+    match parts.as_slice() {
+        [StrPart::Text(text)] => Ok(text.clone()),
+        _ => Err(LexerError::new(
+            "interpolation is not allowed here",
+            location,
+        )),
+    }
+}
+
+// 插值的字符串转换成字符串的拼接 "a{x}" -> "a" + str(x)
+pub fn interpolate(token: &str, location: Location) -> Result<Box<Expression>, LexerError> {
+    let parts = parse_string(token).map_err(|(e, i)| LexerError::new(&e, location.shift(i)))?;
+    let node = |node| Box::new(Expression { location, node });
+    let ident = |value: &str| {
+        node(ExpressionKind::Ident {
+            value: value.to_string(),
+        })
+    };
+    let mut exprs = vec![];
+    for part in parts {
+        exprs.push(match part {
+            StrPart::Text(value) => node(ExpressionKind::Liter {
+                value: Literal::String(value),
+            }),
+            StrPart::Expr(source, i) => {
+                let start = location.shift(i);
+                let expr = TestExprParser::new()
+                    .parse(Lexer::at(&source, start))
+                    .map_err(|e| match e {
+                        ParseError::User { error } => error,
+                        _ => LexerError::new("invalid expression in string", start),
+                    })?;
+                node(ExpressionKind::Call {
+                    callee: ident("str"),
+                    args: vec![expr],
+                })
+            }
+        });
+    }
+    let first = exprs.remove(0);
+    Ok(exprs.into_iter().fold(first, |left, right| {
+        node(ExpressionKind::Call {
+            callee: ident("add"),
+            args: vec![left, right],
+        })
+    }))
+}
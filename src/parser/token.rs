@@ -13,6 +13,8 @@ pub enum LogosToken<'source> {
     Type,
     #[token("struct")]
     Struct,
+    #[token("enum")]
+    Enum,
     #[token("let")]
     Let,
     #[token("const")]
@@ -35,6 +37,26 @@ pub enum LogosToken<'source> {
     Continue,
     #[token("return")]
     Return,
+    #[token("impl")]
+    Impl,
+    #[token("trait")]
+    Trait,
+    #[token("match")]
+    Match,
+    #[token("try")]
+    Try,
+    #[token("catch")]
+    Catch,
+    #[token("throw")]
+    Throw,
+    #[token("import")]
+    Import,
+    #[token("export")]
+    Export,
+    #[token("as")]
+    As,
+    #[token("self")]
+    SelfValue,
     // 内置类型 关键词
     // #[token("i8")]
     // Int8,
@@ -77,6 +99,10 @@ pub enum LogosToken<'source> {
     // 操作符
     #[token(".")]
     Dot,
+    #[token("..")]
+    Range,
+    #[token("..=")]
+    RangeEq,
     #[token("=")]
     Assign,
     #[token(":=")]
@@ -156,8 +182,12 @@ pub enum LogosToken<'source> {
     Semi,
     #[token(":")]
     Colon,
+    #[token("::")]
+    Path,
     #[token("->")]
     Arrow,
+    #[token("=>")]
+    FatArrow,
     #[token(",")]
     Comma,
     #[token("?")]
@@ -175,12 +205,20 @@ pub enum LogosToken<'source> {
     #[token("]")] //"
     Rsqb,
     // 字面量
-    #[regex(r"[0-9]+")] // "
+    #[regex(r"[0-9]+(i8|i16|i32|i64|i128|u8|u16|u32|u64|u128)?")] // "
     Integer(&'source str),
-    #[regex("[0-9]+\\.[0-9]+")] // "
+    #[regex(r"([0-9]+\.[0-9]+(f32|f64)?)|([0-9]+(f32|f64))")] // "
     Float(&'source str),
-    #[regex("(\"[^\"]*\")|('[^\']*')")] // "
+    // 普通字符串不能跨行，可以包含转义字符；原始字符串 r"..." r#"..."# 可以跨行
+    #[token("\"", string)] // "
+    #[regex(r#"'([^'\\\r\n]|\\.)*'"#)] // "
+    #[regex(r#"r"[^"]*""#)] // "
+    #[token("r#\"", raw_string)] // "
     String(&'source str),
+    // 没有结束引号的字符串，词法分析时报错
+    #[regex(r#"'([^'\\\r\n]|\\.)*"#)] // "
+    #[regex(r#"r"[^"]*"#)] // "
+    UnterminatedString,
     // 变量
     #[regex("[_a-zA-Z][_a-zA-Z0-9]*")] //"
     VarName(&'source str),
@@ -190,6 +228,49 @@ pub enum LogosToken<'source> {
     Blank,
 }
 
+// 双引号字符串，插值的表达式中可以包含字符串，例如 "{m["k"]}"
+// 插值不完整时在同一行最后一个引号处结束，由字面量的解析报告插值的错误，没有结束时得到Error
+fn string<'s>(lex: &mut logos::Lexer<'s, LogosToken<'s>>) -> Option<&'s str> {
+    let text = lex.remainder();
+    let line = &text[..text.find(['\r', '\n']).unwrap_or(text.len())];
+    let end = string_end(line).or_else(|| line.rfind('"'))?;
+    lex.bump(end + 1);
+    Some(lex.slice())
+}
+
+// 结束引号的位置，插值中的字符串不结束
+fn string_end(line: &str) -> Option<usize> {
+    // 插值的花括号层数，以及是否在插值的字符串中
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next()?;
+            }
+            '"' if depth == 0 => return Some(i),
+            '"' => quoted = !quoted,
+            _ if quoted => (),
+            // {{ 和 }} 表示花括号本身
+            '{' | '}' if depth == 0 && chars.peek().map(|p| p.1) == Some(c) => {
+                chars.next();
+            }
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+// 原始字符串 r#"..."# 在第一个 "# 处结束，内容可以以引号结尾，没有结束时得到Error
+fn raw_string<'s>(lex: &mut logos::Lexer<'s, LogosToken<'s>>) -> Option<&'s str> {
+    let end = lex.remainder().find("\"#")?;
+    lex.bump(end + 2);
+    Some(lex.slice())
+}
+
 // 获取语法分析得到的类型参数
 /*
 #[derive(Debug, Clone, PartialEq)]
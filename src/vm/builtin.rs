@@ -1,5 +1,10 @@
+use crate::base::number;
+use crate::base::number::Number;
 use crate::base::object;
 use crate::base::object::{ObjectValue, WesoObject};
+use crate::base::range::WesoRange;
+use crate::base::types::NewTypeKind;
+use std::cmp::Ordering;
 use std::sync::Arc;
 use wasm_bindgen::prelude::*;
 
@@ -18,6 +23,14 @@ macro_rules! weso_obj_not {
     };
 }
 
+fn bool_obj(value: bool) -> WesoObject {
+    if value {
+        object::OBJ_TRUE.clone()
+    } else {
+        object::OBJ_FALSE.clone()
+    }
+}
+
 // 基础功能支持
 // ==
 pub fn std_equal(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
@@ -29,6 +42,10 @@ pub fn std_equal(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
     } else {
         let lhs = &args[0];
         let rhs = &args[1];
+        // 不同类型的数值按照数学意义比较
+        if let Some(ord) = number::compare(lhs, rhs) {
+            return Ok(bool_obj(ord == Ordering::Equal));
+        }
         if lhs.get_typ() == rhs.get_typ() {
             if lhs.get_value() == rhs.get_value() {
                 return Ok(object::OBJ_TRUE.clone());
@@ -53,39 +70,15 @@ pub fn std_lt(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
     }
     let lhs = &args[0];
     let rhs = &args[1];
+    if let Some(ord) = number::compare(lhs, rhs) {
+        return Ok(bool_obj(ord == Ordering::Less));
+    }
     match (lhs.get_value(), rhs.get_value()) {
-        (ObjectValue::Integer { value: v1 }, ObjectValue::Integer { value: v2 }) => {
-            Ok(if v1 < v2 {
-                object::OBJ_TRUE.clone()
-            } else {
-                object::OBJ_FALSE.clone()
-            })
-        }
-        (ObjectValue::Float { value: v1 }, ObjectValue::Float { value: v2 }) => Ok(if v1 < v2 {
-            object::OBJ_TRUE.clone()
-        } else {
-            object::OBJ_FALSE.clone()
-        }),
         (ObjectValue::Boolean { value: v1 }, ObjectValue::Boolean { value: v2 }) => {
-            Ok(if v1 < v2 {
-                object::OBJ_TRUE.clone()
-            } else {
-                object::OBJ_FALSE.clone()
-            })
-        }
-        (ObjectValue::Integer { value: v1 }, ObjectValue::Float { value: v2 }) => {
-            Ok(if f64::from(*v1) < *v2 {
-                object::OBJ_TRUE.clone()
-            } else {
-                object::OBJ_FALSE.clone()
-            })
+            Ok(bool_obj(v1 < v2))
         }
-        (ObjectValue::Float { value: v1 }, ObjectValue::Integer { value: v2 }) => {
-            Ok(if *v1 < f64::from(*v2) {
-                object::OBJ_TRUE.clone()
-            } else {
-                object::OBJ_FALSE.clone()
-            })
+        (ObjectValue::String { value: v1 }, ObjectValue::String { value: v2 }) => {
+            Ok(bool_obj(v1 < v2))
         }
         (_, _) => Err(format!(
             "Runtime Error: function lt does not accept type {} and {}.",
@@ -105,39 +98,15 @@ pub fn std_gt(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
     }
     let lhs = &args[0];
     let rhs = &args[1];
+    if let Some(ord) = number::compare(lhs, rhs) {
+        return Ok(bool_obj(ord == Ordering::Greater));
+    }
     match (lhs.get_value(), rhs.get_value()) {
-        (ObjectValue::Integer { value: v1 }, ObjectValue::Integer { value: v2 }) => {
-            Ok(if v1 > v2 {
-                object::OBJ_TRUE.clone()
-            } else {
-                object::OBJ_FALSE.clone()
-            })
-        }
-        (ObjectValue::Float { value: v1 }, ObjectValue::Float { value: v2 }) => Ok(if v1 > v2 {
-            object::OBJ_TRUE.clone()
-        } else {
-            object::OBJ_FALSE.clone()
-        }),
         (ObjectValue::Boolean { value: v1 }, ObjectValue::Boolean { value: v2 }) => {
-            Ok(if v1 > v2 {
-                object::OBJ_TRUE.clone()
-            } else {
-                object::OBJ_FALSE.clone()
-            })
-        }
-        (ObjectValue::Integer { value: v1 }, ObjectValue::Float { value: v2 }) => {
-            Ok(if f64::from(*v1) > *v2 {
-                object::OBJ_TRUE.clone()
-            } else {
-                object::OBJ_FALSE.clone()
-            })
+            Ok(bool_obj(v1 > v2))
         }
-        (ObjectValue::Float { value: v1 }, ObjectValue::Integer { value: v2 }) => {
-            Ok(if *v1 > f64::from(*v2) {
-                object::OBJ_TRUE.clone()
-            } else {
-                object::OBJ_FALSE.clone()
-            })
+        (ObjectValue::String { value: v1 }, ObjectValue::String { value: v2 }) => {
+            Ok(bool_obj(v1 > v2))
         }
         (_, _) => Err(format!(
             "Runtime Error: function gt does not accept type {} and {}.",
@@ -167,19 +136,10 @@ pub fn std_add(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
     } else {
         let lhs = &args[0];
         let rhs = &args[1];
+        if let Some(result) = number::binary("add", lhs, rhs) {
+            return result;
+        }
         match (lhs.get_value(), rhs.get_value()) {
-            (ObjectValue::Integer { value: v1 }, ObjectValue::Integer { value: v2 }) => {
-                Ok(object::create_integer(lhs.get_typ(), v1 + v2))
-            }
-            (ObjectValue::Integer { value: v1 }, ObjectValue::Float { value: v2 }) => {
-                Ok(object::create_float(lhs.get_typ(), f64::from(*v1) + v2))
-            }
-            (ObjectValue::Float { value: v1 }, ObjectValue::Integer { value: v2 }) => {
-                Ok(object::create_float(lhs.get_typ(), v1 + f64::from(*v2)))
-            }
-            (ObjectValue::Float { value: v1 }, ObjectValue::Float { value: v2 }) => {
-                Ok(object::create_float(lhs.get_typ(), v1 + v2))
-            }
             // 字符串
             (ObjectValue::String { value: v1 }, ObjectValue::Unit) => {
                 Ok(object::create_string(format!("{}{}", v1, rhs.to_string())))
@@ -190,11 +150,10 @@ pub fn std_add(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
             (ObjectValue::String { value: v1 }, ObjectValue::Boolean { value }) => {
                 Ok(object::create_string(format!("{}{}", v1, value)))
             }
-            (ObjectValue::String { value: v1 }, ObjectValue::Integer { value: v2 }) => {
-                Ok(object::create_string(format!("{}{}", v1, v2)))
-            }
-            (ObjectValue::String { value: v1 }, ObjectValue::Float { value: v2 }) => {
-                Ok(object::create_string(format!("{}{}", v1, v2)))
+            (ObjectValue::String { value: v1 }, ObjectValue::Integer { value: _ })
+            | (ObjectValue::String { value: v1 }, ObjectValue::Unsigned { value: _ })
+            | (ObjectValue::String { value: v1 }, ObjectValue::Float { value: _ }) => {
+                Ok(object::create_string(format!("{}{}", v1, rhs.to_string())))
             }
             (ObjectValue::String { value: v1 }, ObjectValue::String { value: v2 }) => {
                 Ok(object::create_string(format!("{}{}", v1, v2)))
@@ -205,15 +164,20 @@ pub fn std_add(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
             (ObjectValue::Null, ObjectValue::String { value: v1 }) => {
                 Ok(object::create_string(format!("{}{}", lhs.to_string(), v1)))
             }
-            (ObjectValue::Integer { value: v1 }, ObjectValue::String { value: v2 }) => {
-                Ok(object::create_string(format!("{}{}", v1, v2)))
-            }
-            (ObjectValue::Float { value: v1 }, ObjectValue::String { value: v2 }) => {
-                Ok(object::create_string(format!("{}{}", v1, v2)))
+            (ObjectValue::Integer { value: _ }, ObjectValue::String { value: v2 })
+            | (ObjectValue::Unsigned { value: _ }, ObjectValue::String { value: v2 })
+            | (ObjectValue::Float { value: _ }, ObjectValue::String { value: v2 }) => {
+                Ok(object::create_string(format!("{}{}", lhs.to_string(), v2)))
             }
             (ObjectValue::Boolean { value: v1 }, ObjectValue::String { value }) => {
                 Ok(object::create_string(format!("{}{}", v1, value)))
             }
+            // 数组拼接，得到新的数组
+            (ObjectValue::Array { value: v1 }, ObjectValue::Array { value: v2 }) => {
+                let mut value = v1.borrow().clone();
+                value.extend(v2.borrow().iter().cloned());
+                Ok(object::create_array(value))
+            }
             (_, _) => Err(format!(
                 "Runtime Error: function add does not accept type {} and {}.",
                 lhs.get_typ(),
@@ -225,34 +189,7 @@ pub fn std_add(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
 
 // -
 pub fn std_sub(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
-    if args.len() != 2 {
-        return Err(format!(
-            "Runtime Error: Expected 2 arguments, get {}",
-            args.len()
-        ));
-    } else {
-        let lhs = &args[0];
-        let rhs = &args[1];
-        match (lhs.get_value(), rhs.get_value()) {
-            (ObjectValue::Integer { value: v1 }, ObjectValue::Integer { value: v2 }) => {
-                Ok(object::create_integer(lhs.get_typ(), v1 - v2))
-            }
-            (ObjectValue::Integer { value: v1 }, ObjectValue::Float { value: v2 }) => {
-                Ok(object::create_float(lhs.get_typ(), f64::from(*v1) - v2))
-            }
-            (ObjectValue::Float { value: v1 }, ObjectValue::Integer { value: v2 }) => {
-                Ok(object::create_float(lhs.get_typ(), v1 - f64::from(*v2)))
-            }
-            (ObjectValue::Float { value: v1 }, ObjectValue::Float { value: v2 }) => {
-                Ok(object::create_float(lhs.get_typ(), v1 - v2))
-            }
-            (_, _) => Err(format!(
-                "Runtime Error: function sub does not accept type {} and {}.",
-                lhs.get_typ(),
-                rhs.get_typ()
-            )),
-        }
-    }
+    std_number("sub", args)
 }
 
 // *
@@ -265,18 +202,19 @@ pub fn std_mul(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
     } else {
         let lhs = &args[0];
         let rhs = &args[1];
+        if let Some(result) = number::binary("mul", lhs, rhs) {
+            return result;
+        }
         match (lhs.get_value(), rhs.get_value()) {
-            (ObjectValue::Integer { value: v1 }, ObjectValue::Integer { value: v2 }) => {
-                Ok(object::create_integer(lhs.get_typ(), v1 * v2))
-            }
-            (ObjectValue::Integer { value: v1 }, ObjectValue::Float { value: v2 }) => {
-                Ok(object::create_float(lhs.get_typ(), f64::from(*v1) * v2))
-            }
-            (ObjectValue::Float { value: v1 }, ObjectValue::Integer { value: v2 }) => {
-                Ok(object::create_float(lhs.get_typ(), v1 * f64::from(*v2)))
-            }
-            (ObjectValue::Float { value: v1 }, ObjectValue::Float { value: v2 }) => {
-                Ok(object::create_float(lhs.get_typ(), v1 * v2))
+            // 字符串重复
+            (ObjectValue::String { value: v1 }, ObjectValue::Integer { value: v2 }) => {
+                if *v2 < 0 {
+                    return Err(format!(
+                        "Runtime Error: cannot repeat a string {} times.",
+                        v2
+                    ));
+                }
+                Ok(object::create_string(v1.repeat(*v2 as usize)))
             }
             (_, _) => Err(format!(
                 "Runtime Error: function mul does not accept type {} and {}.",
@@ -289,37 +227,143 @@ pub fn std_mul(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
 
 // /
 pub fn std_div(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
-    if args.len() != 2 {
-        return Err(format!(
-            "Runtime Error: Expected 2 arguments, get {}",
-            args.len()
-        ));
-    } else {
-        let lhs = &args[0];
-        let rhs = &args[1];
-        match (lhs.get_value(), rhs.get_value()) {
-            (ObjectValue::Integer { value: v1 }, ObjectValue::Integer { value: v2 }) => {
-                Ok(object::create_integer(lhs.get_typ(), v1 / v2))
-            }
-            (ObjectValue::Integer { value: v1 }, ObjectValue::Float { value: v2 }) => {
-                Ok(object::create_float(lhs.get_typ(), f64::from(*v1) / v2))
-            }
-            (ObjectValue::Float { value: v1 }, ObjectValue::Integer { value: v2 }) => {
-                Ok(object::create_float(lhs.get_typ(), v1 / f64::from(*v2)))
-            }
-            (ObjectValue::Float { value: v1 }, ObjectValue::Float { value: v2 }) => {
-                Ok(object::create_float(lhs.get_typ(), v1 / v2))
+    std_number("div", args)
+}
+
+// 数值之间的二元运算，其余类型由调用者处理
+fn std_number(name: &str, args: &[WesoObject]) -> Result<WesoObject, String> {
+    expect_args(args, 2)?;
+    let lhs = &args[0];
+    let rhs = &args[1];
+    match number::binary(name, lhs, rhs) {
+        Some(result) => result,
+        None => Err(format!(
+            "Runtime Error: function {} does not accept type {} and {}.",
+            name,
+            lhs.get_typ(),
+            rhs.get_typ()
+        )),
+    }
+}
+
+// %
+pub fn std_mod(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    std_number("mod", args)
+}
+
+// **
+pub fn std_pow(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    std_number("pow", args)
+}
+
+// 位运算，布尔值之间也可以进行与、或、异或
+fn std_bit(
+    name: &str,
+    args: &[WesoObject],
+    bool_op: fn(bool, bool) -> bool,
+) -> Result<WesoObject, String> {
+    expect_args(args, 2)?;
+    match (args[0].get_value(), args[1].get_value()) {
+        (ObjectValue::Boolean { value: v1 }, ObjectValue::Boolean { value: v2 }) => {
+            Ok(bool_obj(bool_op(*v1, *v2)))
+        }
+        (_, _) => std_number(name, args),
+    }
+}
+
+// &
+pub fn std_bitand(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    std_bit("bitand", args, |a, b| a & b)
+}
+
+// |
+pub fn std_bitor(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    std_bit("bitor", args, |a, b| a | b)
+}
+
+// ^
+pub fn std_bitxor(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    std_bit("bitxor", args, |a, b| a ^ b)
+}
+
+// <<
+pub fn std_lshift(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    std_number("lshift", args)
+}
+
+// >>
+pub fn std_rshift(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    std_number("rshift", args)
+}
+
+// 数值的一元运算
+fn std_unary(name: &str, args: &[WesoObject]) -> Result<WesoObject, String> {
+    expect_args(args, 1)?;
+    let obj = &args[0];
+    match number::unary(name, obj) {
+        Some(result) => result,
+        None => Err(format!(
+            "Runtime Error: function {} does not accept type {}.",
+            name,
+            obj.get_typ()
+        )),
+    }
+}
+
+// -x
+pub fn std_neg(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    std_unary("neg", args)
+}
+
+// ~x
+pub fn std_bitnot(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    std_unary("bitnot", args)
+}
+
+// x in xs，数组和元组中查找元素，字符串中查找子串，结构体中查找字段，map中查找键
+pub fn std_inside(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    expect_args(args, 2)?;
+    let lhs = &args[0];
+    let rhs = &args[1];
+    let found = match (lhs.get_value(), rhs.get_value()) {
+        (_, ObjectValue::Array { value }) | (_, ObjectValue::Tuple { value }) => value
+            .borrow()
+            .iter()
+            .any(|obj| obj.get_typ() == lhs.get_typ() && obj.get_value() == lhs.get_value()),
+        (ObjectValue::String { value: v1 }, ObjectValue::String { value: v2 }) => {
+            v2.contains(v1.as_str())
+        }
+        (ObjectValue::String { value: _ }, ObjectValue::Struct { value: _ }) => {
+            rhs.has_attr(&lhs.to_string())
+        }
+        (_, ObjectValue::Map { value }) => value.borrow().contains(lhs)?,
+        (_, ObjectValue::Range { value }) if lhs.is_integer() => {
+            match Number::of(lhs).and_then(Number::to_i128) {
+                Some(v) => value.contains(v),
+                None => false,
             }
-            (_, _) => Err(format!(
-                "Runtime Error: function div does not accept type {} and {}.",
+        }
+        (_, _) => {
+            return Err(format!(
+                "Runtime Error: function inside does not accept type {} and {}.",
                 lhs.get_typ(),
                 rhs.get_typ()
-            )),
+            ))
         }
-    }
+    };
+    Ok(bool_obj(found))
 }
 
 // 内建函数库
+pub fn std_assert(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    for arg in args {
+        if !Arc::ptr_eq(arg, &object::OBJ_TRUE) {
+            return Err(String::from("Assert Error: assertion failed."));
+        }
+    }
+    Ok(object::OBJ_UNIT.clone())
+}
+
 pub fn std_print(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
     for arg in args {
         print!("{}", arg.to_string());
@@ -348,3 +392,234 @@ pub fn std_log(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
     }
     Ok(object::OBJ_UNIT.clone())
 }
+
+// 数组和元组
+fn expect_args(args: &[WesoObject], num: usize) -> Result<(), String> {
+    if args.len() != num {
+        return Err(format!(
+            "Runtime Error: Expected {} arguments, get {}",
+            num,
+            args.len()
+        ));
+    }
+    Ok(())
+}
+
+// 对数组的元素列表进行操作
+fn with_array<T>(
+    name: &str,
+    obj: &WesoObject,
+    f: impl FnOnce(&mut Vec<WesoObject>) -> Result<T, String>,
+) -> Result<T, String> {
+    match obj.get_value() {
+        ObjectValue::Array { value } => f(&mut value.borrow_mut()),
+        _ => Err(format!(
+            "Runtime Error: function {} does not accept type {}.",
+            name,
+            obj.get_typ()
+        )),
+    }
+}
+
+// 插入时下标可以等于长度
+fn array_index(obj: &WesoObject, len: usize) -> Result<usize, String> {
+    if !obj.is_integer() {
+        return Err(format!(
+            "Type Error: index should be an integer, found {}.",
+            obj.get_typ()
+        ));
+    }
+    match Number::of(obj).and_then(Number::to_u128) {
+        Some(value) if value <= len as u128 => Ok(value as usize),
+        _ => Err(format!(
+            "Index Error: index {} is out of range for length {}.",
+            obj.to_string(),
+            len
+        )),
+    }
+}
+
+pub fn std_array(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    Ok(object::create_array(args.clone()))
+}
+
+// map字面量 {k1: v1, k2: v2} 转换成 map(k1, v1, k2, v2)
+pub fn std_map(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    if !args.len().is_multiple_of(2) {
+        return Err(format!(
+            "Runtime Error: Expected even arguments, get {}",
+            args.len()
+        ));
+    }
+    let entries = args
+        .chunks(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect();
+    object::create_map(entries)
+}
+
+// 范围 a..b 转换成 range(a, b)，a..=b 转换成 rangeeq(a, b)，省略的边界为null
+fn std_bounds(args: &[WesoObject], inclusive: bool) -> Result<WesoObject, String> {
+    expect_args(args, 2)?;
+    let mut bounds = vec![];
+    let mut types = vec![];
+    for arg in args {
+        if arg.get_typ().is_null() {
+            bounds.push(None);
+            continue;
+        }
+        match Number::of(arg).and_then(Number::to_i128) {
+            Some(v) if arg.is_integer() => bounds.push(Some(v)),
+            _ => {
+                return Err(format!(
+                    "Type Error: range bound should be an integer, found {}.",
+                    arg.get_typ()
+                ))
+            }
+        }
+        types.push(arg.get_typ().clone());
+    }
+    // 范围中整数的类型由边界决定，没有边界时为i32
+    let item = match types.as_slice() {
+        [] => NewTypeKind::name("i32"),
+        [typ] => typ.clone(),
+        [t1, t2] => NewTypeKind::widen(t1, t2).unwrap_or_else(|| t1.clone()),
+        _ => unreachable!(),
+    };
+    let range = WesoRange::new(bounds[0], bounds[1], inclusive);
+    Ok(object::create_range(range, item))
+}
+
+pub fn std_range(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    std_bounds(args, false)
+}
+
+pub fn std_rangeeq(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    std_bounds(args, true)
+}
+
+// 带步长的范围 step(0..10, 2)，步长为负数时递减 step(10..0, -1)
+pub fn std_step(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    expect_args(args, 2)?;
+    let step = match Number::of(&args[1]).and_then(Number::to_i128) {
+        Some(v) if args[1].is_integer() => v,
+        _ => {
+            return Err(format!(
+                "Type Error: step should be an integer, found {}.",
+                args[1].get_typ()
+            ))
+        }
+    };
+    if step == 0 {
+        return Err("Runtime Error: step cannot be zero.".to_string());
+    }
+    match args[0].get_value() {
+        ObjectValue::Range { value } => {
+            let mut range = value.clone();
+            range.step = step;
+            let item = args[0].get_typ().range_item().cloned();
+            Ok(object::create_range(
+                range,
+                item.unwrap_or_else(|| NewTypeKind::name("i32")),
+            ))
+        }
+        _ => Err(format!(
+            "Runtime Error: function step does not accept type {}.",
+            args[0].get_typ()
+        )),
+    }
+}
+
+pub fn std_tuple(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    Ok(object::create_tuple(args.clone()))
+}
+
+// 转换成字符串，与打印的结果相同
+pub fn std_str(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    expect_args(args, 1)?;
+    Ok(object::create_string(args[0].to_string()))
+}
+
+pub fn std_len(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    expect_args(args, 1)?;
+    let len = match args[0].get_value() {
+        ObjectValue::Array { value } | ObjectValue::Tuple { value } => value.borrow().len(),
+        ObjectValue::String { value } => value.chars().count(),
+        ObjectValue::Map { value } => value.borrow().len(),
+        _ => {
+            return Err(format!(
+                "Runtime Error: function len does not accept type {}.",
+                args[0].get_typ()
+            ))
+        }
+    };
+    Ok(object::create_integer(
+        &NewTypeKind::name("i32"),
+        len as i128,
+    ))
+}
+
+pub fn std_push(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    expect_args(args, 2)?;
+    with_array("push", &args[0], |value| {
+        value.push(args[1].clone());
+        Ok(object::OBJ_UNIT.clone())
+    })
+}
+
+pub fn std_pop(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    expect_args(args, 1)?;
+    with_array("pop", &args[0], |value| match value.pop() {
+        Some(obj) => Ok(obj),
+        None => Err("Index Error: pop from empty array.".to_string()),
+    })
+}
+
+pub fn std_insert(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    expect_args(args, 3)?;
+    with_array("insert", &args[0], |value| {
+        let index = array_index(&args[1], value.len())?;
+        value.insert(index, args[2].clone());
+        Ok(object::OBJ_UNIT.clone())
+    })
+}
+
+pub fn std_remove(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    expect_args(args, 2)?;
+    // map中删除键，返回键对应的值
+    if let ObjectValue::Map { value } = args[0].get_value() {
+        return match value.borrow_mut().remove(&args[1])? {
+            Some(obj) => Ok(obj),
+            None => Err(format!(
+                "Key Error: key {} is not found.",
+                args[1].to_string()
+            )),
+        };
+    }
+    with_array("remove", &args[0], |value| {
+        let index = array_index(&args[1], value.len())?;
+        if index == value.len() {
+            return Err(format!(
+                "Index Error: index {} is out of range for length {}.",
+                index,
+                value.len()
+            ));
+        }
+        Ok(value.remove(index))
+    })
+}
+
+// 构造错误 error(message) 或者 error(kind, message)
+pub fn std_error(args: &Vec<WesoObject>) -> Result<WesoObject, String> {
+    let (kind, message) = match args.as_slice() {
+        [message] => ("User".to_string(), message.to_string()),
+        [kind, message] => (kind.to_string(), message.to_string()),
+        _ => {
+            return Err(format!(
+                "Runtime Error: Expected 1 or 2 arguments, get {}",
+                args.len()
+            ))
+        }
+    };
+    Ok(object::create_error(&kind, &message, ""))
+}
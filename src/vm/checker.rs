@@ -0,0 +1,2901 @@
+// 静态类型检查，在生成指令之前遍历语法树
+use crate::base::ast::{
+    Expression, ExpressionKind, Located, MatchArm, Pattern, Statement, StatementKind, StmtList,
+};
+use crate::base::func;
+use crate::base::number;
+use crate::base::object::WesoObject;
+use crate::base::types::{NewTypeKind, TypeScope};
+use crate::parser::lexer::Location;
+use crate::parser::liter::Literal;
+use crate::vm::global::WesoSTD;
+use crate::vm::pattern;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+pub type TypeError = Located<String>;
+
+// 函数签名：参数类型和返回值类型
+type FuncSign = (Vec<NewTypeKind>, NewTypeKind);
+
+// 检查时记录的变量信息
+struct VarInfo {
+    typ: NewTypeKind,
+    mutable: bool,
+    assigned: bool, // 常量只允许赋值一次
+    init: bool,     // 定义时是否有初始值
+    param: bool,    // 函数参数不能重新赋值，但是可以修改内容
+}
+
+// 检查时使用的作用域
+struct Frame {
+    types: Rc<RefCell<TypeScope>>,
+    vars: HashMap<String, VarInfo>,
+    funcs: HashMap<String, Vec<FuncSign>>,
+    // 本块中顺序执行时已经被转移的变量
+    moved: HashSet<String>,
+    // 函数体的作用域，外层的转移不影响函数体
+    function: bool,
+    // 本块中顺序执行时已经确定赋值的变量
+    inited: HashSet<String>,
+    // 本块中已经确定不为null的可选类型变量
+    narrowed: HashSet<String>,
+    // 导入的模块 别名->导出的定义
+    modules: HashMap<String, Rc<Exports>>,
+    // 本块中定义的所有名称，函数体中可以使用外层块之后才定义的名称
+    declared: HashSet<String>,
+}
+
+// 模块导出的定义，变量和函数通过模块的别名访问
+// 模块中的类型已经改用唯一的名称，全部引入导入者，类型的方法随类型一起引入
+#[derive(Default)]
+pub struct Exports {
+    vars: HashMap<String, NewTypeKind>,
+    funcs: HashMap<String, Vec<FuncSign>>,
+    // 模块最外层的所有类型，包括没有导出的，导出的函数可能使用它们
+    types: Vec<String>,
+    methods: HashMap<String, Vec<FuncSign>>,
+    // 模块最外层的类型作用域
+    scope: Option<Rc<RefCell<TypeScope>>>,
+}
+
+impl Exports {
+    // 将模块的类型引入types，已经定义过的是从其他路径引入的同一个类型
+    pub fn import_types(&self, types: &mut TypeScope) {
+        for key in self.types.iter() {
+            if let Some(scope) = &self.scope {
+                if !types.is_defined(key) {
+                    types.import(&scope.borrow(), key);
+                }
+            }
+        }
+    }
+}
+
+pub struct Checker {
+    frames: Vec<Frame>,
+
+    // 已经检查过的模块 path->导出的定义
+    modules: HashMap<String, Rc<Exports>>,
+
+    // 当前所在函数的返回值类型
+    rettyps: Vec<NewTypeKind>,
+
+    // 在函数体中被赋值的外层变量，调用函数后之前的检查失效
+    captured: HashSet<String>,
+
+    errors: Vec<TypeError>,
+}
+
+// 检查整个模块，modules为可以导入的模块，返回模块导出的定义或者所有的类型错误
+pub fn check_stmts(
+    stmts: &StmtList,
+    modules: &HashMap<String, Rc<Exports>>,
+) -> Result<Exports, Vec<TypeError>> {
+    let mut checker = Checker::new();
+    checker.modules = modules.clone();
+    captured_vars(stmts, &mut checker.captured);
+    checker.check_block(stmts);
+    if checker.errors.is_empty() {
+        Ok(checker.exports(stmts))
+    } else {
+        Err(checker.errors)
+    }
+}
+
+fn any() -> NewTypeKind {
+    NewTypeKind::name("any")
+}
+
+fn show_types(types: &[NewTypeKind]) -> String {
+    types
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// 修改集合内容的内建函数，第一个参数是被修改的集合
+fn is_mutator(name: &str) -> bool {
+    matches!(name, "push" | "pop" | "insert" | "remove")
+}
+
+// 运算符对应的内建函数
+fn is_operator(name: &str) -> bool {
+    matches!(
+        name,
+        "neg"
+            | "bitnot"
+            | "lt"
+            | "gt"
+            | "leq"
+            | "geq"
+            | "inside"
+            | "range"
+            | "rangeeq"
+            | "add"
+            | "sub"
+            | "mul"
+            | "div"
+            | "mod"
+            | "pow"
+            | "bitand"
+            | "bitor"
+            | "bitxor"
+            | "lshift"
+            | "rshift"
+    )
+}
+
+// 语句块是否一定会跳出，之后的语句不会执行
+fn diverges(stmts: &StmtList) -> bool {
+    stmts.iter().any(|stmt| match &stmt.node {
+        StatementKind::Return { .. }
+        | StatementKind::Break
+        | StatementKind::Continue
+        | StatementKind::Throw { .. } => true,
+        StatementKind::If { then, orelse, .. } => diverges(then) && diverges(orelse),
+        StatementKind::Try { then, catch, .. } => diverges(then) && diverges(catch),
+        // 没有break的 while true 不会执行到之后的语句
+        StatementKind::While { test, then } => {
+            matches!(
+                &test.node,
+                ExpressionKind::Liter {
+                    value: Literal::True
+                }
+            ) && !breaks(then)
+        }
+        _ => false,
+    })
+}
+
+// 语句块中是否有跳出当前循环的break，内层循环中的break不算
+fn breaks(stmts: &StmtList) -> bool {
+    stmts.iter().any(|stmt| match &stmt.node {
+        StatementKind::Break => true,
+        StatementKind::If { then, orelse, .. } => breaks(then) || breaks(orelse),
+        StatementKind::Try { then, catch, .. } => breaks(then) || breaks(catch),
+        _ => false,
+    })
+}
+
+// 语句块中定义的名称，包括变量、函数和解构绑定的变量
+fn declared_names(stmts: &StmtList) -> Vec<String> {
+    let mut names = vec![];
+    for stmt in stmts {
+        match &stmt.definition().node {
+            StatementKind::Destruct { pattern, .. } => names.extend(pattern.bindings()),
+            StatementKind::Import { name, .. } => names.push(name.clone()),
+            _ => names.extend(stmt.defined_name().cloned()),
+        }
+    }
+    names
+}
+
+// 变量或者变量的属性 a.b.c 可以检查是否为null，返回检查的路径
+fn null_path(expr: &Expression) -> Option<String> {
+    match &expr.node {
+        ExpressionKind::Ident { value } => Some(value.clone()),
+        ExpressionKind::Dot { left, right } => match &right.node {
+            ExpressionKind::Ident { value } => Some(format!("{}.{}", null_path(left)?, value)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// 路径path是否是name本身或者name的属性
+fn within(path: &str, name: &str) -> bool {
+    path.strip_prefix(name)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+// 赋值的目标，变量为变量名，属性为 .属性名
+// 其他别名可能指向同一个对象，所以属性的赋值与通过哪个变量访问无关
+fn assigned_path(target: &Expression) -> Option<String> {
+    match &target.node {
+        ExpressionKind::Ident { value } => Some(value.clone()),
+        ExpressionKind::Dot { right, .. } => match &right.node {
+            ExpressionKind::Ident { value } => Some(format!(".{}", value)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// 对assigned的赋值是否使路径path的检查失效，属性的赋值使所有经过同名属性的路径失效
+fn invalidates(assigned: &str, path: &str) -> bool {
+    match assigned.strip_prefix('.') {
+        Some(field) => path.split('.').skip(1).any(|name| name == field),
+        None => within(path, assigned),
+    }
+}
+
+// 条件中检查过不为null的变量和属性，分别在条件为真和为假时成立
+fn null_checks(expr: &Expression) -> (Vec<String>, Vec<String>) {
+    match &expr.node {
+        ExpressionKind::Call { callee, args } if args.len() == 2 => {
+            let names = match (&args[0].node, &args[1].node) {
+                (
+                    _,
+                    ExpressionKind::Liter {
+                        value: Literal::Null,
+                    },
+                ) => null_path(&args[0]).into_iter().collect(),
+                (
+                    ExpressionKind::Liter {
+                        value: Literal::Null,
+                    },
+                    _,
+                ) => null_path(&args[1]).into_iter().collect(),
+                _ => vec![],
+            };
+            match &callee.node {
+                ExpressionKind::Ident { value } if value == "noteq" || value == "neq" => {
+                    (names, vec![])
+                }
+                ExpressionKind::Ident { value } if value == "equal" => (vec![], names),
+                _ => (vec![], vec![]),
+            }
+        }
+        ExpressionKind::And { left, right } => {
+            let mut names = null_checks(left).0;
+            names.extend(null_checks(right).0);
+            (names, vec![])
+        }
+        ExpressionKind::Or { left, right } => {
+            let mut names = null_checks(left).1;
+            names.extend(null_checks(right).1);
+            (vec![], names)
+        }
+        ExpressionKind::Not { expr } => {
+            let (yes, no) = null_checks(expr);
+            (no, yes)
+        }
+        _ => (vec![], vec![]),
+    }
+}
+
+// 收集语句中直接赋值的变量和属性，包括嵌套的语句块和函数
+fn assigned_vars(stmts: &[Statement], names: &mut HashSet<String>) {
+    collect_assigned(stmts, names, false);
+}
+
+// 收集函数体中赋值的外层变量，不包括函数的参数和函数体中定义的变量
+fn captured_vars(stmts: &[Statement], names: &mut HashSet<String>) {
+    collect_assigned(stmts, names, true);
+}
+
+// captured为true时只收集函数体中的赋值
+fn collect_assigned(stmts: &[Statement], names: &mut HashSet<String>, captured: bool) {
+    for stmt in stmts {
+        match &stmt.node {
+            StatementKind::Assign { left, right }
+            | StatementKind::Move { left, right }
+            | StatementKind::AugAssign { left, right, .. } => {
+                // 属性的赋值也会使之前对属性的检查失效
+                if let Some(path) = assigned_path(left).filter(|_| !captured) {
+                    names.insert(path);
+                }
+                expr_assigned(left, names, captured);
+                expr_assigned(right, names, captured);
+            }
+            StatementKind::Return { value }
+            | StatementKind::Throw { value }
+            | StatementKind::Expression { expr: value } => expr_assigned(value, names, captured),
+            StatementKind::VarDef {
+                assign: Some(value),
+                ..
+            }
+            | StatementKind::Destruct { value, .. } => expr_assigned(value, names, captured),
+            StatementKind::FuncDef { args, block, .. } => {
+                func_assigned(args, block, names, captured)
+            }
+            StatementKind::Impl { methods, .. } => collect_assigned(methods, names, captured),
+            StatementKind::If { test, then, orelse } => {
+                expr_assigned(test, names, captured);
+                collect_assigned(then, names, captured);
+                collect_assigned(orelse, names, captured);
+            }
+            StatementKind::While { test, then }
+            | StatementKind::For {
+                iter: test, then, ..
+            } => {
+                expr_assigned(test, names, captured);
+                collect_assigned(then, names, captured);
+            }
+            StatementKind::Try { then, catch, .. } => {
+                collect_assigned(then, names, captured);
+                collect_assigned(catch, names, captured);
+            }
+            StatementKind::Export { stmt } => {
+                collect_assigned(std::slice::from_ref(stmt), names, captured)
+            }
+            _ => {}
+        }
+    }
+}
+
+// 函数体中的赋值，captured为true时去掉函数的参数和函数体中定义的变量
+fn func_assigned(
+    args: &[(String, NewTypeKind)],
+    block: &StmtList,
+    names: &mut HashSet<String>,
+    captured: bool,
+) {
+    let mut assigned = HashSet::new();
+    collect_assigned(block, &mut assigned, false);
+    if captured {
+        let locals = declared_names(block);
+        assigned.retain(|name| {
+            !name.starts_with('.')
+                && !locals.contains(name)
+                && !args.iter().any(|(arg, _)| arg == name)
+        });
+    }
+    names.extend(assigned);
+}
+
+fn expr_assigned(expr: &Expression, names: &mut HashSet<String>, captured: bool) {
+    match &expr.node {
+        ExpressionKind::Block { block } => collect_assigned(block, names, captured),
+        ExpressionKind::Function { args, block, .. } => func_assigned(args, block, names, captured),
+        ExpressionKind::Dot { left, right }
+        | ExpressionKind::Index { left, right }
+        | ExpressionKind::And { left, right }
+        | ExpressionKind::Or { left, right } => {
+            expr_assigned(left, names, captured);
+            expr_assigned(right, names, captured);
+        }
+        ExpressionKind::Call { callee, args } => {
+            expr_assigned(callee, names, captured);
+            for arg in args {
+                expr_assigned(arg, names, captured);
+            }
+        }
+        ExpressionKind::Not { expr } => expr_assigned(expr, names, captured),
+        ExpressionKind::Struct { args, .. } => {
+            for (_, arg) in args {
+                expr_assigned(arg, names, captured);
+            }
+        }
+        ExpressionKind::Question { test, then, orelse } => {
+            for item in [test, then, orelse] {
+                expr_assigned(item, names, captured);
+            }
+        }
+        ExpressionKind::Match { value, arms } => {
+            expr_assigned(value, names, captured);
+            for arm in arms {
+                if let Some(guard) = &arm.node.guard {
+                    expr_assigned(guard, names, captured);
+                }
+                expr_assigned(&arm.node.body, names, captured);
+            }
+        }
+        ExpressionKind::Liter { .. } | ExpressionKind::Ident { .. } => {}
+    }
+}
+
+// 数值运算的结果类型，不同的数值类型按照widen的规则提升
+fn arith_type(lhs: &NewTypeKind, rhs: &NewTypeKind) -> Option<NewTypeKind> {
+    NewTypeKind::widen(lhs, rhs)
+}
+
+// 没有后缀的数值字面量，它的类型可以由使用的位置决定
+fn is_untyped(literal: &Literal) -> bool {
+    match literal {
+        Literal::Integer(s) => !s.contains(['i', 'u']),
+        Literal::Float(s) => !s.contains('f'),
+        _ => false,
+    }
+}
+
+// 没有后缀的数值字面量可以作为同类的其他数值类型，例如 let a: u8 = 1;
+// 类型不是同类的数值时返回None，超出范围时返回错误
+fn literal_as(literal: &Literal, typ: &NewTypeKind) -> Option<Result<NewTypeKind, String>> {
+    let value = match literal {
+        Literal::Integer(s) if typ.is_integer() => number::int_literal(s),
+        Literal::Float(s) if typ.is_float() => number::float_literal(s),
+        _ => return None,
+    };
+    Some(
+        value
+            .and_then(|obj| number::cast(&obj, typ))
+            .map(|_| typ.clone()),
+    )
+}
+
+// 没有后缀的数值字面量在范围内时作为期望的类型，否则保持原来的类型
+fn fit_literal(
+    literal: Option<&Literal>,
+    typ: &NewTypeKind,
+    expected: &NewTypeKind,
+) -> NewTypeKind {
+    match literal.and_then(|literal| literal_as(literal, expected.non_null())) {
+        Some(Ok(expected)) => expected,
+        _ => typ.clone(),
+    }
+}
+
+// 实参中的字面量按照对应形参的类型解释
+fn fit_args(
+    params: &[NewTypeKind],
+    args: &[NewTypeKind],
+    literals: &[Option<Literal>],
+) -> Vec<NewTypeKind> {
+    args.iter()
+        .enumerate()
+        .map(|(i, arg)| match params.get(i) {
+            Some(param) => fit_literal(literals.get(i).and_then(Option::as_ref), arg, param),
+            None => arg.clone(),
+        })
+        .collect()
+}
+
+// 内建函数中的字面量按照元素或者键的类型解释，例如 push(a, 1)
+// 运算符的结果类型由操作数决定，所以 a + 1 中的字面量仍然是i32
+fn fit_builtin(name: &str, args: &[NewTypeKind], literals: &[Option<Literal>]) -> Vec<NewTypeKind> {
+    let mut params = args.to_vec();
+    match (name, args) {
+        ("push", [NewTypeKind::Array(item), _]) => params[1] = *item.clone(),
+        ("insert", [NewTypeKind::Array(item), _, _]) => params[2] = *item.clone(),
+        ("remove", [NewTypeKind::Map(key, _), _]) => params[1] = *key.clone(),
+        ("inside", [_, NewTypeKind::Map(key, _)]) => params[0] = *key.clone(),
+        _ => return args.to_vec(),
+    }
+    fit_args(&params, args, literals)
+}
+
+// 内建函数的返回值类型，None表示不是内建函数
+fn builtin_type(name: &str, args: &[NewTypeKind]) -> Option<Result<NewTypeKind, String>> {
+    match name {
+        "print" | "println" | "log" | "assert" => return Some(Ok(NewTypeKind::name("unit"))),
+        "array" => return Some(Ok(NewTypeKind::Array(Box::new(NewTypeKind::common(args))))),
+        "tuple" => return Some(Ok(NewTypeKind::Tuple(args.to_vec()))),
+        "map" => return Some(map_type(args)),
+        "range" | "rangeeq" => return Some(range_type(name, args)),
+        "step" => return Some(step_type(args)),
+        "str" if args.len() != 1 => {
+            return Some(Err(format!(
+                "Type Error: function str expects 1 arguments, found {}.",
+                args.len()
+            )))
+        }
+        "str" => return Some(Ok(NewTypeKind::name("str"))),
+        "len" | "push" | "pop" | "insert" | "remove" => {
+            return Some(array_builtin_type(name, args))
+        }
+        "neg" | "bitnot" => return Some(unary_type(name, args)),
+        "error" => return Some(error_type(args)),
+        "equal" | "neq" | "noteq" | "lt" | "gt" | "leq" | "geq" | "inside" | "add" | "sub"
+        | "mul" | "div" | "mod" | "pow" | "bitand" | "bitor" | "bitxor" | "lshift" | "rshift" => (),
+        _ => return None,
+    };
+    if args.len() != 2 {
+        return Some(Err(format!(
+            "Type Error: function {} expects 2 arguments, found {}.",
+            name,
+            args.len()
+        )));
+    }
+    let (lhs, rhs) = (&args[0], &args[1]);
+    let bool_typ = NewTypeKind::name("bool");
+    let str_typ = NewTypeKind::name("str");
+    let result = match name {
+        "equal" | "neq" | "noteq" => Some(bool_typ),
+        "inside" => match rhs {
+            NewTypeKind::Array(_) | NewTypeKind::Tuple(_) => Some(bool_typ),
+            NewTypeKind::Map(key, _) if NewTypeKind::is_compatible(key, lhs) => Some(bool_typ),
+            // 判断整数是否在范围中
+            _ if rhs.range_item().is_some() && (lhs.is_integer() || lhs.is_any()) => Some(bool_typ),
+            _ if rhs.is_any() => Some(bool_typ),
+            // 字符串中查找子串，结构体中查找字段
+            NewTypeKind::Named(_) if *rhs == str_typ || !rhs.is_builtin() => {
+                if NewTypeKind::is_compatible(&str_typ, lhs) {
+                    Some(bool_typ)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        },
+        _ if lhs.is_any() || rhs.is_any() => match name {
+            "lt" | "gt" | "leq" | "geq" => Some(bool_typ),
+            _ => Some(any()),
+        },
+        "lt" | "gt" | "leq" | "geq" => {
+            if arith_type(lhs, rhs).is_some()
+                || (*lhs == bool_typ && *rhs == bool_typ)
+                || (*lhs == str_typ && *rhs == str_typ)
+            {
+                Some(bool_typ)
+            } else {
+                None
+            }
+        }
+        "add" => match (lhs, rhs) {
+            // 数组拼接
+            (NewTypeKind::Array(t), NewTypeKind::Array(s)) => {
+                if t == s {
+                    Some(lhs.clone())
+                } else {
+                    Some(NewTypeKind::Array(Box::new(any())))
+                }
+            }
+            _ => match arith_type(lhs, rhs) {
+                Some(t) => Some(t),
+                // 字符串可以和基础类型相加
+                None => {
+                    let printable = |t: &NewTypeKind| {
+                        t.is_integer()
+                            || t.is_float()
+                            || *t == str_typ
+                            || *t == bool_typ
+                            || *t == NewTypeKind::name("unit")
+                    };
+                    if (*lhs == str_typ && printable(rhs)) || (*rhs == str_typ && printable(lhs)) {
+                        Some(str_typ)
+                    } else {
+                        None
+                    }
+                }
+            },
+        },
+        // 字符串重复
+        "mul" if *lhs == str_typ && rhs.is_integer() => Some(str_typ),
+        "bitand" | "bitor" | "bitxor" if *lhs == bool_typ && *rhs == bool_typ => Some(bool_typ),
+        "bitand" | "bitor" | "bitxor" if lhs.is_integer() && rhs.is_integer() => {
+            arith_type(lhs, rhs)
+        }
+        // 乘方和移位保持左值的类型
+        "pow" | "lshift" | "rshift" if lhs.is_integer() && rhs.is_integer() => Some(lhs.clone()),
+        "bitand" | "bitor" | "bitxor" | "lshift" | "rshift" => None,
+        _ => arith_type(lhs, rhs),
+    };
+    Some(result.ok_or_else(|| {
+        format!(
+            "Type Error: function {} does not accept type {} and {}.",
+            name, lhs, rhs
+        )
+    }))
+}
+
+// map字面量的类型，参数依次是键和值，键需要能够哈希
+fn map_type(args: &[NewTypeKind]) -> Result<NewTypeKind, String> {
+    if !args.len().is_multiple_of(2) {
+        return Err(format!(
+            "Type Error: function map expects even arguments, found {}.",
+            args.len()
+        ));
+    }
+    let keys = args
+        .iter()
+        .step_by(2)
+        .cloned()
+        .collect::<Vec<NewTypeKind>>();
+    let values = args
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .cloned()
+        .collect::<Vec<NewTypeKind>>();
+    if let Some(key) = keys.iter().find(|key| !key.is_hashable()) {
+        return Err(format!("Type Error: {} cannot be used as a map key.", key));
+    }
+    Ok(NewTypeKind::Map(
+        Box::new(NewTypeKind::common(&keys)),
+        Box::new(NewTypeKind::common(&values)),
+    ))
+}
+
+// 范围的类型 range<T>，边界需要是整数，省略的边界为null
+// 整数的类型由边界决定，两个边界的类型不同时取能容纳两者的类型
+fn range_type(name: &str, args: &[NewTypeKind]) -> Result<NewTypeKind, String> {
+    if args.len() != 2 {
+        return Err(format!(
+            "Type Error: function {} expects 2 arguments, found {}.",
+            name,
+            args.len()
+        ));
+    }
+    let mut item: Option<NewTypeKind> = None;
+    for typ in args.iter().filter(|typ| !typ.is_null()) {
+        if !typ.is_integer() && !typ.is_any() {
+            return Err(format!(
+                "Type Error: range bound should be an integer, found {}.",
+                typ
+            ));
+        }
+        item = Some(match item {
+            Some(t) => NewTypeKind::widen(&t, typ).unwrap_or(t),
+            None => typ.clone(),
+        });
+    }
+    let item = item.unwrap_or_else(|| NewTypeKind::name("i32"));
+    Ok(NewTypeKind::Generic("range".to_string(), vec![item]))
+}
+
+// 带步长的范围 step(0..10, 2)，类型与原来的范围相同
+fn step_type(args: &[NewTypeKind]) -> Result<NewTypeKind, String> {
+    if args.len() != 2 {
+        return Err(format!(
+            "Type Error: function step expects 2 arguments, found {}.",
+            args.len()
+        ));
+    }
+    if args[0].range_item().is_none() && !args[0].is_any() {
+        return Err(format!(
+            "Type Error: function step does not accept type {}.",
+            args[0]
+        ));
+    }
+    if !args[1].is_integer() && !args[1].is_any() {
+        return Err(format!(
+            "Type Error: step should be an integer, found {}.",
+            args[1]
+        ));
+    }
+    Ok(args[0].clone())
+}
+
+// 构造错误 error(message) 和 error(kind, message)，参数都是字符串
+fn error_type(args: &[NewTypeKind]) -> Result<NewTypeKind, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!(
+            "Type Error: function error expects 1 or 2 arguments, found {}.",
+            args.len()
+        ));
+    }
+    let str_typ = NewTypeKind::name("str");
+    match args
+        .iter()
+        .find(|typ| !NewTypeKind::is_compatible(&str_typ, typ))
+    {
+        Some(typ) => Err(format!(
+            "Type Error: function error does not accept type {}.",
+            typ
+        )),
+        None => Ok(NewTypeKind::name("error")),
+    }
+}
+
+// 一元运算 -x 和 ~x 的类型
+fn unary_type(name: &str, args: &[NewTypeKind]) -> Result<NewTypeKind, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Type Error: function {} expects 1 arguments, found {}.",
+            name,
+            args.len()
+        ));
+    }
+    let typ = &args[0];
+    if typ.is_any() || typ.is_integer() || (name == "neg" && typ.is_float()) {
+        Ok(typ.clone())
+    } else {
+        Err(format!(
+            "Type Error: function {} does not accept type {}.",
+            name, typ
+        ))
+    }
+}
+
+// 数组相关内建函数的类型，第一个参数是被操作的数组
+fn array_builtin_type(name: &str, args: &[NewTypeKind]) -> Result<NewTypeKind, String> {
+    let expected = match name {
+        "len" | "pop" => 1,
+        "push" | "remove" => 2,
+        _ => 3,
+    };
+    if args.len() != expected {
+        return Err(format!(
+            "Type Error: function {} expects {} arguments, found {}.",
+            name,
+            expected,
+            args.len()
+        ));
+    }
+    let target = &args[0];
+    let unit = NewTypeKind::name("unit");
+    // map可以获取长度和删除键，删除时返回键对应的值
+    if let NewTypeKind::Map(key, value) = target {
+        return match name {
+            "len" => Ok(NewTypeKind::name("i32")),
+            "remove" if NewTypeKind::is_compatible(key, &args[1]) => Ok(*value.clone()),
+            "remove" => Err(format!(
+                "Type Error: key should be {}, found {}.",
+                key, args[1]
+            )),
+            _ => Err(format!(
+                "Type Error: function {} does not accept type {}.",
+                name, target
+            )),
+        };
+    }
+    let item = match target {
+        NewTypeKind::Array(item) => *item.clone(),
+        _ if target.is_any() => any(),
+        NewTypeKind::Tuple(_) if name == "len" => any(),
+        NewTypeKind::Named(s) if s == "str" && name == "len" => any(),
+        _ => {
+            return Err(format!(
+                "Type Error: function {} does not accept type {}.",
+                name, target
+            ))
+        }
+    };
+    let check_index = |typ: &NewTypeKind| {
+        if typ.is_integer() || typ.is_any() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Type Error: index should be an integer, found {}.",
+                typ
+            ))
+        }
+    };
+    let check_item = |typ: &NewTypeKind| {
+        if NewTypeKind::is_compatible(&item, typ) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Type Error: cannot {} {} into {}.",
+                name, typ, target
+            ))
+        }
+    };
+    match name {
+        "len" => Ok(NewTypeKind::name("i32")),
+        "push" => check_item(&args[1]).map(|_| unit),
+        "insert" => check_index(&args[1])
+            .and_then(|_| check_item(&args[2]))
+            .map(|_| unit),
+        // any也可能是map，键的类型在运行时检查
+        "remove" if target.is_any() => Ok(item.clone()),
+        "remove" => check_index(&args[1]).map(|_| item.clone()),
+        _ => Ok(item.clone()),
+    }
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Checker {
+            frames: vec![Frame {
+                types: Rc::new(RefCell::new(TypeScope::new(None))),
+                vars: HashMap::new(),
+                funcs: HashMap::new(),
+                moved: HashSet::new(),
+                function: false,
+                inited: HashSet::new(),
+                narrowed: HashSet::new(),
+                modules: HashMap::new(),
+                declared: HashSet::new(),
+            }],
+            modules: HashMap::new(),
+            rettyps: vec![any()],
+            captured: HashSet::new(),
+            errors: vec![],
+        }
+    }
+
+    fn error(&mut self, location: &Location, msg: String) {
+        self.errors.push(Located {
+            location: *location,
+            node: msg,
+        });
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn types(&self) -> Rc<RefCell<TypeScope>> {
+        self.frames.last().unwrap().types.clone()
+    }
+
+    // 进入新的块作用域
+    fn enter(&mut self) {
+        let parent = self.types();
+        self.frames.push(Frame {
+            types: Rc::new(RefCell::new(TypeScope::new(Some(parent)))),
+            vars: HashMap::new(),
+            funcs: HashMap::new(),
+            moved: HashSet::new(),
+            function: false,
+            inited: HashSet::new(),
+            narrowed: HashSet::new(),
+            modules: HashMap::new(),
+            declared: HashSet::new(),
+        });
+    }
+
+    fn leave(&mut self) {
+        self.frames.pop();
+    }
+
+    fn define_var(&mut self, name: &str, typ: NewTypeKind, mutable: bool, assigned: bool) {
+        self.frame().moved.remove(name);
+        self.frame().inited.remove(name);
+        self.frame().narrowed.retain(|path| !within(path, name));
+        self.frame().vars.insert(
+            name.to_string(),
+            VarInfo {
+                typ,
+                mutable,
+                assigned,
+                init: assigned,
+                param: false,
+            },
+        );
+    }
+
+    fn define_param(&mut self, name: &str, typ: NewTypeKind) {
+        self.define_var(name, typ, false, true);
+        if let Some(var) = self.frame().vars.get_mut(name) {
+            var.param = true;
+        }
+    }
+
+    // 变量是否确定已经赋值，没有初始值的变量需要在所有分支中都赋值后才能读取
+    fn is_inited(&self, name: &str) -> bool {
+        for frame in self.frames.iter().rev() {
+            if frame.inited.contains(name) {
+                return true;
+            }
+            if let Some(var) = frame.vars.get(name) {
+                return var.init;
+            }
+        }
+        true
+    }
+
+    // 可选类型的变量或者属性是否确定不为null，函数体中不能依赖外层的检查
+    // 属性可能通过其他别名修改，赋值同名属性或者调用函数后属性的检查失效
+    fn is_narrowed(&self, path: &str) -> bool {
+        let root = path.split('.').next().unwrap_or(path);
+        for frame in self.frames.iter().rev() {
+            if frame.narrowed.contains(path) {
+                return true;
+            }
+            if frame.vars.contains_key(root) || frame.function {
+                return false;
+            }
+        }
+        false
+    }
+
+    // 对变量直接赋值，值确定不为null时之后读取变量不需要检查
+    fn assign_var(&mut self, name: &str, value: &NewTypeKind) {
+        self.forget(name);
+        self.frame().inited.insert(name.to_string());
+        if !value.is_optional() && !value.is_null() && !value.is_any() {
+            self.frame().narrowed.insert(name.to_string());
+        }
+    }
+
+    // 变量或者属性可能被重新赋值为null，之前对它和它的属性的检查失效
+    // name为assigned_path返回的赋值目标
+    fn forget(&mut self, name: &str) {
+        for frame in self.frames.iter_mut() {
+            frame.narrowed.retain(|path| !invalidates(name, path));
+        }
+    }
+
+    // 调用的函数可能修改任意对象的属性，以及在函数体中赋值的外层变量
+    fn forget_after_call(&mut self) {
+        let captured = &self.captured;
+        for frame in self.frames.iter_mut() {
+            frame
+                .narrowed
+                .retain(|path| !path.contains('.') && !captured.contains(path));
+        }
+    }
+
+    // 可选类型的值在使用前需要检查不为null
+    fn check_null(&mut self, expr: &Expression, typ: NewTypeKind) -> NewTypeKind {
+        match typ {
+            NewTypeKind::Optional(item) => {
+                let name = match null_path(expr) {
+                    Some(path) => path,
+                    None => format!("value of type {}?", item),
+                };
+                self.error(&expr.location, format!("Type Error: {} may be null.", name));
+                *item
+            }
+            _ => typ,
+        }
+    }
+
+    // 检查分支的语句块，narrowed为分支条件保证不为null的变量
+    // 返回分支中确定赋值的外层变量，分支一定跳出时返回None
+    fn check_branch(&mut self, stmts: &StmtList, narrowed: &[String]) -> Option<HashSet<String>> {
+        self.enter();
+        self.frame().narrowed.extend(narrowed.iter().cloned());
+        self.check_block(stmts);
+        let frame = self.frames.pop().unwrap();
+        if diverges(stmts) {
+            return None;
+        }
+        let vars = frame.vars;
+        Some(
+            frame
+                .inited
+                .into_iter()
+                .filter(|name| !vars.contains_key(name))
+                .collect(),
+        )
+    }
+
+    // 所有没有跳出的分支中都赋值的变量，在分支之后确定已经赋值
+    fn merge_branches(&mut self, branches: Vec<Option<HashSet<String>>>) {
+        let mut branches = branches.into_iter().flatten();
+        if let Some(first) = branches.next() {
+            let common = branches.fold(first, |acc, names| {
+                acc.intersection(&names).cloned().collect()
+            });
+            self.frame().inited.extend(common);
+        }
+    }
+
+    // 循环体中赋值的变量在下一次循环开始时可能为null
+    fn check_loop(&mut self, stmts: &StmtList) {
+        let mut names = HashSet::new();
+        assigned_vars(stmts, &mut names);
+        for name in names.iter() {
+            self.forget(name);
+        }
+    }
+
+    fn lookup_var(&self, name: &str) -> Option<NewTypeKind> {
+        self.frames
+            .iter()
+            .rev()
+            .find_map(|frame| frame.vars.get(name).map(|var| var.typ.clone()))
+    }
+
+    // 名称是否已经定义，包括内建函数
+    // 函数体在调用时才执行，可以使用外层块中之后才定义的名称
+    fn is_defined(&self, name: &str) -> bool {
+        let mut outer = false;
+        for frame in self.frames.iter().rev() {
+            if frame.vars.contains_key(name)
+                || frame.funcs.contains_key(name)
+                || frame.modules.contains_key(name)
+                || (outer && frame.declared.contains(name))
+            {
+                return true;
+            }
+            outer = outer || frame.function;
+        }
+        WesoSTD::get_ins().get_func(&name.to_string()).is_some()
+    }
+
+    fn lookup_var_mut(&mut self, name: &str) -> Option<&mut VarInfo> {
+        self.frames
+            .iter_mut()
+            .rev()
+            .find_map(|frame| frame.vars.get_mut(name))
+    }
+
+    // 变量是否确定已经被转移，分支和循环中的转移留给运行时检查
+    fn is_moved(&self, name: &str) -> bool {
+        for frame in self.frames.iter().rev() {
+            if frame.moved.contains(name) {
+                return true;
+            }
+            if frame.vars.contains_key(name) || frame.function {
+                return false;
+            }
+        }
+        false
+    }
+
+    // 重新赋值后变量恢复可用
+    fn revive(&mut self, name: &str) {
+        for frame in self.frames.iter_mut() {
+            frame.moved.remove(name);
+        }
+    }
+
+    // 检查赋值目标是否为常量，a = b 只检查a，a.b = c 和 a[i] = c 不允许通过常量a修改
+    // contents表示修改的是对象的内容，比如push修改数组，参数的内容可以修改
+    fn check_mutable(&mut self, target: &Expression, contents: bool, location: &Location) {
+        let (name, is_attr) = match &target.node {
+            ExpressionKind::Ident { value } => (value, contents),
+            ExpressionKind::Dot { left, right: _ } | ExpressionKind::Index { left, right: _ } => {
+                let mut root = left;
+                while let ExpressionKind::Dot { left, right: _ }
+                | ExpressionKind::Index { left, right: _ } = &root.node
+                {
+                    root = left;
+                }
+                match &root.node {
+                    ExpressionKind::Ident { value } => (value, true),
+                    _ => return,
+                }
+            }
+            _ => return,
+        };
+        let denied = match self.lookup_var_mut(name) {
+            Some(var) if !var.mutable => {
+                let denied = if is_attr { !var.param } else { var.assigned };
+                var.assigned = true;
+                denied
+            }
+            _ => false,
+        };
+        if denied {
+            self.error(
+                location,
+                format!("Variable Error: {} is const and cannot be modified.", name),
+            );
+        }
+    }
+
+    // 同名函数可能定义在不同的作用域中，内层和后定义的函数覆盖参数类型相同的函数
+    fn lookup_funcs(&self, name: &str) -> Vec<FuncSign> {
+        let mut signs: Vec<FuncSign> = vec![];
+        for frame in self.frames.iter().rev() {
+            for sign in frame.funcs.get(name).into_iter().flatten().rev() {
+                if !signs.iter().any(|s| s.0 == sign.0) {
+                    signs.push(sign.clone());
+                }
+            }
+        }
+        // 按照定义的顺序排列
+        signs.reverse();
+        signs
+    }
+
+    // 重载排序时实参转换成形参的代价，泛型参数在运行时作为any
+    fn cost(&self, param: &NewTypeKind, arg: &NewTypeKind) -> u8 {
+        if param.substitute(&HashMap::new()) != *param {
+            return 3;
+        }
+        // 实现了trait的类型和按照形参类型解释的字面量也属于其他转换
+        NewTypeKind::conversion_cost(param, arg).unwrap_or(2)
+    }
+
+    fn resolve(&mut self, typ: &NewTypeKind, location: &Location) -> NewTypeKind {
+        let resolved = self.types().borrow().resolve(typ);
+        match resolved {
+            Ok(t) => t,
+            Err(e) => {
+                self.error(location, e);
+                any()
+            }
+        }
+    }
+
+    // 泛型结构体的字段类型按照实例的泛型参数替换，没有参数时为any
+    fn get_struct(&self, typ: &NewTypeKind) -> Option<HashMap<String, NewTypeKind>> {
+        let (name, args) = match typ {
+            NewTypeKind::Named(name) => (name, vec![]),
+            NewTypeKind::Generic(name, args) => (name, args.clone()),
+            _ => return None,
+        };
+        let types = self.types();
+        let types = types.borrow();
+        let fields = types.get_struct(name)?;
+        let bound = types
+            .get_generics(name)
+            .into_iter()
+            .zip(args)
+            .collect::<HashMap<String, NewTypeKind>>();
+        Some(
+            fields
+                .into_iter()
+                .map(|(key, item)| (key, item.substitute(&bound)))
+                .collect(),
+        )
+    }
+
+    // 根据值的类型推断泛型参数，同一个参数推断出不兼容的类型时返回错误
+    // nested表示在数组等类型的内部，这时类型需要一致
+    fn bind(
+        &self,
+        param: &NewTypeKind,
+        value: &NewTypeKind,
+        bound: &mut HashMap<String, NewTypeKind>,
+        nested: bool,
+    ) -> Result<bool, String> {
+        match (param, value) {
+            (NewTypeKind::Param(name), _) => match bound.get(name) {
+                Some(found) if found.is_any() => {
+                    bound.insert(name.clone(), value.clone());
+                    Ok(true)
+                }
+                Some(found)
+                    if !self.compatible(found, value)
+                        || (nested && !NewTypeKind::is_same(found, value)) =>
+                {
+                    Err(format!(
+                        "Type Error: generic argument {} cannot be both {} and {}.",
+                        name, found, value
+                    ))
+                }
+                Some(_) => Ok(true),
+                None => {
+                    bound.insert(name.clone(), value.clone());
+                    Ok(true)
+                }
+            },
+            (_, _) if value.is_any() => Ok(true),
+            (NewTypeKind::Array(p), NewTypeKind::Array(v)) => self.bind(p, v, bound, true),
+            (NewTypeKind::Map(k1, v1), NewTypeKind::Map(k2, v2)) => {
+                Ok(self.bind(k1, k2, bound, true)? && self.bind(v1, v2, bound, true)?)
+            }
+            (NewTypeKind::Tuple(p), NewTypeKind::Tuple(v)) => self.bind_all(p, v, bound, true),
+            (NewTypeKind::Generic(n1, p), NewTypeKind::Generic(n2, v)) if n1 == n2 => {
+                self.bind_all(p, v, bound, true)
+            }
+            (NewTypeKind::Function(p, r1), NewTypeKind::Function(v, r2)) => {
+                Ok(self.bind_all(p, v, bound, true)? && self.bind(r1, r2, bound, true)?)
+            }
+            _ if nested => Ok(NewTypeKind::is_same(param, value)),
+            _ => Ok(self.compatible(param, value)),
+        }
+    }
+
+    fn bind_all(
+        &self,
+        params: &[NewTypeKind],
+        values: &[NewTypeKind],
+        bound: &mut HashMap<String, NewTypeKind>,
+        nested: bool,
+    ) -> Result<bool, String> {
+        if params.len() != values.len() {
+            return Ok(false);
+        }
+        for (p, v) in params.iter().zip(values.iter()) {
+            if !self.bind(p, v, bound, nested)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // trait类型可以接受实现了它的类型
+    fn compatible(&self, target: &NewTypeKind, value: &NewTypeKind) -> bool {
+        self.types().borrow().is_compatible(target, value)
+    }
+
+    fn check_assign(&mut self, target: &NewTypeKind, value: &NewTypeKind, location: &Location) {
+        if !self.compatible(target, value) {
+            self.error(
+                location,
+                format!("Type Error: cannot assign {} to {}.", value, target),
+            );
+        }
+    }
+
+    // 没有后缀的数值字面量，负数是对字面量调用neg
+    fn untyped_literal(&self, expr: &Expression) -> Option<Literal> {
+        match &expr.node {
+            ExpressionKind::Liter { value } if is_untyped(value) => Some(value.clone()),
+            ExpressionKind::Call { callee, args }
+                if args.len() == 1 && self.lookup_funcs("neg").is_empty() =>
+            {
+                match (&callee.node, self.untyped_literal(&args[0])?) {
+                    (ExpressionKind::Ident { value }, Literal::Integer(s))
+                        if value == "neg" && !s.starts_with('-') =>
+                    {
+                        Some(Literal::Integer(format!("-{}", s)))
+                    }
+                    (ExpressionKind::Ident { value }, Literal::Float(s))
+                        if value == "neg" && !s.starts_with('-') =>
+                    {
+                        Some(Literal::Float(format!("-{}", s)))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // 表达式中没有后缀的数值字面量按照期望的类型解释，超出范围时报错，typ为推断出的类型
+    // 数组、map和元组字面量的元素也按照期望的元素类型解释，例如 let a: [u8] = [1, 2];
+    fn fit(&mut self, expr: &Expression, typ: NewTypeKind, expected: &NewTypeKind) -> NewTypeKind {
+        let expected = expected.non_null();
+        if let Some(literal) = self.untyped_literal(expr) {
+            return match literal_as(&literal, expected) {
+                Some(Ok(typ)) => typ,
+                Some(Err(e)) => {
+                    self.error(&expr.location, e);
+                    expected.clone()
+                }
+                None => typ,
+            };
+        }
+        let (name, items) = match &expr.node {
+            ExpressionKind::Call { callee, args } => match &callee.node {
+                ExpressionKind::Ident { value } if self.lookup_funcs(value).is_empty() => {
+                    (value.as_str(), args)
+                }
+                _ => return typ,
+            },
+            _ => return typ,
+        };
+        match (name, &typ, expected) {
+            ("array", NewTypeKind::Array(item), NewTypeKind::Array(target)) => {
+                let types = items
+                    .iter()
+                    .map(|e| self.fit(e, *item.clone(), target))
+                    .collect::<Vec<_>>();
+                NewTypeKind::Array(Box::new(NewTypeKind::common(&types)))
+            }
+            ("tuple", NewTypeKind::Tuple(types), NewTypeKind::Tuple(targets))
+                if types.len() == targets.len() && items.len() == types.len() =>
+            {
+                let types = items
+                    .iter()
+                    .zip(types.iter().zip(targets.iter()))
+                    .map(|(e, (t, target))| self.fit(e, t.clone(), target))
+                    .collect();
+                NewTypeKind::Tuple(types)
+            }
+            ("map", NewTypeKind::Map(key, value), NewTypeKind::Map(k, v)) => {
+                let types = items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| match i % 2 {
+                        0 => self.fit(e, *key.clone(), k),
+                        _ => self.fit(e, *value.clone(), v),
+                    })
+                    .collect::<Vec<_>>();
+                map_type(&types).unwrap_or(typ)
+            }
+            _ => typ,
+        }
+    }
+
+    // 条件表达式必须是布尔值
+    fn check_cond(&mut self, expr: &Expression) {
+        let typ = self.infer(expr);
+        if !NewTypeKind::is_compatible(&NewTypeKind::name("bool"), &typ) {
+            self.error(
+                &expr.location,
+                format!("Type Error: condition should be bool, found {}.", typ),
+            );
+        }
+    }
+
+    pub fn check_block(&mut self, stmts: &StmtList) {
+        // 先引入导入的类型，块内的定义可以使用它们
+        for stmt in stmts {
+            if let StatementKind::Import { path, .. } = &stmt.node {
+                if let Some(exports) = self.modules.get(path).cloned() {
+                    exports.import_types(&mut self.types().borrow_mut());
+                }
+            }
+        }
+        // 预先登记块内的结构体和枚举名称
+        for stmt in stmts.iter().map(Statement::definition) {
+            if let StatementKind::TypeDef {
+                left,
+                right,
+                generics,
+            } = &stmt.node
+            {
+                if !matches!(right, NewTypeKind::Struct(_) | NewTypeKind::Enum(_)) {
+                    continue;
+                }
+                let types = self.types();
+                if types.borrow().is_defined(left) {
+                    self.error(
+                        &stmt.location,
+                        format!("Type Error: {} has been defined.", left),
+                    );
+                } else if let NewTypeKind::Enum(_) = right {
+                    types.borrow_mut().define_enum(left, vec![]);
+                } else {
+                    types.borrow_mut().declare_struct(left);
+                    if !generics.is_empty() {
+                        types.borrow_mut().define_generics(left, generics);
+                    }
+                }
+            }
+        }
+        let names = declared_names(stmts);
+        self.frame().declared.extend(names);
+        for stmt in stmts {
+            self.check_stmt(&stmt.node, &stmt.location);
+        }
+    }
+
+    fn check_stmt(&mut self, stmt: &StatementKind, location: &Location) {
+        match stmt {
+            StatementKind::Break | StatementKind::Continue => (),
+
+            StatementKind::Return { value } => {
+                let typ = self.infer(value);
+                let expected = self.rettyps.last().unwrap().clone();
+                let typ = self.fit(value, typ, &expected);
+                if !self.compatible(&expected, &typ) {
+                    self.error(
+                        location,
+                        format!(
+                            "Type Error: function should return {}, found {}.",
+                            expected, typ
+                        ),
+                    );
+                }
+            }
+
+            StatementKind::Assign { left, right } => {
+                self.check_mutable(left, false, location);
+                let value = self.infer(right);
+                let target = self.infer_target(left);
+                let value = self.fit(right, value, &target);
+                self.check_assign(&target, &value, location);
+                if let ExpressionKind::Ident { value: name } = &left.node {
+                    self.assign_var(name, &value);
+                }
+            }
+
+            StatementKind::Move { left, right } => {
+                self.check_mutable(left, false, location);
+                let value = self.infer(right);
+                let target = self.infer_target(left);
+                self.check_assign(&target, &value, location);
+                if let ExpressionKind::Ident { value: name } = &left.node {
+                    self.assign_var(name, &value);
+                }
+                match (&left.node, &right.node) {
+                    (ExpressionKind::Ident { value: a }, ExpressionKind::Ident { value: b })
+                        if a == b => {}
+                    (_, ExpressionKind::Ident { value }) => {
+                        self.frame().moved.insert(value.clone());
+                    }
+                    (_, ExpressionKind::Dot { .. }) => self.error(
+                        location,
+                        "Variable Error: cannot move out of a field.".to_string(),
+                    ),
+                    (_, ExpressionKind::Index { .. }) => self.error(
+                        location,
+                        "Variable Error: cannot move out of an element.".to_string(),
+                    ),
+                    _ => {}
+                }
+            }
+
+            StatementKind::AugAssign { op, left, right } => {
+                self.check_mutable(left, false, location);
+                let target = self.infer(left);
+                let value = self.infer(right);
+                // 运算的结果需要放回目标中，字面量按照目标的类型解释，例如 a += 1
+                let literal = self.untyped_literal(right);
+                let value = fit_literal(literal.as_ref(), &value, &target);
+                let result = self.check_call(op, &[target.clone(), value], &[], location);
+                self.check_assign(&target, &result, location);
+                if let ExpressionKind::Ident { value: name } = &left.node {
+                    self.assign_var(name, &result);
+                }
+            }
+
+            StatementKind::VarDef {
+                mutable,
+                name,
+                typ,
+                assign,
+            } => {
+                let declared = typ.as_ref().map(|typ| self.resolve(typ, location));
+                let value = match (&declared, assign) {
+                    (Some(declared), Some(expr)) => {
+                        let value = self.infer(expr);
+                        Some(self.fit(expr, value, declared))
+                    }
+                    (None, Some(expr)) => Some(self.infer(expr)),
+                    (_, None) => None,
+                };
+                let typ = match (declared, &value) {
+                    // 没有标注类型时，由初始值推断，null不能确定类型
+                    (None, Some(value)) if !value.is_null() => value.clone(),
+                    (None, _) => any(),
+                    (Some(declared), Some(value)) => {
+                        self.check_assign(&declared, value, location);
+                        declared
+                    }
+                    (Some(declared), None) => declared,
+                };
+                self.define_var(name, typ, *mutable, assign.is_some());
+                if let Some(value) = value {
+                    self.assign_var(name, &value);
+                }
+            }
+
+            StatementKind::Destruct {
+                mutable,
+                pattern,
+                value,
+            } => {
+                let typ = self.infer(value);
+                self.check_pattern(pattern, &typ, &mut vec![], *mutable, location);
+            }
+
+            StatementKind::TypeDef {
+                left,
+                right,
+                generics,
+            } => match right {
+                // 泛型结构体的字段在定义了泛型参数的作用域中解析
+                NewTypeKind::Struct(_) => {
+                    self.enter();
+                    self.types().borrow_mut().define_params(generics);
+                    let typ = self.resolve(right, location);
+                    self.leave();
+                    if let NewTypeKind::Struct(fields) = typ {
+                        self.types().borrow_mut().define_struct(left, fields);
+                    }
+                }
+                NewTypeKind::Enum(_) => {
+                    if let NewTypeKind::Enum(variants) = self.resolve(right, location) {
+                        self.types().borrow_mut().define_enum(left, variants);
+                    }
+                }
+                _ => {
+                    if self.types().borrow().is_defined(left) {
+                        self.error(location, format!("Type Error: {} has been defined.", left));
+                    } else {
+                        let typ = self.resolve(right, location);
+                        self.types().borrow_mut().define_alias(left, typ);
+                    }
+                }
+            },
+
+            StatementKind::FuncDef {
+                name,
+                generics,
+                args,
+                rettyp,
+                block,
+            } => {
+                // 先登记函数，使函数体内可以递归调用
+                let (args, rettyp) = self.define_func(name, generics, args, rettyp, location);
+                self.check_function(generics, args, rettyp, block, location);
+            }
+
+            StatementKind::Impl {
+                name,
+                trait_name,
+                methods,
+            } => {
+                if self.get_struct(&NewTypeKind::name(name)).is_none() {
+                    self.error(location, format!("Type Error: {} is not a struct.", name));
+                    return;
+                }
+                if let Some(trait_name) = trait_name {
+                    self.check_impl(name, trait_name, methods, location);
+                }
+                // 先登记所有方法，使方法之间可以互相调用
+                let mut defined = vec![];
+                for method in methods {
+                    if let StatementKind::FuncDef {
+                        name: method_name,
+                        generics,
+                        args,
+                        rettyp,
+                        block,
+                    } = &method.node
+                    {
+                        let name = format!("{}::{}", name, method_name);
+                        let sign =
+                            self.define_func(&name, generics, args, rettyp, &method.location);
+                        defined.push((generics, sign, block, &method.location));
+                    }
+                }
+                for (generics, (args, rettyp), block, location) in defined {
+                    self.check_function(generics, args, rettyp, block, location);
+                }
+            }
+
+            StatementKind::Trait { name, methods } => {
+                if self.types().borrow().is_defined(name) {
+                    self.error(location, format!("Type Error: {} has been defined.", name));
+                    return;
+                }
+                let typ = NewTypeKind::Trait(methods.iter().cloned().collect());
+                if let NewTypeKind::Trait(methods) = self.resolve(&typ, location) {
+                    self.types().borrow_mut().define_trait(name, methods);
+                }
+            }
+
+            StatementKind::Expression { expr } => {
+                self.infer(expr);
+            }
+
+            StatementKind::If { test, then, orelse } => {
+                self.check_cond(test);
+                let (yes, no) = null_checks(test);
+                let branches = vec![
+                    self.check_branch(then, &yes),
+                    self.check_branch(orelse, &no),
+                ];
+                self.merge_branches(branches);
+                // 一个分支一定跳出时，之后的语句满足另一个分支的条件
+                let (narrowed, other) = match (diverges(then), diverges(orelse)) {
+                    (true, false) => (no, orelse),
+                    (false, true) => (yes, then),
+                    _ => return,
+                };
+                let mut assigned = HashSet::new();
+                assigned_vars(other, &mut assigned);
+                for name in narrowed
+                    .into_iter()
+                    .filter(|n| !assigned.iter().any(|a| invalidates(a, n)))
+                {
+                    self.frame().narrowed.insert(name);
+                }
+            }
+
+            StatementKind::While { test, then } => {
+                self.check_loop(then);
+                self.check_cond(test);
+                self.check_branch(then, &null_checks(test).0);
+            }
+
+            StatementKind::For {
+                pattern,
+                iter,
+                then,
+            } => {
+                let typ = self.infer(iter);
+                let typ = self.check_null(iter, typ);
+                let item = self.iter_item(&typ).unwrap_or_else(|| {
+                    self.error(
+                        &iter.location,
+                        format!("Type Error: {} is not iterable.", typ),
+                    );
+                    any()
+                });
+                self.check_loop(then);
+                self.enter();
+                self.check_pattern(pattern, &item, &mut vec![], true, location);
+                self.check_branch(then, &[]);
+                self.leave();
+            }
+
+            // try中的语句可能没有全部执行，catch之后确定赋值的变量需要两个分支都赋值
+            StatementKind::Try { then, name, catch } => {
+                let then = self.check_branch(then, &[]);
+                self.enter();
+                if let Some(name) = name {
+                    self.define_var(name, NewTypeKind::name("error"), false, true);
+                }
+                let catch = self.check_branch(catch, &[]);
+                self.leave();
+                self.merge_branches(vec![then, catch]);
+            }
+
+            StatementKind::Import { path, name } => {
+                let exports = match self.modules.get(path) {
+                    Some(exports) => exports.clone(),
+                    None => {
+                        self.error(
+                            location,
+                            format!("Import Error: module {} is not loaded.", path),
+                        );
+                        return;
+                    }
+                };
+                for (method, signs) in exports.methods.iter() {
+                    self.frame().funcs.insert(method.clone(), signs.clone());
+                }
+                self.define_var(name, NewTypeKind::name("module"), false, true);
+                self.frame().modules.insert(name.clone(), exports);
+            }
+
+            StatementKind::Export { stmt } => self.check_stmt(&stmt.node, &stmt.location),
+
+            StatementKind::Throw { value } => {
+                let typ = self.infer(value);
+                if !self.compatible(&NewTypeKind::name("error"), &typ) {
+                    self.error(
+                        location,
+                        format!("Type Error: throw expects error, found {}.", typ),
+                    );
+                }
+            }
+        }
+    }
+
+    // 收集模块最外层导出的定义
+    fn exports(&self, stmts: &StmtList) -> Exports {
+        let frame = &self.frames[0];
+        let mut exports = Exports {
+            scope: Some(frame.types.clone()),
+            ..Exports::default()
+        };
+        let names = stmts
+            .iter()
+            .filter(|stmt| matches!(stmt.node, StatementKind::Export { .. }))
+            .filter_map(|stmt| stmt.defined_name());
+        for name in names {
+            if let Some(var) = frame.vars.get(name) {
+                exports.vars.insert(name.clone(), var.typ.clone());
+            } else if let Some(signs) = frame.funcs.get(name) {
+                exports.funcs.insert(name.clone(), signs.clone());
+            }
+        }
+        exports.types = frame.types.borrow().names();
+        for (method, signs) in frame.funcs.iter() {
+            let typ = method.split("::").next().unwrap_or_default();
+            if method.contains("::") && exports.types.iter().any(|t| t == typ) {
+                exports.methods.insert(method.clone(), signs.clone());
+            }
+        }
+        exports
+    }
+
+    // 表达式是导入的模块时返回模块的别名和导出的定义，同名的变量会覆盖模块
+    fn lookup_module(&self, expr: &Expression) -> Option<(String, Rc<Exports>)> {
+        let name = match &expr.node {
+            ExpressionKind::Ident { value } => value,
+            _ => return None,
+        };
+        let frame = self
+            .frames
+            .iter()
+            .rev()
+            .find(|frame| frame.vars.contains_key(name))?;
+        let exports = frame.modules.get(name)?;
+        Some((name.clone(), exports.clone()))
+    }
+
+    // 模块导出的变量或者函数的类型
+    fn export_type(
+        &mut self,
+        module: &str,
+        exports: &Exports,
+        name: &str,
+        location: &Location,
+    ) -> NewTypeKind {
+        if let Some(typ) = exports.vars.get(name) {
+            return typ.clone();
+        }
+        let msg = match exports.funcs.get(name).map(|signs| signs.as_slice()) {
+            Some([(params, rettyp)]) => {
+                return NewTypeKind::Function(params.clone(), Box::new(rettyp.clone()))
+            }
+            Some(_) => format!(
+                "Type Error: function {}.{} is overloaded and cannot be used as a value.",
+                module, name
+            ),
+            None => format!("Type Error: module {} does not export {}.", module, name),
+        };
+        self.error(location, msg);
+        any()
+    }
+
+    // 检查impl是否实现了trait的所有方法，并登记实现关系
+    fn check_impl(
+        &mut self,
+        name: &str,
+        trait_name: &str,
+        methods: &StmtList,
+        location: &Location,
+    ) {
+        let declared = match self.types().borrow().get_trait(trait_name) {
+            Some(declared) => declared,
+            None => {
+                self.error(
+                    location,
+                    format!("Type Error: {} is not a trait.", trait_name),
+                );
+                return;
+            }
+        };
+        self.types().borrow_mut().define_impl(name, trait_name);
+        let mut keys = declared.keys().collect::<Vec<&String>>();
+        keys.sort();
+        for key in keys {
+            // 方法的类型不包含self参数
+            let found = methods.iter().find_map(|method| match &method.node {
+                StatementKind::FuncDef {
+                    name: method_name,
+                    args,
+                    rettyp,
+                    ..
+                } if method_name == key && args.first().is_some_and(|a| a.0 == "self") => {
+                    Some(NewTypeKind::Function(
+                        args[1..].iter().map(|a| a.1.clone()).collect(),
+                        Box::new(rettyp.clone()),
+                    ))
+                }
+                _ => None,
+            });
+            match found.map(|typ| self.resolve(&typ, location)) {
+                None => self.error(
+                    location,
+                    format!(
+                        "Type Error: {} does not implement {}.{}.",
+                        name, trait_name, key
+                    ),
+                ),
+                Some(typ) if typ != declared[key] => self.error(
+                    location,
+                    format!(
+                        "Type Error: {}::{} should be {}, found {}.",
+                        name, key, declared[key], typ
+                    ),
+                ),
+                _ => (),
+            }
+        }
+    }
+
+    // 登记函数签名，返回解析后的参数和返回值类型，泛型参数解析为Param
+    fn define_func(
+        &mut self,
+        name: &str,
+        generics: &[String],
+        args: &[(String, NewTypeKind)],
+        rettyp: &NewTypeKind,
+        location: &Location,
+    ) -> (Vec<(String, NewTypeKind)>, NewTypeKind) {
+        self.enter();
+        self.types().borrow_mut().define_params(generics);
+        let args = args
+            .iter()
+            .map(|(arg, typ)| (arg.clone(), self.resolve(typ, location)))
+            .collect::<Vec<(String, NewTypeKind)>>();
+        let rettyp = self.resolve(rettyp, location);
+        self.leave();
+        let sign = (args.iter().map(|a| a.1.clone()).collect(), rettyp.clone());
+        self.frame()
+            .funcs
+            .entry(name.to_string())
+            .or_default()
+            .push(sign);
+        (args, rettyp)
+    }
+
+    // 在新的函数作用域中检查函数体
+    fn check_function(
+        &mut self,
+        generics: &[String],
+        args: Vec<(String, NewTypeKind)>,
+        rettyp: NewTypeKind,
+        block: &StmtList,
+        location: &Location,
+    ) {
+        self.enter();
+        self.frame().function = true;
+        self.types().borrow_mut().define_params(generics);
+        for (arg, typ) in args {
+            self.define_param(&arg, typ);
+        }
+        // 执行到函数体末尾时返回unit
+        if rettyp != NewTypeKind::name("unit") && !rettyp.is_any() && !diverges(block) {
+            self.error(
+                location,
+                format!("Type Error: function may not return {}.", rettyp),
+            );
+        }
+        self.rettyps.push(rettyp);
+        self.check_block(block);
+        self.rettyps.pop();
+        self.leave();
+    }
+
+    // 迭代时每一项的类型，None表示不能迭代
+    fn iter_item(&self, typ: &NewTypeKind) -> Option<NewTypeKind> {
+        match typ {
+            _ if typ.is_any() => Some(any()),
+            NewTypeKind::Array(item) => Some(*item.clone()),
+            NewTypeKind::Tuple(_) => Some(any()),
+            // map按照插入顺序迭代 (key, value)
+            NewTypeKind::Map(key, value) => {
+                Some(NewTypeKind::Tuple(vec![*key.clone(), *value.clone()]))
+            }
+            _ if typ.range_item().is_some() => typ.range_item().cloned(),
+            NewTypeKind::Named(name) if name == "str" => Some(typ.clone()),
+            _ if self.get_struct(typ).is_some() => {
+                Some(NewTypeKind::Tuple(vec![NewTypeKind::name("str"), any()]))
+            }
+            _ => None,
+        }
+    }
+
+    // literals为实参中没有后缀的数值字面量，它们按照形参的类型解释
+    fn check_call(
+        &mut self,
+        name: &str,
+        args: &[NewTypeKind],
+        literals: &[Option<Literal>],
+        location: &Location,
+    ) -> NewTypeKind {
+        let signs = self.lookup_funcs(name);
+        if !signs.is_empty() {
+            return self.select_func(name, &signs, args, literals, location);
+        }
+        let variant = self.types().borrow().get_variant(name);
+        if let Some((typ, data)) = variant {
+            let args = match &data {
+                NewTypeKind::Tuple(items) => fit_args(items, args, literals),
+                _ => args.to_vec(),
+            };
+            return self.variant_type(name, &typ, &data, Some(&args), location);
+        }
+        match builtin_type(name, &fit_builtin(name, args, literals)) {
+            Some(Ok(typ)) => typ,
+            Some(Err(e)) => {
+                self.error(location, e);
+                any()
+            }
+            // 结构体的关联函数只能由impl定义
+            None if name.contains("::") || !self.is_defined(name) => {
+                self.error(
+                    location,
+                    format!("Variable Error: {} is not defined.", name),
+                );
+                any()
+            }
+            None => any(),
+        }
+    }
+
+    // 按照参数的转换代价在重载的函数中选择，泛型函数的返回值类型由推断出的泛型参数决定
+    fn select_func(
+        &mut self,
+        name: &str,
+        signs: &[FuncSign],
+        args: &[NewTypeKind],
+        literals: &[Option<Literal>],
+        location: &Location,
+    ) -> NewTypeKind {
+        let show = |indexes: &mut dyn Iterator<Item = usize>| {
+            indexes
+                .map(|i| format!("{}({})", name, show_types(&signs[i].0)))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        let mut conflict = None;
+        let mut viable = vec![];
+        let mut costs = vec![];
+        for (i, (params, rettyp)) in signs.iter().enumerate() {
+            let mut bound = HashMap::new();
+            match self.bind_all(params, &fit_args(params, args, literals), &mut bound, false) {
+                Ok(true) => {
+                    viable.push((i, rettyp.substitute(&bound)));
+                    costs.push(
+                        params
+                            .iter()
+                            .zip(args.iter())
+                            .map(|(p, a)| self.cost(p, a))
+                            .collect(),
+                    );
+                }
+                Ok(false) => (),
+                Err(e) => {
+                    conflict.get_or_insert(e);
+                }
+            }
+        }
+        let msg = match func::select_overload(&costs) {
+            Ok(i) => return viable[i].1.clone(),
+            Err(_) if viable.is_empty() => match conflict {
+                Some(e) => e,
+                None if signs.len() == 1 => format!(
+                    "Type Error: function {} does not accept ({}).",
+                    name,
+                    show_types(args)
+                ),
+                None => format!(
+                    "Type Error: no overload of {} accepts ({}); candidates: {}.",
+                    name,
+                    show_types(args),
+                    show(&mut (0..signs.len()))
+                ),
+            },
+            // 实参的类型未知时由运行时选择
+            Err(_) if args.iter().any(|a| a.is_any()) => return any(),
+            Err(tied) => format!(
+                "Type Error: call to {} with ({}) is ambiguous; candidates: {}.",
+                name,
+                show_types(args),
+                show(&mut tied.into_iter().map(|i| viable[i].0))
+            ),
+        };
+        self.error(location, msg);
+        any()
+    }
+
+    // 检查结构体字面量的字段是否与定义一致
+    fn check_fields(
+        &mut self,
+        typ: &NewTypeKind,
+        fields: &HashMap<String, NewTypeKind>,
+        args: &[(String, Box<Expression>)],
+        values: &[(String, NewTypeKind)],
+        location: &Location,
+    ) {
+        for ((_, expr), (key, value)) in args.iter().zip(values.iter()) {
+            match fields.get(key) {
+                None => self.error(
+                    location,
+                    format!("Type Error: struct {} has no field {}.", typ, key),
+                ),
+                Some(t) => {
+                    let value = self.fit(expr, value.clone(), t);
+                    if !self.compatible(t, &value) {
+                        self.error(
+                            location,
+                            format!(
+                                "Type Error: field {}.{} expects {}, found {}.",
+                                typ, key, t, value
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+        let mut missing = fields
+            .keys()
+            .filter(|key| !values.iter().any(|(k, _)| k == *key))
+            .cloned()
+            .collect::<Vec<String>>();
+        if !missing.is_empty() {
+            missing.sort();
+            self.error(
+                location,
+                format!(
+                    "Type Error: struct {} is missing field {}.",
+                    typ,
+                    missing.join(", ")
+                ),
+            );
+        }
+    }
+
+    // 根据字段的值推断泛型结构体的实例类型
+    fn infer_generics(
+        &mut self,
+        name: &str,
+        params: Vec<String>,
+        values: &[(String, NewTypeKind)],
+        location: &Location,
+    ) -> NewTypeKind {
+        let fields = self
+            .types()
+            .borrow()
+            .get_struct(name)
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<HashMap<String, NewTypeKind>>();
+        let mut bound = HashMap::new();
+        for (key, value) in values {
+            if let Some(field) = fields.get(key) {
+                if let Err(e) = self.bind(field, value, &mut bound, false) {
+                    self.error(location, e);
+                }
+            }
+        }
+        NewTypeKind::Generic(
+            name.to_string(),
+            params
+                .iter()
+                .map(|param| bound.get(param).cloned().unwrap_or_else(any))
+                .collect(),
+        )
+    }
+
+    // 构造枚举变体的类型，args为None时变体作为值使用
+    fn variant_type(
+        &mut self,
+        name: &str,
+        typ: &str,
+        data: &NewTypeKind,
+        args: Option<&[NewTypeKind]>,
+        location: &Location,
+    ) -> NewTypeKind {
+        match (data, args) {
+            (NewTypeKind::Tuple(items), Some(args)) => {
+                let accepted = items.len() == args.len()
+                    && items
+                        .iter()
+                        .zip(args.iter())
+                        .all(|(t, a)| self.compatible(t, a));
+                if !accepted {
+                    self.error(
+                        location,
+                        format!(
+                            "Type Error: variant {} does not accept ({}).",
+                            name,
+                            show_types(args)
+                        ),
+                    );
+                }
+            }
+            (NewTypeKind::Tuple(_), None) => self.error(
+                location,
+                format!("Type Error: variant {} expects {}.", name, data),
+            ),
+            (NewTypeKind::Struct(fields), _) => {
+                let mut keys = fields.iter().map(|f| f.0.clone()).collect::<Vec<String>>();
+                keys.sort();
+                self.error(
+                    location,
+                    format!(
+                        "Type Error: variant {} expects fields {}.",
+                        name,
+                        keys.join(",")
+                    ),
+                );
+            }
+            (_, None) => (),
+            (_, Some(_)) => {
+                self.error(location, format!("Type Error: {} is not callable.", name));
+            }
+        }
+        NewTypeKind::name(typ)
+    }
+
+    // 检查模式能否匹配类型typ的值，定义模式中绑定的变量，mutable为绑定的变量能否修改
+    // 返回结构体别名替换成结构体名称后的模式
+    fn check_pattern(
+        &mut self,
+        pattern: &Pattern,
+        typ: &NewTypeKind,
+        bound: &mut Vec<String>,
+        mutable: bool,
+        location: &Location,
+    ) -> Pattern {
+        let mismatch = |checker: &mut Self| {
+            checker.error(
+                location,
+                format!(
+                    "Type Error: pattern {} does not match type {}.",
+                    pattern, typ
+                ),
+            );
+        };
+        match pattern {
+            Pattern::Wildcard => Pattern::Wildcard,
+            Pattern::Binding(name) => {
+                if bound.contains(name) {
+                    self.error(
+                        location,
+                        format!(
+                            "Variable Error: {} is bound more than once in the pattern.",
+                            name
+                        ),
+                    );
+                }
+                bound.push(name.clone());
+                self.define_var(name, typ.clone(), mutable, true);
+                pattern.clone()
+            }
+            Pattern::Literal(value) => {
+                let value_typ = match value {
+                    Literal::Integer(s) => self.literal_type(location, number::int_literal(s)),
+                    Literal::Float(s) => self.literal_type(location, number::float_literal(s)),
+                    Literal::String(_) => NewTypeKind::name("str"),
+                    Literal::True | Literal::False => NewTypeKind::name("bool"),
+                    Literal::Unit => NewTypeKind::name("unit"),
+                    Literal::Null => NewTypeKind::name("null"),
+                };
+                let fits =
+                    is_untyped(value) && matches!(literal_as(value, typ.non_null()), Some(Ok(_)));
+                if !fits && !NewTypeKind::is_compatible(typ, &value_typ) {
+                    mismatch(self);
+                }
+                pattern.clone()
+            }
+            Pattern::Range { start, end, .. } => {
+                for value in [start, end] {
+                    let value_typ = match value {
+                        Literal::Integer(s) => self.literal_type(location, number::int_literal(s)),
+                        Literal::Float(s) => self.literal_type(location, number::float_literal(s)),
+                        _ => NewTypeKind::name("str"),
+                    };
+                    let numeric = value_typ.is_integer() || value_typ.is_float();
+                    let fits = is_untyped(value)
+                        && matches!(literal_as(value, typ.non_null()), Some(Ok(_)));
+                    if !numeric || !(fits || NewTypeKind::is_compatible(typ, &value_typ)) {
+                        mismatch(self);
+                        break;
+                    }
+                }
+                pattern.clone()
+            }
+            Pattern::Tuple(items) => {
+                let typs = match typ {
+                    NewTypeKind::Tuple(typs) if typs.len() == items.len() => typs.clone(),
+                    _ if typ.is_any() => vec![any(); items.len()],
+                    _ => {
+                        mismatch(self);
+                        vec![any(); items.len()]
+                    }
+                };
+                Pattern::Tuple(
+                    items
+                        .iter()
+                        .zip(typs.iter())
+                        .map(|(item, t)| self.check_pattern(item, t, bound, mutable, location))
+                        .collect(),
+                )
+            }
+            Pattern::Array { items, rest } => {
+                let item_typ = match typ {
+                    NewTypeKind::Array(item) => *item.clone(),
+                    _ if typ.is_any() => any(),
+                    _ => {
+                        mismatch(self);
+                        any()
+                    }
+                };
+                Pattern::Array {
+                    items: items
+                        .iter()
+                        .map(|item| self.check_pattern(item, &item_typ, bound, mutable, location))
+                        .collect(),
+                    rest: *rest,
+                }
+            }
+            // 省略名称的结构体模式使用值的类型，any类型的值在运行时检查
+            Pattern::Struct { name, fields } if name.is_empty() => {
+                if typ.is_any() {
+                    let fields = fields
+                        .iter()
+                        .map(|(key, item)| {
+                            let item = self.check_pattern(item, &any(), bound, mutable, location);
+                            (key.clone(), item)
+                        })
+                        .collect();
+                    return Pattern::Struct {
+                        name: String::new(),
+                        fields,
+                    };
+                }
+                if self.get_struct(typ).is_none() {
+                    mismatch(self);
+                    return Pattern::Wildcard;
+                }
+                let named = Pattern::Struct {
+                    name: typ.base_name(),
+                    fields: fields.clone(),
+                };
+                self.check_pattern(&named, typ, bound, mutable, location)
+            }
+            Pattern::Struct { name, fields } => {
+                // 结构体形式的枚举变体，值的类型为枚举
+                let variant = self.types().borrow().get_variant(name);
+                let (resolved, owner, defined) = match variant {
+                    Some((enum_name, NewTypeKind::Struct(defined))) => {
+                        let defined = defined.into_iter().collect();
+                        (name.clone(), NewTypeKind::name(&enum_name), defined)
+                    }
+                    Some(_) => {
+                        self.error(location, format!("Type Error: {} is not a struct.", name));
+                        return Pattern::Wildcard;
+                    }
+                    None => {
+                        let mut resolved = self.resolve(&NewTypeKind::name(name), location);
+                        // 泛型结构体的字段类型由匹配的值的类型决定
+                        if typ.base_name() == resolved.base_name() {
+                            resolved = typ.clone();
+                        }
+                        match self.get_struct(&resolved) {
+                            Some(defined) => (resolved.base_name(), resolved, defined),
+                            None => {
+                                if !resolved.is_any() {
+                                    self.error(
+                                        location,
+                                        format!("Type Error: {} is not a struct.", name),
+                                    );
+                                }
+                                return Pattern::Wildcard;
+                            }
+                        }
+                    }
+                };
+                if !typ.is_any() && *typ != owner {
+                    mismatch(self);
+                }
+                let mut checked = vec![];
+                for (key, item) in fields {
+                    let field_typ = match defined.get(key) {
+                        Some(t) => t.clone(),
+                        None => {
+                            self.error(
+                                location,
+                                format!("Type Error: struct {} has no field {}.", name, key),
+                            );
+                            any()
+                        }
+                    };
+                    checked.push((
+                        key.clone(),
+                        self.check_pattern(item, &field_typ, bound, mutable, location),
+                    ));
+                }
+                Pattern::Struct {
+                    name: resolved,
+                    fields: checked,
+                }
+            }
+            Pattern::Variant { name, items } => {
+                let variant = self.types().borrow().get_variant(name);
+                let (enum_name, data) = match variant {
+                    Some(variant) => variant,
+                    None => {
+                        self.error(
+                            location,
+                            format!("Type Error: {} is not an enum variant.", name),
+                        );
+                        return Pattern::Wildcard;
+                    }
+                };
+                if !typ.is_any() && *typ != NewTypeKind::name(&enum_name) {
+                    mismatch(self);
+                }
+                match (&data, items) {
+                    (NewTypeKind::Tuple(typs), Some(items)) if typs.len() == items.len() => {
+                        Pattern::Variant {
+                            name: name.clone(),
+                            items: Some(
+                                items
+                                    .iter()
+                                    .zip(typs.iter())
+                                    .map(|(item, t)| {
+                                        self.check_pattern(item, t, bound, mutable, location)
+                                    })
+                                    .collect(),
+                            ),
+                        }
+                    }
+                    (NewTypeKind::Tuple(_), _) => {
+                        self.error(
+                            location,
+                            format!("Type Error: variant {} expects {}.", name, data),
+                        );
+                        pattern.clone()
+                    }
+                    (NewTypeKind::Struct(fields), _) => {
+                        let mut keys = fields.iter().map(|f| f.0.clone()).collect::<Vec<String>>();
+                        keys.sort();
+                        self.error(
+                            location,
+                            format!(
+                                "Type Error: variant {} expects fields {}.",
+                                name,
+                                keys.join(",")
+                            ),
+                        );
+                        pattern.clone()
+                    }
+                    (_, Some(_)) => {
+                        self.error(
+                            location,
+                            format!("Type Error: variant {} has no data.", name),
+                        );
+                        pattern.clone()
+                    }
+                    (_, None) => pattern.clone(),
+                }
+            }
+            // 不同的选项可能匹配不同的数据，不能绑定变量
+            Pattern::Or(items) => {
+                if !pattern.bindings().is_empty() {
+                    self.error(
+                        location,
+                        "Variable Error: cannot bind variables in an or-pattern.".to_string(),
+                    );
+                }
+                Pattern::Or(
+                    items
+                        .iter()
+                        .map(|item| self.check_pattern(item, typ, &mut vec![], mutable, location))
+                        .collect(),
+                )
+            }
+        }
+    }
+
+    // 检查match的各个分支，分支的值类型一致时作为match的类型
+    fn check_match(
+        &mut self,
+        value: &Expression,
+        arms: &[Located<MatchArm>],
+        location: &Location,
+    ) -> NewTypeKind {
+        let typ = self.infer(value);
+        let errors = self.errors.len();
+        let mut patterns = vec![];
+        let mut typs = vec![];
+        for arm in arms {
+            self.enter();
+            let pattern =
+                self.check_pattern(&arm.node.pattern, &typ, &mut vec![], false, &arm.location);
+            if let Some(guard) = &arm.node.guard {
+                self.check_cond(guard);
+            }
+            typs.push(self.infer(&arm.node.body));
+            self.leave();
+            patterns.push((pattern, arm.node.guard.is_some()));
+        }
+        // 模式有错误时不再检查是否穷尽
+        if self.errors.len() == errors {
+            let types = self.types();
+            let (unreachable, witness) = pattern::check_arms(&types.borrow(), &typ, &patterns);
+            for i in unreachable {
+                self.error(
+                    &arms[i].location,
+                    "Type Error: unreachable match arm.".to_string(),
+                );
+            }
+            // any类型的值在运行时没有匹配的分支时报错
+            if let Some(witness) = witness.filter(|_| !typ.is_any()) {
+                self.error(
+                    location,
+                    format!(
+                        "Type Error: match is not exhaustive, {} is not covered.",
+                        witness
+                    ),
+                );
+            }
+        }
+        match typs.first() {
+            Some(first) if typs.iter().all(|t| t == first) => first.clone(),
+            _ => any(),
+        }
+    }
+
+    // 方法调用的返回值类型，结构体中同名的字段优先
+    fn method_type(
+        &mut self,
+        typ: &NewTypeKind,
+        name: &str,
+        args: &[NewTypeKind],
+        literals: &[Option<Literal>],
+        location: &Location,
+    ) -> NewTypeKind {
+        if typ.is_any() {
+            return any();
+        }
+        if let Some(field) = self
+            .get_struct(typ)
+            .and_then(|fields| fields.get(name).cloned())
+        {
+            return self.call_type(&field, args, literals, location);
+        }
+        // trait类型的方法由trait的声明决定，运行时按照对象的类型调用
+        if let NewTypeKind::Named(trait_name) = typ {
+            if let Some(declared) = self.types().borrow().get_trait(trait_name) {
+                return match declared.get(name) {
+                    Some(method) => self.call_type(method, args, literals, location),
+                    None => {
+                        self.error(
+                            location,
+                            format!("Type Error: {} has no method {}.", typ, name),
+                        );
+                        any()
+                    }
+                };
+            }
+        }
+        let method = format!("{}::{}", typ.base_name(), name);
+        if self.lookup_funcs(&method).is_empty() {
+            self.error(
+                location,
+                format!("Type Error: {} has no method {}.", typ, name),
+            );
+            return any();
+        }
+        let mut params = vec![typ.clone()];
+        params.extend_from_slice(args);
+        let mut shifted = vec![None];
+        shifted.extend_from_slice(literals);
+        self.check_call(&method, &params, &shifted, location)
+    }
+
+    // 调用函数值，返回值类型由函数类型决定
+    fn call_type(
+        &mut self,
+        callee: &NewTypeKind,
+        args: &[NewTypeKind],
+        literals: &[Option<Literal>],
+        location: &Location,
+    ) -> NewTypeKind {
+        match callee {
+            NewTypeKind::Function(params, rettyp) => {
+                let mut bound = HashMap::new();
+                match self.bind_all(params, &fit_args(params, args, literals), &mut bound, false) {
+                    Ok(true) => (),
+                    Ok(false) => self.error(
+                        location,
+                        format!(
+                            "Type Error: function {} does not accept ({}).",
+                            callee,
+                            show_types(args)
+                        ),
+                    ),
+                    Err(e) => self.error(location, e),
+                }
+                rettyp.substitute(&bound)
+            }
+            _ if callee.is_any() => any(),
+            _ => {
+                self.error(location, format!("Type Error: {} is not callable.", callee));
+                any()
+            }
+        }
+    }
+
+    // 赋值目标的类型，对变量直接赋值不算读取
+    fn infer_target(&mut self, target: &Expression) -> NewTypeKind {
+        match &target.node {
+            ExpressionKind::Ident { value } => {
+                self.revive(value);
+                self.lookup_var(value).unwrap_or_else(any)
+            }
+            // 元组的元素不能修改
+            ExpressionKind::Index { left, right } => {
+                let typ = self.infer(left);
+                let typ = self.check_null(left, typ);
+                if let NewTypeKind::Tuple(_) = typ {
+                    self.error(
+                        &target.location,
+                        "Type Error: tuple is immutable.".to_string(),
+                    );
+                }
+                let index_typ = self.infer(right);
+                // 切片是新的对象，给切片赋值没有意义
+                if index_typ.range_item().is_some() {
+                    self.error(
+                        &target.location,
+                        "Type Error: slice cannot be assigned.".to_string(),
+                    );
+                }
+                self.index_type(&typ, right, index_typ)
+            }
+            // 属性重新赋值后之前的检查失效，赋值的类型是声明的可选类型
+            ExpressionKind::Dot { .. } => {
+                if let Some(path) = assigned_path(target) {
+                    self.forget(&path);
+                }
+                self.infer(target)
+            }
+            _ => self.infer(target),
+        }
+    }
+
+    // 下标访问的结果类型，元组只有下标是整数字面量时才能确定
+    // 下标是范围时为切片，数组和字符串的切片类型不变
+    fn index_type(
+        &mut self,
+        typ: &NewTypeKind,
+        index: &Expression,
+        index_typ: NewTypeKind,
+    ) -> NewTypeKind {
+        if index_typ.range_item().is_some() {
+            return match typ {
+                NewTypeKind::Array(_) => typ.clone(),
+                NewTypeKind::Named(name) if name == "str" => typ.clone(),
+                _ if typ.is_any() => any(),
+                _ => {
+                    self.error(
+                        &index.location,
+                        format!("Type Error: {} cannot be sliced.", typ),
+                    );
+                    any()
+                }
+            };
+        }
+        let is_array = matches!(typ, NewTypeKind::Array(_) | NewTypeKind::Tuple(_));
+        if is_array && !(index_typ.is_integer() || index_typ.is_any()) {
+            self.error(
+                &index.location,
+                format!(
+                    "Type Error: index should be an integer, found {}.",
+                    index_typ
+                ),
+            );
+            return any();
+        }
+        match typ {
+            NewTypeKind::Array(item) => *item.clone(),
+            NewTypeKind::Map(key, value) => {
+                let literal = self.untyped_literal(index);
+                let index_typ = fit_literal(literal.as_ref(), &index_typ, key);
+                if !NewTypeKind::is_compatible(key, &index_typ) {
+                    self.error(
+                        &index.location,
+                        format!("Type Error: key should be {}, found {}.", key, index_typ),
+                    );
+                }
+                *value.clone()
+            }
+            NewTypeKind::Tuple(items) => match &index.node {
+                ExpressionKind::Liter {
+                    value: Literal::Integer(i),
+                } => match i.parse::<usize>() {
+                    Ok(i) if i < items.len() => items[i].clone(),
+                    _ => {
+                        self.error(
+                            &index.location,
+                            format!(
+                                "Index Error: index {} is out of range for length {}.",
+                                i,
+                                items.len()
+                            ),
+                        );
+                        any()
+                    }
+                },
+                _ => any(),
+            },
+            _ if typ.is_any() || self.get_struct(typ).is_some() => any(),
+            _ => {
+                self.error(
+                    &index.location,
+                    format!("Type Error: {} cannot be indexed.", typ),
+                );
+                any()
+            }
+        }
+    }
+
+    // 数值字面量的类型由后缀决定，超出范围时报错
+    fn literal_type(
+        &mut self,
+        location: &Location,
+        literal: Result<WesoObject, String>,
+    ) -> NewTypeKind {
+        match literal {
+            Ok(obj) => obj.get_typ().clone(),
+            Err(why) => {
+                self.error(location, why);
+                any()
+            }
+        }
+    }
+
+    // 推断表达式的类型，无法确定时为any
+    fn infer(&mut self, expr: &Expression) -> NewTypeKind {
+        let location = &expr.location;
+        match &expr.node {
+            ExpressionKind::Liter { value } => match value {
+                Literal::Integer(s) => self.literal_type(location, number::int_literal(s)),
+                Literal::Float(s) => self.literal_type(location, number::float_literal(s)),
+                Literal::String(_) => NewTypeKind::name("str"),
+                Literal::True | Literal::False => NewTypeKind::name("bool"),
+                Literal::Unit => NewTypeKind::name("unit"),
+                Literal::Null => NewTypeKind::name("null"),
+            },
+
+            ExpressionKind::Ident { value } => {
+                if self.is_moved(value) {
+                    self.error(
+                        location,
+                        format!("Variable Error: use after move of {}.", value),
+                    );
+                }
+                if let Some(typ) = self.lookup_var(value) {
+                    if !self.is_inited(value) {
+                        self.error(
+                            location,
+                            format!("Variable Error: {} is used before being assigned.", value),
+                        );
+                    }
+                    return match typ {
+                        NewTypeKind::Optional(item) if self.is_narrowed(value) => *item,
+                        _ => typ,
+                    };
+                }
+                // 没有数据的枚举变体
+                let variant = self.types().borrow().get_variant(value);
+                if let Some((typ, data)) = variant {
+                    return self.variant_type(value, &typ, &data, None, location);
+                }
+                // 函数名作为函数值使用
+                let signs = self.lookup_funcs(value);
+                match signs.as_slice() {
+                    [] if !self.is_defined(value) => {
+                        self.error(
+                            location,
+                            format!("Variable Error: {} is not defined.", value),
+                        );
+                        any()
+                    }
+                    [] => any(),
+                    [(params, rettyp)] => {
+                        NewTypeKind::Function(params.clone(), Box::new(rettyp.clone()))
+                    }
+                    _ => {
+                        self.error(
+                            location,
+                            format!(
+                                "Type Error: function {} is overloaded and cannot be used as a value.",
+                                value
+                            ),
+                        );
+                        any()
+                    }
+                }
+            }
+
+            // 模块导出的变量和函数
+            ExpressionKind::Dot { left, right } if self.lookup_module(left).is_some() => {
+                let (module, exports) = self.lookup_module(left).unwrap();
+                match &right.node {
+                    ExpressionKind::Ident { value } => {
+                        self.export_type(&module, &exports, value, location)
+                    }
+                    // 导出的类型已经替换成类型本身，剩下的是没有导出的类型
+                    ExpressionKind::Struct { name, .. } => {
+                        self.error(
+                            location,
+                            format!("Type Error: module {} does not export {}.", module, name),
+                        );
+                        any()
+                    }
+                    _ => {
+                        self.error(
+                            location,
+                            format!("Type Error: expected a name after module {}.", module),
+                        );
+                        any()
+                    }
+                }
+            }
+
+            ExpressionKind::Dot { left, right } => {
+                let typ = self.infer(left);
+                let typ = self.check_null(left, typ);
+                match &right.node {
+                    ExpressionKind::Ident { value } => match self.get_struct(&typ) {
+                        Some(fields) => match fields.get(value) {
+                            Some(NewTypeKind::Optional(item))
+                                if null_path(expr).is_some_and(|p| self.is_narrowed(&p)) =>
+                            {
+                                *item.clone()
+                            }
+                            Some(t) => t.clone(),
+                            None => {
+                                self.error(
+                                    location,
+                                    format!("Type Error: struct {} has no field {}.", typ, value),
+                                );
+                                any()
+                            }
+                        },
+                        // map的值只能通过下标访问
+                        None if matches!(typ, NewTypeKind::Map(..)) => {
+                            self.error(
+                                location,
+                                format!("Type Error: {} has no field {}.", typ, value),
+                            );
+                            any()
+                        }
+                        None => any(),
+                    },
+                    _ => {
+                        self.infer(right);
+                        any()
+                    }
+                }
+            }
+
+            ExpressionKind::Index { left, right } => {
+                let typ = self.infer(left);
+                let typ = self.check_null(left, typ);
+                let index_typ = self.infer(right);
+                self.index_type(&typ, right, index_typ)
+            }
+
+            ExpressionKind::Call { callee, args } => {
+                let literals = args
+                    .iter()
+                    .map(|arg| self.untyped_literal(arg))
+                    .collect::<Vec<_>>();
+                let mut types = args.iter().map(|a| self.infer(a)).collect::<Vec<_>>();
+                // 运算符的操作数不能为null，只有相等比较可以用于检查null
+                let operator = match &callee.node {
+                    ExpressionKind::Ident { value } => {
+                        is_operator(value) && self.lookup_funcs(value).is_empty()
+                    }
+                    _ => false,
+                };
+                if operator {
+                    for (arg, typ) in args.iter().zip(types.iter_mut()) {
+                        *typ = self.check_null(arg, typ.clone());
+                    }
+                }
+                if let (ExpressionKind::Ident { value }, Some(target)) =
+                    (&callee.node, args.first())
+                {
+                    if is_mutator(value) && self.lookup_funcs(value).is_empty() {
+                        self.check_mutable(target, true, location);
+                    }
+                }
+                let args = types;
+                // 内建函数和枚举变体不会执行其他代码，之前的检查仍然有效
+                let builtin = match &callee.node {
+                    ExpressionKind::Ident { value } => {
+                        self.lookup_funcs(value).is_empty()
+                            && (builtin_type(value, &args).is_some()
+                                || self.types().borrow().get_variant(value).is_some())
+                    }
+                    _ => false,
+                };
+                let typ = match &callee.node {
+                    // 变量中保存的函数值，同名的函数和内建函数优先
+                    ExpressionKind::Ident { value } => match self.lookup_var(value) {
+                        Some(_)
+                            if self.lookup_funcs(value).is_empty()
+                                && builtin_type(value, &args).is_none() =>
+                        {
+                            let typ = self.infer(callee);
+                            let typ = self.check_null(callee, typ);
+                            self.call_type(&typ, &args, &literals, location)
+                        }
+                        _ => self.check_call(value, &args, &literals, location),
+                    },
+                    // 调用模块导出的函数，按照模块中的重载选择
+                    ExpressionKind::Dot { left, right }
+                        if callee.is_method() && self.lookup_module(left).is_some() =>
+                    {
+                        let (module, exports) = self.lookup_module(left).unwrap();
+                        let name = right.to_operand().unwrap().clone();
+                        match exports.funcs.get(&name) {
+                            Some(signs) => {
+                                let full = format!("{}.{}", module, name);
+                                self.select_func(&full, signs, &args, &literals, location)
+                            }
+                            None => {
+                                let typ = self.export_type(&module, &exports, &name, location);
+                                self.call_type(&typ, &args, &literals, location)
+                            }
+                        }
+                    }
+                    ExpressionKind::Dot { left, right } if callee.is_method() => {
+                        let typ = self.infer(left);
+                        let typ = self.check_null(left, typ);
+                        let name = right.to_operand().unwrap().clone();
+                        self.method_type(&typ, &name, &args, &literals, location)
+                    }
+                    _ => {
+                        let typ = self.infer(callee);
+                        let typ = self.check_null(callee, typ);
+                        self.call_type(&typ, &args, &literals, location)
+                    }
+                };
+                if !builtin {
+                    self.forget_after_call();
+                }
+                typ
+            }
+
+            // 右侧只在左侧为真(&&)或者为假(||)时执行
+            ExpressionKind::And { left, right } | ExpressionKind::Or { left, right } => {
+                self.check_cond(left);
+                let (yes, no) = null_checks(left);
+                self.enter();
+                let narrowed = match &expr.node {
+                    ExpressionKind::And { .. } => yes,
+                    _ => no,
+                };
+                self.frame().narrowed.extend(narrowed);
+                self.check_cond(right);
+                self.leave();
+                NewTypeKind::name("bool")
+            }
+
+            ExpressionKind::Not { expr } => {
+                self.check_cond(expr);
+                NewTypeKind::name("bool")
+            }
+
+            ExpressionKind::Struct { name, args } => {
+                let values = args
+                    .iter()
+                    .map(|(key, value)| (key.clone(), self.infer(value)))
+                    .collect::<Vec<(String, NewTypeKind)>>();
+                // 结构体形式的枚举变体，字段由变体定义，值的类型为枚举
+                let variant = self.types().borrow().get_variant(name);
+                match variant {
+                    Some((typ, NewTypeKind::Struct(fields))) => {
+                        let variant = NewTypeKind::name(name);
+                        let fields = fields.into_iter().collect();
+                        self.check_fields(&variant, &fields, args, &values, location);
+                        return NewTypeKind::name(&typ);
+                    }
+                    Some(_) => {
+                        self.error(location, format!("Type Error: {} is not a struct.", name));
+                        return any();
+                    }
+                    None => (),
+                }
+                let mut typ = self.resolve(&NewTypeKind::name(name), location);
+                // 泛型结构体的泛型参数由字段的值推断
+                let params = self.types().borrow().get_generics(&typ.to_string());
+                if !params.is_empty() {
+                    typ = self.infer_generics(&typ.to_string(), params, &values, location);
+                }
+                match self.get_struct(&typ) {
+                    Some(fields) => self.check_fields(&typ, &fields, args, &values, location),
+                    None => {
+                        if !typ.is_any() {
+                            self.error(location, format!("Type Error: {} is not a struct.", name));
+                        }
+                        return any();
+                    }
+                }
+                typ
+            }
+
+            ExpressionKind::Function {
+                args,
+                rettyp,
+                block,
+            } => {
+                let args = args
+                    .iter()
+                    .map(|(arg, typ)| (arg.clone(), self.resolve(typ, location)))
+                    .collect::<Vec<(String, NewTypeKind)>>();
+                let rettyp = self.resolve(rettyp, location);
+                let typ = NewTypeKind::Function(
+                    args.iter().map(|a| a.1.clone()).collect(),
+                    Box::new(rettyp.clone()),
+                );
+                self.check_function(&[], args, rettyp, block, location);
+                typ
+            }
+
+            ExpressionKind::Match { value, arms } => self.check_match(value, arms, location),
+
+            ExpressionKind::Block { block } => {
+                self.enter();
+                self.check_block(block);
+                self.leave();
+                NewTypeKind::name("unit")
+            }
+
+            ExpressionKind::Question { test, then, orelse } => {
+                self.check_cond(test);
+                let (yes, no) = null_checks(test);
+                self.enter();
+                self.frame().narrowed.extend(yes);
+                let then = self.infer(then);
+                self.leave();
+                self.enter();
+                self.frame().narrowed.extend(no);
+                let orelse = self.infer(orelse);
+                self.leave();
+                // 一侧为null时结果为可选类型
+                match (then, orelse) {
+                    (then, orelse) if then == orelse => then,
+                    (typ, null) | (null, typ) if null.is_null() && !typ.is_any() => match typ {
+                        NewTypeKind::Optional(_) => typ,
+                        _ => NewTypeKind::Optional(Box::new(typ)),
+                    },
+                    _ => any(),
+                }
+            }
+        }
+    }
+}
@@ -1,16 +1,18 @@
 // 将ast转换成指令操作集
-use crate::base::ast::{Expression, ExpressionKind, StatementKind, StmtList};
+use crate::base::ast::{Expression, ExpressionKind, Pattern, Statement, StatementKind, StmtList};
 use crate::base::func::{FuncManager, Function};
 use crate::base::opcode::{InnerInstruction, Instruction, Operand};
+use crate::base::types::{NewTypeKind, TypeScope};
 use crate::parser::lexer::Location;
+use std::cell::RefCell;
 use std::collections::HashSet;
-// use std::rc::Rc;
+use std::rc::Rc;
 
 // 检查expression并将其放入列表指令中
 macro_rules! check_expr_and_append {
-    ($ex:expr, $v:expr, $idx:expr) => {
+    ($ex:expr, $v:expr, $idx:expr, $types:expr) => {
         if $ex.not_simple() {
-            let res = parse_expr($ex, $idx);
+            let res = parse_expr($ex, $idx, $types);
             if res.is_err() {
                 return Err(res.err().unwrap());
             }
@@ -22,8 +24,8 @@ macro_rules! check_expr_and_append {
 }
 
 macro_rules! handle_expr_parse_err {
-    ($ex:expr, $idx:expr) => {
-        match parse_expr($ex, $idx) {
+    ($ex:expr, $idx:expr, $types:expr) => {
+        match parse_expr($ex, $idx, $types) {
             Ok(t) => t,
             Err(e) => return Err(e),
         }
@@ -39,28 +41,208 @@ macro_rules! instruction {
     };
 }
 
+// 解析类型，出错时附带位置信息
+fn resolve_type(
+    types: &Rc<RefCell<TypeScope>>,
+    typ: &NewTypeKind,
+    location: &Location,
+) -> Result<NewTypeKind, String> {
+    types
+        .borrow()
+        .resolve(typ)
+        .map(|typ| typ.erase())
+        .map_err(|e| format!("{} ({})", e, location))
+}
+
+// 创建块内的类型作用域
+fn child_scope(types: &Rc<RefCell<TypeScope>>) -> Rc<RefCell<TypeScope>> {
+    Rc::new(RefCell::new(TypeScope::new(Some(types.clone()))))
+}
+
+// 块内还未处理的break和continue需要多离开一层作用域
+fn deepen(list: &mut [Instruction]) {
+    for ins in list.iter_mut() {
+        match &mut ins.node {
+            InnerInstruction::Break { depth } | InnerInstruction::Continue { depth } => *depth += 1,
+            _ => {}
+        }
+    }
+}
+
+// 在新的作用域中解析语句块
+//   n: enter
+//        ...
+//   m: leave
+fn parse_block(
+    stmts: &StmtList,
+    begin: usize,
+    types: &Rc<RefCell<TypeScope>>,
+    location: &Location,
+) -> Result<Vec<Instruction>, String> {
+    let mut list = vec![instruction!(location, InnerInstruction::Enter)];
+    let mut block = parse_stmts(stmts, begin + 1, &child_scope(types))?;
+    deepen(&mut block);
+    list.append(&mut block);
+    list.push(instruction!(location, InnerInstruction::Leave));
+    Ok(list)
+}
+
+// 编译函数体并注册到全局的函数管理器中，返回函数的编号和签名
+fn parse_function(
+    generics: &[String],
+    args: &[(String, NewTypeKind)],
+    rettyp: &NewTypeKind,
+    block: &StmtList,
+    types: &Rc<RefCell<TypeScope>>,
+    location: &Location,
+) -> Result<(usize, String), String> {
+    // 泛型参数在运行时作为any
+    let types = &child_scope(types);
+    types.borrow_mut().define_params(generics);
+    let mut new_args = vec![];
+    for (arg, typ) in args {
+        new_args.push((arg.clone(), resolve_type(types, typ, location)?));
+    }
+    let rettyp = resolve_type(types, rettyp, location)?;
+    let codes = parse_stmts(block, 0, &child_scope(types))?;
+    let sign = FuncManager::get_sign(&new_args);
+    let id = FuncManager::get_ins().register(Function {
+        // name: name.clone(),
+        args: new_args,
+        rettyp,
+        codes,
+    });
+    Ok((id, sign))
+}
+
+// 将模式中结构体的别名替换成结构体的名称
+fn resolve_pattern(
+    pattern: &Pattern,
+    types: &Rc<RefCell<TypeScope>>,
+    location: &Location,
+) -> Result<Pattern, String> {
+    let resolve_all = |items: &[Pattern]| {
+        items
+            .iter()
+            .map(|item| resolve_pattern(item, types, location))
+            .collect::<Result<Vec<Pattern>, String>>()
+    };
+    Ok(match pattern {
+        Pattern::Tuple(items) => Pattern::Tuple(resolve_all(items)?),
+        Pattern::Array { items, rest } => Pattern::Array {
+            items: resolve_all(items)?,
+            rest: *rest,
+        },
+        Pattern::Or(items) => Pattern::Or(resolve_all(items)?),
+        Pattern::Variant {
+            name,
+            items: Some(items),
+        } => Pattern::Variant {
+            name: name.clone(),
+            items: Some(resolve_all(items)?),
+        },
+        Pattern::Struct { name, fields } => {
+            let mut new_fields = vec![];
+            for (key, item) in fields {
+                new_fields.push((key.clone(), resolve_pattern(item, types, location)?));
+            }
+            let variant = types.borrow().get_variant(name);
+            let name = match variant {
+                // 省略名称时在运行时匹配值本身的结构体
+                _ if name.is_empty() => String::new(),
+                Some((_, NewTypeKind::Struct(_))) => name.clone(),
+                _ => match resolve_type(types, &NewTypeKind::name(name), location)? {
+                    NewTypeKind::Named(value) if types.borrow().get_struct(&value).is_some() => {
+                        value
+                    }
+                    _ => {
+                        return Err(format!(
+                            "Type Error: {} is not a struct. ({})",
+                            name, location
+                        ))
+                    }
+                },
+            };
+            Pattern::Struct {
+                name,
+                fields: new_fields,
+            }
+        }
+        _ => pattern.clone(),
+    })
+}
+
+// 将循环体中的break和continue替换成跳转
+fn resolve_loop(list: &mut [Instruction], break_addr: usize, continue_addr: usize) {
+    for ins in list.iter_mut() {
+        let (addr, depth) = match ins.node {
+            InnerInstruction::Break { depth } => (break_addr, depth),
+            InnerInstruction::Continue { depth } => (continue_addr, depth),
+            _ => continue,
+        };
+        ins.node = InnerInstruction::Exit { addr, depth };
+    }
+}
+
 // 将语句转成指令集，并收集函数定义、结构体定义
 #[allow(unused)]
-pub fn parse_stmts(stmts: &StmtList, begin: usize) -> Result<Vec<Instruction>, String> {
+pub fn parse_stmts(
+    stmts: &StmtList,
+    begin: usize,
+    types: &Rc<RefCell<TypeScope>>,
+) -> Result<Vec<Instruction>, String> {
     let mut list = vec![];
     let mut index;
     let mut variables = HashSet::new();
 
-    for stmt in stmts {
+    // 预先登记块内的结构体和枚举名称
+    for stmt in stmts.iter().map(Statement::definition) {
+        if let StatementKind::TypeDef {
+            left,
+            right,
+            generics,
+        } = &stmt.node
+        {
+            if !matches!(right, NewTypeKind::Struct(_) | NewTypeKind::Enum(_)) {
+                continue;
+            }
+            if types.borrow().is_defined(left) {
+                return Err(format!(
+                    "Type Error: {} has been defined. ({})",
+                    left, stmt.location
+                ));
+            }
+            match right {
+                NewTypeKind::Enum(_) => types.borrow_mut().define_enum(left, vec![]),
+                _ => {
+                    types.borrow_mut().declare_struct(left);
+                    if !generics.is_empty() {
+                        types.borrow_mut().define_generics(left, generics);
+                    }
+                }
+            }
+        }
+    }
+
+    // 导出的定义和普通的定义相同
+    for stmt in stmts.iter().map(Statement::definition) {
         index = list.len() + begin;
         let location = &stmt.location;
 
         match &stmt.node {
             StatementKind::Break => {
-                list.push(instruction!(location, InnerInstruction::Break));
+                list.push(instruction!(location, InnerInstruction::Break { depth: 0 }));
             }
 
             StatementKind::Continue => {
-                list.push(instruction!(location, InnerInstruction::Continue));
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::Continue { depth: 0 }
+                ));
             }
 
             StatementKind::Return { value } => {
-                check_expr_and_append!(value, list, index);
+                check_expr_and_append!(value, list, index, types);
                 // check_symbol_is_defined!(scope.clone(), &value);
                 list.push(instruction!(
                     location,
@@ -71,8 +253,8 @@ pub fn parse_stmts(stmts: &StmtList, begin: usize) -> Result<Vec<Instruction>, S
             }
 
             StatementKind::Assign { left, right } => {
-                check_expr_and_append!(left, list, index);
-                check_expr_and_append!(right, list, index);
+                check_expr_and_append!(left, list, index, types);
+                check_expr_and_append!(right, list, index, types);
                 // check_symbol_is_defined!(scope.clone(), left);
                 // check_symbol_is_defined!(scope.clone(), right);
                 list.push(instruction!(
@@ -85,8 +267,8 @@ pub fn parse_stmts(stmts: &StmtList, begin: usize) -> Result<Vec<Instruction>, S
             }
 
             StatementKind::Move { left, right } => {
-                check_expr_and_append!(left, list, index);
-                check_expr_and_append!(right, list, index);
+                check_expr_and_append!(left, list, index, types);
+                check_expr_and_append!(right, list, index, types);
                 // check_symbol_is_defined!(scope.clone(), left);
                 // check_symbol_is_defined!(scope.clone(), right);
                 list.push(instruction!(
@@ -101,7 +283,7 @@ pub fn parse_stmts(stmts: &StmtList, begin: usize) -> Result<Vec<Instruction>, S
             StatementKind::AugAssign { op, left, right } => {
                 if left.not_simple() {
                     // check_symbol_is_defined!(scope.clone(), left);
-                    let mut left_vec = handle_expr_parse_err!(left, index);
+                    let mut left_vec = handle_expr_parse_err!(left, index, types);
                     index += left_vec.len();
                     list.append(&mut left_vec);
                     list.push(instruction!(location, InnerInstruction::Repeat)); // 拷贝栈顶的值
@@ -118,7 +300,7 @@ pub fn parse_stmts(stmts: &StmtList, begin: usize) -> Result<Vec<Instruction>, S
                 // check_expr_and_append!(right, list, scope.clone(), index);
                 if right.not_simple() {
                     // check_symbol_is_defined!(scope.clone(), right);
-                    let mut right_vec = handle_expr_parse_err!(right, index);
+                    let mut right_vec = handle_expr_parse_err!(right, index, types);
                     // index += right_vec.len();
                     list.append(&mut right_vec);
                 } else {
@@ -158,19 +340,22 @@ pub fn parse_stmts(stmts: &StmtList, begin: usize) -> Result<Vec<Instruction>, S
                 if variables.get(name).is_some() {
                     return Err(format!("Variable Error: {} has been defined.", name));
                 }
-                variables.insert(name);
+                variables.insert(name.clone());
                 list.push(instruction!(
                     location,
                     InnerInstruction::DefVar {
                         mutable: *mutable,
                         name: Operand::Var(name.clone()),
-                        typ: typ.clone(),
+                        typ: match typ {
+                            Some(typ) => Some(resolve_type(types, typ, location)?),
+                            None => None,
+                        },
                     }
                 ));
                 index += 1;
                 // 定义变量
                 if let Some(expr) = assign {
-                    check_expr_and_append!(expr, list, index);
+                    check_expr_and_append!(expr, list, index, types);
                     // check_symbol_is_defined!(scope.clone(), expr);
                     list.push(instruction!(
                         location,
@@ -182,77 +367,164 @@ pub fn parse_stmts(stmts: &StmtList, begin: usize) -> Result<Vec<Instruction>, S
                 }
             }
 
-            // ToDo
-            StatementKind::TypeDef { left, right } => {
-                // let typeid = scope.borrow_mut().types.insert(right);
-                // if let Some(id) = typeid {
-                //     scope.borrow_mut().types.insert_alias(left, id);
-                // } else {
-                //     // 在当前作用域找不到的话，在不断在父作用域中寻找
-                //     let mut p = scope.clone();
-                //     loop {
-                //         let p_scope = p.borrow().parent.clone();
-                //         if let Some(tmp) = p_scope {
-                //             println!("get scope {:?}", tmp.clone());
-                //             if let Some(typ) = tmp.clone().borrow().types.get_item_by_key(left)
-                //             {
-                //                 println!("log1 {:?}", typ);
-                //                 let id = scope.borrow_mut().types.insert_kind(typ);
-                //                 scope.borrow_mut().types.insert_alias(left, id);
-                //                 break;
-                //             } else {
-                //                 println!("log2 ");
-                //                 p = tmp;
-                //             }
-                //         } else {
-                //             return Err(format!(
-                //                 "Type Error: {} is undefined. ({})",
-                //                 right.to_string(),
-                //                 stmt.location,
-                //             ));
-                //         }
-                //     }
-                // }
+            StatementKind::Destruct {
+                mutable,
+                pattern,
+                value,
+            } => {
+                for name in pattern.bindings() {
+                    if variables.contains(&name) {
+                        return Err(format!("Variable Error: {} has been defined.", name));
+                    }
+                    variables.insert(name);
+                }
+                check_expr_and_append!(value, list, index, types);
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::Destruct {
+                        mutable: *mutable,
+                        value: value.to_operand(),
+                        pattern: resolve_pattern(pattern, types, location)?,
+                    }
+                ));
             }
 
-            // ToDo
-            // StatementKind::StructDef { name, value } => {
-            //     // scope.borrow_mut().types.insert_struct(name, value.clone());
-            //     StructManager::get_ins().register(name.clone(), value.clone());
-            // }
+            // 类型别名和结构体定义
+            StatementKind::TypeDef {
+                left,
+                right,
+                generics,
+            } => match right {
+                NewTypeKind::Struct(_) => {
+                    // 结构体名称已经预先登记，字段可以引用自身
+                    let scope = child_scope(types);
+                    scope.borrow_mut().define_params(generics);
+                    let typ = resolve_type(&scope, right, location)?;
+                    if let NewTypeKind::Struct(fields) = &typ {
+                        types.borrow_mut().define_struct(left, fields.clone());
+                    }
+                    list.push(instruction!(
+                        location,
+                        InnerInstruction::DefType {
+                            name: left.clone(),
+                            typ,
+                        }
+                    ));
+                }
+                // 枚举在运行时用于构造变体
+                NewTypeKind::Enum(_) => {
+                    let typ = resolve_type(types, right, location)?;
+                    if let NewTypeKind::Enum(variants) = &typ {
+                        types.borrow_mut().define_enum(left, variants.clone());
+                    }
+                    list.push(instruction!(
+                        location,
+                        InnerInstruction::DefType {
+                            name: left.clone(),
+                            typ,
+                        }
+                    ));
+                }
+                _ => {
+                    if types.borrow().is_defined(left) {
+                        return Err(format!(
+                            "Type Error: {} has been defined. ({})",
+                            left, location
+                        ));
+                    }
+                    let typ = resolve_type(types, right, location)?;
+                    types.borrow_mut().define_alias(left, typ);
+                }
+            },
 
             // 定义一个函数，将函数放到全局的管理中
             StatementKind::FuncDef {
                 name,
+                generics,
                 args,
                 rettyp,
                 block,
             } => {
-                match parse_stmts(&block, 0) {
-                    Ok(codes) => {
-                        // 注册该函数
-                        let id = FuncManager::get_ins().register(Function {
-                            // name: name.clone(),
-                            args: args.clone(),
-                            rettyp: rettyp.clone(),
-                            codes,
-                        });
-                        // 添加一行指令
+                let (id, sign) = parse_function(generics, args, rettyp, block, types, location)?;
+                // 添加一行指令
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::DefFunc {
+                        name: name.clone(),
+                        id,
+                        sign,
+                    }
+                ));
+            }
+
+            // 方法以 T::name 的名称注册，方法的self参数类型为T
+            StatementKind::Impl {
+                name,
+                trait_name,
+                methods,
+            } => {
+                if types.borrow().get_struct(name).is_none() {
+                    return Err(format!(
+                        "Type Error: {} is not a struct. ({})",
+                        name, location
+                    ));
+                }
+                // 先登记实现关系，使方法中可以将self作为trait类型使用
+                if let Some(trait_name) = trait_name {
+                    if types.borrow().get_trait(trait_name).is_none() {
+                        return Err(format!(
+                            "Type Error: {} is not a trait. ({})",
+                            trait_name, location
+                        ));
+                    }
+                    types.borrow_mut().define_impl(name, trait_name);
+                    list.push(instruction!(
+                        location,
+                        InnerInstruction::DefImpl {
+                            typ: name.clone(),
+                            name: trait_name.clone(),
+                        }
+                    ));
+                }
+                for method in methods {
+                    if let StatementKind::FuncDef {
+                        name: method_name,
+                        generics,
+                        args,
+                        rettyp,
+                        block,
+                    } = &method.node
+                    {
+                        let (id, sign) =
+                            parse_function(generics, args, rettyp, block, types, &method.location)?;
                         list.push(instruction!(
-                            location,
+                            method.location,
                             InnerInstruction::DefFunc {
-                                name: name.clone(),
+                                name: format!("{}::{}", name, method_name),
                                 id,
-                                sign: FuncManager::get_sign(args),
+                                sign,
                             }
                         ));
                     }
-                    Err(s) => return Err(s),
-                };
+                }
+            }
+
+            // trait只在编译期使用，运行时按照对象的类型调用方法
+            StatementKind::Trait { name, methods } => {
+                if types.borrow().is_defined(name) {
+                    return Err(format!(
+                        "Type Error: {} has been defined. ({})",
+                        name, location
+                    ));
+                }
+                let typ = NewTypeKind::Trait(methods.iter().cloned().collect());
+                if let NewTypeKind::Trait(methods) = resolve_type(types, &typ, location)? {
+                    types.borrow_mut().define_trait(name, methods);
+                }
             }
 
             StatementKind::Expression { expr } => {
-                let mut tmp_vec = handle_expr_parse_err!(expr, index);
+                let mut tmp_vec = handle_expr_parse_err!(expr, index, types);
                 list.append(&mut tmp_vec);
             }
 
@@ -263,13 +535,10 @@ pub fn parse_stmts(stmts: &StmtList, begin: usize) -> Result<Vec<Instruction>, S
                 // m+1:  ...       // else statement
                 //   x:  ...       // out of if statement
 
-                check_expr_and_append!(test, list, index); // 插入test语句
+                check_expr_and_append!(test, list, index, types); // 插入test语句
                 index += 1; // if语句的位置
 
-                let mut then_vec = match parse_stmts(&then, index) {
-                    Ok(v) => v,
-                    Err(e) => return Err(e),
-                };
+                let mut then_vec = parse_block(then, index, types, location)?;
                 index += then_vec.len();
 
                 // 根据有无else来获得跳转地址
@@ -289,10 +558,7 @@ pub fn parse_stmts(stmts: &StmtList, begin: usize) -> Result<Vec<Instruction>, S
 
                 if orelse.len() > 0 {
                     index += 1; // 插入else的goto
-                    let mut else_vec = match parse_stmts(&orelse, index) {
-                        Ok(v) => v,
-                        Err(e) => return Err(e),
-                    };
+                    let mut else_vec = parse_block(orelse, index, types, location)?;
                     index += else_vec.len();
                     // 在else语句前加一个goto用于跳出整个else语句块
                     list.push(instruction!(
@@ -306,18 +572,17 @@ pub fn parse_stmts(stmts: &StmtList, begin: usize) -> Result<Vec<Instruction>, S
             StatementKind::While { test, then } => {
                 //   a: test expr
                 //   n: if $0 m+1
+                //      enter
                 //        ...
+                //      leave
                 //   m: goto a
                 let beg_addr = index;
 
-                check_expr_and_append!(test, list, index); // 插入test语句
+                check_expr_and_append!(test, list, index, types); // 插入test语句
 
                 index += 1; // if语句的位置
 
-                let mut then_vec = match parse_stmts(&then, index) {
-                    Ok(v) => v,
-                    Err(e) => return Err(e),
-                };
+                let mut then_vec = parse_block(then, index, types, location)?;
                 index += then_vec.len();
 
                 // 插入IF
@@ -330,17 +595,7 @@ pub fn parse_stmts(stmts: &StmtList, begin: usize) -> Result<Vec<Instruction>, S
                 ));
 
                 // 替换掉出现的break和continue
-                for i in then_vec.iter_mut() {
-                    match i.node {
-                        InnerInstruction::Break => {
-                            *i = instruction!(location, InnerInstruction::Goto { addr: index + 1 })
-                        }
-                        InnerInstruction::Continue => {
-                            *i = instruction!(location, InnerInstruction::Goto { addr: beg_addr })
-                        }
-                        _ => {}
-                    }
-                }
+                resolve_loop(&mut then_vec, index + 1, beg_addr);
 
                 // 插入then
                 list.append(&mut then_vec);
@@ -351,24 +606,155 @@ pub fn parse_stmts(stmts: &StmtList, begin: usize) -> Result<Vec<Instruction>, S
                 ));
             }
 
-            // ToDo
-            StatementKind::For { name, iter, then } => {}
+            StatementKind::For {
+                pattern,
+                iter,
+                then,
+            } => {
+                //   a: iter expr
+                //   n: iter $0
+                // n+1: next #m+1     // 迭代结束时跳出
+                //      enter         // 每次迭代都是新的作用域
+                //      let pattern $0
+                //        ...
+                //      leave
+                //   m: goto n+1
+                // m+1: enditer
+                check_expr_and_append!(iter, list, index, types); // 插入iter语句
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::Iter {
+                        value: iter.to_operand(),
+                    }
+                ));
+                index += 1;
+
+                let pattern = resolve_pattern(pattern, types, location)?;
+                let next_addr = index;
+                index += 3; // next, enter, let
+
+                let mut then_vec = parse_stmts(then, index, &child_scope(types))?;
+                deepen(&mut then_vec);
+                index += then_vec.len() + 1; // leave
+
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::Next { addr: index + 1 }
+                ));
+                list.push(instruction!(location, InnerInstruction::Enter));
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::Destruct {
+                        mutable: true,
+                        value: Operand::Stack,
+                        pattern,
+                    }
+                ));
+
+                // 替换掉出现的break和continue
+                resolve_loop(&mut then_vec, index + 1, next_addr);
+
+                list.append(&mut then_vec);
+                list.push(instruction!(location, InnerInstruction::Leave));
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::Goto { addr: next_addr }
+                ));
+                list.push(instruction!(location, InnerInstruction::EndIter));
+            }
+
+            StatementKind::Try { then, name, catch } => {
+                //   n: enter
+                // n+1: try #m+1          // 出错时离开try的作用域，错误放入栈中
+                //        ...
+                //      leave
+                //   m: goto x
+                // m+1: enter
+                //      const name: error
+                //      assign name $0
+                //        ...
+                //      leave
+                //   x: ...
+                index += 2; // enter, try
+                let mut then_vec = parse_stmts(then, index, &child_scope(types))?;
+                deepen(&mut then_vec);
+                index += then_vec.len() + 2; // leave, goto
+
+                let catch_addr = index;
+                index += 3; // enter, const, assign
+                let mut catch_vec = parse_stmts(catch, index, &child_scope(types))?;
+                deepen(&mut catch_vec);
+                index += catch_vec.len() + 1; // leave
+
+                list.push(instruction!(location, InnerInstruction::Enter));
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::Try { addr: catch_addr }
+                ));
+                list.append(&mut then_vec);
+                list.push(instruction!(location, InnerInstruction::Leave));
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::Goto { addr: index }
+                ));
+
+                // 没有名称时错误保存在$error中
+                let name = Operand::Var(name.clone().unwrap_or_else(|| "$error".to_string()));
+                list.push(instruction!(location, InnerInstruction::Enter));
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::DefVar {
+                        mutable: false,
+                        name: name.clone(),
+                        typ: Some(NewTypeKind::name("error")),
+                    }
+                ));
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::Assign {
+                        lhs: name,
+                        rhs: Operand::Stack,
+                    }
+                ));
+                list.append(&mut catch_vec);
+                list.push(instruction!(location, InnerInstruction::Leave));
+            }
+
+            StatementKind::Throw { value } => {
+                check_expr_and_append!(value, list, index, types);
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::Throw {
+                        value: value.to_operand(),
+                    }
+                ));
+            }
+
+            StatementKind::Import { path, name } => {
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::Import {
+                        path: path.clone(),
+                        name: name.clone(),
+                    }
+                ));
+            }
+
+            // 导出语句已经展开成定义
+            StatementKind::Export { .. } => {}
         }
     }
 
-    // 销毁在该块创建的变量
-    for var in variables {
-        list.push(instruction!(
-            Location::default(),
-            InnerInstruction::Destroy { value: var.clone() }
-        ))
-    }
     Ok(list)
 }
 
 // 将表达式转换成
 #[allow(unused)]
-fn parse_expr(expr: &Box<Expression>, begin: usize) -> Result<Vec<Instruction>, String> {
+fn parse_expr(
+    expr: &Box<Expression>,
+    begin: usize,
+    types: &Rc<RefCell<TypeScope>>,
+) -> Result<Vec<Instruction>, String> {
     let mut list = Vec::new();
     let mut index = begin;
     let location = &expr.location;
@@ -389,8 +775,8 @@ fn parse_expr(expr: &Box<Expression>, begin: usize) -> Result<Vec<Instruction>,
         )),
 
         ExpressionKind::Dot { left, right } => {
-            check_expr_and_append!(left, list, index);
-            check_expr_and_append!(right, list, index);
+            check_expr_and_append!(left, list, index, types);
+            check_expr_and_append!(right, list, index, types);
             // check_symbol_is_defined!(scope.clone(), left);
             list.push(instruction!(
                 location,
@@ -401,10 +787,46 @@ fn parse_expr(expr: &Box<Expression>, begin: usize) -> Result<Vec<Instruction>,
             ))
         }
 
+        ExpressionKind::Index { left, right } => {
+            check_expr_and_append!(left, list, index, types);
+            // 下标总是先求值放入栈中，避免变量名被当作属性名
+            let mut tmp_vec = handle_expr_parse_err!(right, index, types);
+            index += tmp_vec.len();
+            list.append(&mut tmp_vec);
+            list.push(instruction!(
+                location,
+                InnerInstruction::Dot {
+                    lhs: left.to_operand(),
+                    rhs: Operand::Stack,
+                }
+            ))
+        }
+
+        // 方法调用 a.f()，接收者先入栈，然后是参数
+        ExpressionKind::Call { callee, args } if callee.is_method() => {
+            if let ExpressionKind::Dot { left, right } = &callee.node {
+                let mut tmp_vec = handle_expr_parse_err!(left, index, types);
+                index += tmp_vec.len();
+                list.append(&mut tmp_vec);
+                for arg in args {
+                    let mut arg_vec = handle_expr_parse_err!(arg, index, types);
+                    index += arg_vec.len();
+                    list.append(&mut arg_vec);
+                }
+                list.push(instruction!(
+                    location,
+                    InnerInstruction::Method {
+                        name: right.to_operand().unwrap().clone(),
+                        num: args.len(),
+                    }
+                ))
+            }
+        }
+
         ExpressionKind::Call { callee, args } => {
-            check_expr_and_append!(callee, list, index);
+            check_expr_and_append!(callee, list, index, types);
             for arg in args {
-                let res = parse_expr(arg, index);
+                let res = parse_expr(arg, index, types);
                 if res.is_err() {
                     return Err(res.err().unwrap());
                 }
@@ -421,11 +843,21 @@ fn parse_expr(expr: &Box<Expression>, begin: usize) -> Result<Vec<Instruction>,
             ))
         }
 
+        // 匿名函数在编译时注册，运行时只创建函数值
+        ExpressionKind::Function {
+            args,
+            rettyp,
+            block,
+        } => {
+            let (id, _) = parse_function(&[], args, rettyp, block, types, location)?;
+            list.push(instruction!(location, InnerInstruction::Lambda { id }));
+        }
+
         ExpressionKind::And { left, right } => {
-            check_expr_and_append!(left, list, index);
+            check_expr_and_append!(left, list, index, types);
             index += 1; // if 语句本身占一条
             let mut tmp_vec = {
-                let res = parse_expr(right, index);
+                let res = parse_expr(right, index, types);
                 if res.is_err() {
                     return Err(res.err().unwrap());
                 }
@@ -458,10 +890,10 @@ fn parse_expr(expr: &Box<Expression>, begin: usize) -> Result<Vec<Instruction>,
 
         ExpressionKind::Or { left, right } => {
             // check_symbol_is_defined!(scope.clone(), left);
-            check_expr_and_append!(left, list, index);
+            check_expr_and_append!(left, list, index, types);
             index += 1;
             // check_symbol_is_defined!(scope.clone(), right);
-            let mut tmp_vec = handle_expr_parse_err!(right, index);
+            let mut tmp_vec = handle_expr_parse_err!(right, index, types);
             index += tmp_vec.len();
             // Ifnot
             list.push(instruction!(
@@ -489,7 +921,7 @@ fn parse_expr(expr: &Box<Expression>, begin: usize) -> Result<Vec<Instruction>,
 
         ExpressionKind::Not { expr } => {
             // check_symbol_is_defined!(scope.clone(), expr);
-            check_expr_and_append!(expr, list, index);
+            check_expr_and_append!(expr, list, index, types);
             list.push(instruction!(
                 location,
                 InnerInstruction::Not {
@@ -498,15 +930,136 @@ fn parse_expr(expr: &Box<Expression>, begin: usize) -> Result<Vec<Instruction>,
             ));
         }
 
+        // 匹配的值保存在新作用域的变量$match中，每个分支在单独的作用域中绑定变量
+        //      enter
+        //      def $match
+        //   n: enter
+        //      match $match pattern
+        //      if $0 m
+        //      if guard m
+        //      ...             // 分支的值放入栈中
+        //      leave
+        //      goto x
+        //   m: leave
+        //      ...             // 其余的分支
+        //      fail
+        //   x: leave
+        ExpressionKind::Match { value, arms } => {
+            check_expr_and_append!(value, list, index, types);
+            let matched = Operand::Var(String::from("$match"));
+            let mut inner = vec![
+                instruction!(
+                    location,
+                    InnerInstruction::DefVar {
+                        mutable: false,
+                        name: matched.clone(),
+                        typ: Some(NewTypeKind::name("any")),
+                    }
+                ),
+                instruction!(
+                    location,
+                    InnerInstruction::Assign {
+                        lhs: matched.clone(),
+                        rhs: value.to_operand(),
+                    }
+                ),
+            ];
+            index += 3; // 插入enter、def和assign
+            let mut gotos = vec![];
+            for arm in arms {
+                let arm_location = &arm.location;
+                let pattern = resolve_pattern(&arm.node.pattern, types, arm_location)?;
+                let mut code = vec![
+                    instruction!(arm_location, InnerInstruction::Enter),
+                    instruction!(
+                        arm_location,
+                        InnerInstruction::Match {
+                            value: matched.clone(),
+                            pattern,
+                        }
+                    ),
+                ];
+                // if指令的跳转地址在分支生成之后填写
+                let mut tests = vec![code.len()];
+                code.push(instruction!(
+                    arm_location,
+                    InnerInstruction::If {
+                        value: Operand::Stack,
+                        addr: 0,
+                    }
+                ));
+                let mut arm_index = index + code.len();
+                if let Some(guard) = &arm.node.guard {
+                    let mut guard_vec = vec![];
+                    check_expr_and_append!(guard, guard_vec, arm_index, types);
+                    code.append(&mut guard_vec);
+                    tests.push(code.len());
+                    code.push(instruction!(
+                        guard.location,
+                        InnerInstruction::If {
+                            value: guard.to_operand(),
+                            addr: 0,
+                        }
+                    ));
+                    arm_index += 1;
+                }
+                let mut body = handle_expr_parse_err!(&arm.node.body, arm_index, types);
+                code.append(&mut body);
+                code.push(instruction!(arm_location, InnerInstruction::Leave));
+                gotos.push(inner.len() + code.len());
+                code.push(instruction!(
+                    arm_location,
+                    InnerInstruction::Goto { addr: 0 }
+                ));
+                let fail_addr = index + code.len();
+                for i in tests {
+                    if let InnerInstruction::If { addr, .. } = &mut code[i].node {
+                        *addr = fail_addr;
+                    }
+                }
+                code.push(instruction!(arm_location, InnerInstruction::Leave));
+                // 分支内的break和continue需要离开分支的作用域
+                deepen(&mut code);
+                index += code.len();
+                inner.append(&mut code);
+            }
+            inner.push(instruction!(
+                location,
+                InnerInstruction::Fail {
+                    message: String::from("Runtime Error: no match arm matches the value."),
+                }
+            ));
+            index += 1;
+            for i in gotos {
+                inner[i].node = InnerInstruction::Goto { addr: index };
+            }
+            deepen(&mut inner);
+            list.push(instruction!(location, InnerInstruction::Enter));
+            list.append(&mut inner);
+            list.push(instruction!(location, InnerInstruction::Leave));
+        }
+
+        // 分支中的语句块，值为unit
+        ExpressionKind::Block { block } => {
+            let mut block_vec = parse_block(block, index, types, location)?;
+            list.append(&mut block_vec);
+            list.push(instruction!(
+                location,
+                InnerInstruction::Push {
+                    value: Operand::Unit,
+                }
+            ));
+        }
+
         ExpressionKind::Question { test, then, orelse } => {
-            check_expr_and_append!(test, list, index);
+            check_expr_and_append!(test, list, index, types);
 
             index += 1; // 插入if
-            let mut tmp_vec = handle_expr_parse_err!(then, index);
+            let mut tmp_vec = handle_expr_parse_err!(then, index, types);
             index += tmp_vec.len(); // 插入then
             index += 1; // 插入Goto
             let if_addr = index;
-            let mut else_vec = handle_expr_parse_err!(orelse, index);
+            let mut else_vec = handle_expr_parse_err!(orelse, index, types);
             index += else_vec.len(); // 插入else
 
             list.push(instruction!(
@@ -530,30 +1083,42 @@ fn parse_expr(expr: &Box<Expression>, begin: usize) -> Result<Vec<Instruction>,
                 .iter()
                 .map(|s| (s.0.clone(), &s.1))
                 .collect::<Vec<(String, &Box<Expression>)>>();
-            let num = new_args.len();
             new_args.sort_by(|a, b| a.0.cmp(&b.0));
+            let mut keys = vec![];
             for arg in new_args {
-                // check_symbol_is_defined!(scope.clone(), arg.1);
-                let mut tmp_vec = handle_expr_parse_err!(arg.1, index);
+                // 每个字段的值都放入栈中
+                let mut tmp_vec = handle_expr_parse_err!(arg.1, index, types);
                 index += tmp_vec.len();
                 list.append(&mut tmp_vec);
-                index += 1;
-                list.push(instruction!(
-                    location,
-                    InnerInstruction::Push {
-                        value: arg.1.to_operand(),
-                    }
-                ));
+                keys.push(arg.0);
             }
+            // 名称可能是结构体的别名，或者是结构体形式的枚举变体
+            let variant = types.borrow().get_variant(name);
+            let value = match variant {
+                Some((_, NewTypeKind::Struct(_))) => name.clone(),
+                Some(_) => {
+                    return Err(format!(
+                        "Type Error: {} is not a struct. ({})",
+                        name, location
+                    ))
+                }
+                None => match resolve_type(types, &NewTypeKind::name(name), location)? {
+                    NewTypeKind::Named(value) if types.borrow().get_struct(&value).is_some() => {
+                        value
+                    }
+                    _ => {
+                        return Err(format!(
+                            "Type Error: {} is not a struct. ({})",
+                            name, location
+                        ))
+                    }
+                },
+            };
             list.push(instruction!(
                 location,
-                InnerInstruction::Struct {
-                    value: name.clone(),
-                    num,
-                }
+                InnerInstruction::Struct { value, keys }
             ));
-        } //
-          // ExpressionKind::TypeMark {} => {}
+        }
     }
     Ok(list)
 }
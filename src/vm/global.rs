@@ -21,11 +21,13 @@ impl WesoSTD {
         unsafe {
             WESO_STD.get_or_insert_with(|| {
                 let mut hash: HashMap<String, Arc<WesoFunc>> = HashMap::new();
+                hash_insert!(hash, "assert", builtin::std_assert);
                 hash_insert!(hash, "print", builtin::std_print);
                 hash_insert!(hash, "println", builtin::std_println);
                 hash_insert!(hash, "log", builtin::std_log);
                 hash_insert!(hash, "equal", builtin::std_equal);
                 hash_insert!(hash, "neq", builtin::std_noequal);
+                hash_insert!(hash, "noteq", builtin::std_noequal);
                 hash_insert!(hash, "lt", builtin::std_lt);
                 hash_insert!(hash, "gt", builtin::std_gt);
                 hash_insert!(hash, "leq", builtin::std_leq);
@@ -34,6 +36,29 @@ impl WesoSTD {
                 hash_insert!(hash, "sub", builtin::std_sub);
                 hash_insert!(hash, "mul", builtin::std_mul);
                 hash_insert!(hash, "div", builtin::std_div);
+                hash_insert!(hash, "mod", builtin::std_mod);
+                hash_insert!(hash, "pow", builtin::std_pow);
+                hash_insert!(hash, "bitand", builtin::std_bitand);
+                hash_insert!(hash, "bitor", builtin::std_bitor);
+                hash_insert!(hash, "bitxor", builtin::std_bitxor);
+                hash_insert!(hash, "lshift", builtin::std_lshift);
+                hash_insert!(hash, "rshift", builtin::std_rshift);
+                hash_insert!(hash, "neg", builtin::std_neg);
+                hash_insert!(hash, "bitnot", builtin::std_bitnot);
+                hash_insert!(hash, "inside", builtin::std_inside);
+                hash_insert!(hash, "array", builtin::std_array);
+                hash_insert!(hash, "tuple", builtin::std_tuple);
+                hash_insert!(hash, "map", builtin::std_map);
+                hash_insert!(hash, "range", builtin::std_range);
+                hash_insert!(hash, "rangeeq", builtin::std_rangeeq);
+                hash_insert!(hash, "step", builtin::std_step);
+                hash_insert!(hash, "str", builtin::std_str);
+                hash_insert!(hash, "len", builtin::std_len);
+                hash_insert!(hash, "push", builtin::std_push);
+                hash_insert!(hash, "pop", builtin::std_pop);
+                hash_insert!(hash, "insert", builtin::std_insert);
+                hash_insert!(hash, "remove", builtin::std_remove);
+                hash_insert!(hash, "error", builtin::std_error);
 
                 Arc::new(WesoSTD { hash })
             });
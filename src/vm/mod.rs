@@ -1,77 +1,105 @@
 pub mod builtin;
+pub mod checker;
 pub mod convert;
 pub mod global;
+pub mod module;
+pub mod pattern;
 pub mod runtime;
 pub mod wasm;
 
+use crate::base::ast::StmtList;
 use crate::base::func::{FuncManager, Function};
 use crate::base::scope::Scope;
 use crate::base::types::NewTypeKind;
 use crate::parser::lexer::Lexer;
 use crate::parser::weso::*;
+use checker::Exports;
+use lalrpop_util::ParseError;
+use module::{FileLoader, Linker, ModuleLoader};
 use runtime::Runtime;
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
-// 解析获得指令集
-pub fn weso_parse(code: &str) -> Result<Vec<String>, String> {
+// 语法分析
+fn parse(code: &str) -> Result<StmtList, String> {
     let lexer = Lexer::new(code);
-    let stmts = wesoParser::new().parse(lexer);
-    match stmts {
-        // 获得一系列语句
-        Ok(stmts) => {
-            // let global = Rc::new(RefCell::new(Scope::new(None)));
-            let result = convert::parse_stmts(&stmts, 0);
-            match result {
-                Ok(ins) => {
-                    let mut v = Vec::new();
-                    for (i, item) in ins.clone().iter().enumerate() {
-                        v.push(format!(
-                            "{:<6} {:<10} {}",
-                            i,
-                            item.location.short_show(),
-                            item.node
-                        ));
-                    }
-                    Ok(v)
-                }
-                Err(why) => Err(why),
-            }
-        }
-        Err(why) => Err(format!("{:?}", why)),
+    wesoParser::new().parse(lexer).map_err(|why| match why {
+        ParseError::User { error } => error.to_string(),
+        _ => format!("{:?}", why),
+    })
+}
+
+// 类型检查，将所有错误合并成一条信息
+fn check(stmts: &StmtList, modules: &HashMap<String, Rc<Exports>>) -> Result<Exports, String> {
+    checker::check_stmts(stmts, modules).map_err(|errors| {
+        errors
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    })
+}
+
+// 解析获得指令集，导入的模块只检查不输出
+pub fn weso_parse_with(code: &str, loader: &dyn ModuleLoader) -> Result<Vec<String>, String> {
+    let mut stmts = parse(code)?;
+    let mut linker = Linker::new(loader);
+    linker.link(&mut stmts)?;
+    check(&stmts, linker.exports())?;
+    let ins = convert::parse_stmts(&stmts, 0, &linker.types(&stmts))?;
+    let mut v = Vec::new();
+    for (i, item) in ins.iter().enumerate() {
+        v.push(format!(
+            "{:<6} {:<10} {}",
+            i,
+            item.location.short_show(),
+            item.node
+        ));
     }
+    Ok(v)
+}
+
+#[allow(unused)]
+pub fn weso_parse(code: &str) -> Result<Vec<String>, String> {
+    weso_parse_with(code, &FileLoader::new(PathBuf::from(".")))
 }
 
-// 直接运行
+// 直接运行，先运行导入的模块
+pub fn weso_run_with(code: &str, loader: &dyn ModuleLoader) -> Result<(), String> {
+    let mut stmts = parse(code)?;
+    let mut linker = Linker::new(loader);
+    linker.link(&mut stmts)?;
+    check(&stmts, linker.exports())?;
+    let global = Rc::new(RefCell::new(Scope::new(None)));
+    let ins = convert::parse_stmts(&stmts, 0, &linker.types(&stmts))?;
+    linker.run(&global)?;
+
+    // 构造一个运行函数
+    let main_func = Function {
+        // name: String::from("__main__"),
+        args: Vec::new(),
+        rettyp: NewTypeKind::Named(String::from("unit")),
+        codes: ins,
+    };
+    let func_id = FuncManager::get_ins().register(main_func);
+    // 构造一个运行时
+    let mut runtime = Runtime::new(Some(global), func_id, vec![]);
+    runtime.run().map(|_| ())
+}
+
+// 模块的路径相对于当前目录
+#[allow(unused)]
 pub fn weso_run(code: &str) -> Result<(), String> {
-    let lexer = Lexer::new(code);
-    let stmts = wesoParser::new().parse(lexer);
-    match stmts {
-        // 获得一系列语句
-        Ok(stmts) => {
-            let global = Rc::new(RefCell::new(Scope::new(None)));
-            let result = convert::parse_stmts(&stmts, 0);
-            match result {
-                Ok(ins) => {
-                    // 构造一个运行函数
-                    let main_func = Function {
-                        // name: String::from("__main__"),
-                        args: Vec::new(),
-                        rettyp: NewTypeKind::Named(String::from("unit")),
-                        codes: ins,
-                    };
-                    let parent = Some(global.clone());
-                    let func_id = FuncManager::get_ins().register(main_func);
-                    // 构造一个运行时
-                    let mut runtime = Runtime::new(parent, func_id, vec![]);
-                    match runtime.run() {
-                        Ok(_) => Ok(()),
-                        Err(e) => Err(e),
-                    }
-                }
-                Err(why) => Err(why),
-            }
-        }
-        Err(why) => Err(format!("{:?}", why)),
-    }
+    weso_run_with(code, &FileLoader::new(PathBuf::from(".")))
+}
+
+// 运行文件，模块的路径相对于文件所在的目录
+#[allow(unused)]
+pub fn weso_run_file(path: &str) -> Result<(), String> {
+    let code = std::fs::read_to_string(path)
+        .map_err(|_| format!("Import Error: cannot load module {}.", path))?;
+    let root = Path::new(path).parent().unwrap_or_else(|| Path::new("."));
+    weso_run_with(&code, &FileLoader::new(root.to_path_buf()))
 }
@@ -0,0 +1,495 @@
+// 模块的加载和链接，每个模块只编译和运行一次
+use super::checker::Exports;
+use super::runtime::Runtime;
+use super::{check, convert, parse};
+use crate::base::ast::{Expression, ExpressionKind, Pattern, StatementKind, StmtList};
+use crate::base::func::{FuncManager, Function};
+use crate::base::object;
+use crate::base::scope::Scope;
+use crate::base::types::{NewTypeKind, TypeScope};
+use crate::parser::lexer::Location;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+// 根据import中的路径读取模块的源码
+pub trait ModuleLoader {
+    fn load(&self, path: &str) -> Result<String, String>;
+
+    // 模块的唯一名称，同一个模块的不同写法只加载一次
+    fn resolve(&self, path: &Path) -> String {
+        normalize(path)
+    }
+}
+
+// 去掉路径中的 . 并且抵消 ..，开头无法抵消的 .. 保留
+fn normalize(path: &Path) -> String {
+    let mut parts: Vec<Component> = vec![];
+    for part in path.components() {
+        match part {
+            Component::CurDir => (),
+            Component::ParentDir if matches!(parts.last(), Some(Component::Normal(_))) => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts
+        .iter()
+        .collect::<PathBuf>()
+        .to_string_lossy()
+        .to_string()
+}
+
+// 从文件系统中读取，路径相对于root
+pub struct FileLoader {
+    root: PathBuf,
+}
+
+impl FileLoader {
+    pub fn new(root: PathBuf) -> Self {
+        FileLoader { root }
+    }
+}
+
+impl ModuleLoader for FileLoader {
+    fn load(&self, path: &str) -> Result<String, String> {
+        std::fs::read_to_string(self.root.join(path))
+            .map_err(|_| format!("Import Error: cannot load module {}.", path))
+    }
+}
+
+// 从内存中读取，wasm中没有文件系统
+#[derive(Default)]
+pub struct MemoryLoader {
+    files: HashMap<String, String>,
+}
+
+impl MemoryLoader {
+    pub fn insert(&mut self, path: &str, code: &str) {
+        self.files.insert(path.to_string(), code.to_string());
+    }
+}
+
+impl ModuleLoader for MemoryLoader {
+    fn load(&self, path: &str) -> Result<String, String> {
+        match self.files.get(path) {
+            Some(code) => Ok(code.clone()),
+            None => Err(format!("Import Error: cannot load module {}.", path)),
+        }
+    }
+}
+
+// 模块中定义的类型的唯一名称 路径:名称，不同模块中同名的类型互不影响
+// 源码中无法写出这样的名称，没有导出的类型只能通过导出的函数得到
+fn qualified(path: &str, name: &str) -> String {
+    format!("{}:{}", path, name)
+}
+
+// 按照names替换 T、T::V 和 T::f 中的类型名称
+fn rename_path(name: &mut String, names: &HashMap<String, String>) {
+    let (typ, rest) = match name.split_once("::") {
+        Some((typ, rest)) => (typ, format!("::{}", rest)),
+        None => (name.as_str(), String::new()),
+    };
+    if let Some(typ) = names.get(typ) {
+        *name = format!("{}{}", typ, rest);
+    }
+}
+
+// 泛型参数覆盖同名的类型
+fn without(names: &HashMap<String, String>, generics: &[String]) -> HashMap<String, String> {
+    let mut names = names.clone();
+    for param in generics {
+        names.remove(param);
+    }
+    names
+}
+
+// 替换语句中使用的所有类型名称
+fn rename_stmts(stmts: &mut StmtList, names: &HashMap<String, String>) {
+    for stmt in stmts.iter_mut() {
+        rename_stmt(&mut stmt.node, names);
+    }
+}
+
+fn rename_stmt(stmt: &mut StatementKind, names: &HashMap<String, String>) {
+    match stmt {
+        StatementKind::Break | StatementKind::Continue | StatementKind::Import { .. } => (),
+        StatementKind::Return { value } | StatementKind::Throw { value } => {
+            rename_expr(value, names)
+        }
+        StatementKind::Assign { left, right }
+        | StatementKind::Move { left, right }
+        | StatementKind::AugAssign { left, right, .. } => {
+            rename_expr(left, names);
+            rename_expr(right, names);
+        }
+        StatementKind::VarDef { typ, assign, .. } => {
+            if let Some(typ) = typ {
+                *typ = typ.rename(names);
+            }
+            if let Some(assign) = assign {
+                rename_expr(assign, names);
+            }
+        }
+        StatementKind::Destruct { pattern, value, .. } => {
+            rename_pattern(pattern, names);
+            rename_expr(value, names);
+        }
+        StatementKind::TypeDef {
+            left,
+            right,
+            generics,
+        } => {
+            rename_path(left, names);
+            *right = right.rename(&without(names, generics));
+        }
+        StatementKind::FuncDef {
+            generics,
+            args,
+            rettyp,
+            block,
+            ..
+        } => {
+            let names = without(names, generics);
+            for (_, typ) in args.iter_mut() {
+                *typ = typ.rename(&names);
+            }
+            *rettyp = rettyp.rename(&names);
+            rename_stmts(block, &names);
+        }
+        StatementKind::Impl {
+            name,
+            trait_name,
+            methods,
+        } => {
+            rename_path(name, names);
+            if let Some(trait_name) = trait_name {
+                rename_path(trait_name, names);
+            }
+            rename_stmts(methods, names);
+        }
+        StatementKind::Trait { name, methods } => {
+            rename_path(name, names);
+            for (_, typ) in methods.iter_mut() {
+                *typ = typ.rename(names);
+            }
+        }
+        StatementKind::Expression { expr } => rename_expr(expr, names),
+        StatementKind::If { test, then, orelse } => {
+            rename_expr(test, names);
+            rename_stmts(then, names);
+            rename_stmts(orelse, names);
+        }
+        StatementKind::While { test, then } => {
+            rename_expr(test, names);
+            rename_stmts(then, names);
+        }
+        StatementKind::For {
+            pattern,
+            iter,
+            then,
+        } => {
+            rename_pattern(pattern, names);
+            rename_expr(iter, names);
+            rename_stmts(then, names);
+        }
+        StatementKind::Try { then, catch, .. } => {
+            rename_stmts(then, names);
+            rename_stmts(catch, names);
+        }
+        StatementKind::Export { stmt } => rename_stmt(&mut stmt.node, names),
+    }
+}
+
+// 模块的别名和类型组成的 geo.Circle @ {...} 以及 geo.Shape::V 解析为属性访问，返回带别名的名称
+fn qualified_member(expr: &Expression, names: &HashMap<String, String>) -> Option<String> {
+    let (module, right) = match &expr.node {
+        ExpressionKind::Dot { left, right } => match &left.node {
+            ExpressionKind::Ident { value } => (value, right),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let name = match &right.node {
+        ExpressionKind::Struct { name, .. } => name,
+        ExpressionKind::Ident { value } if value.contains("::") => value,
+        _ => return None,
+    };
+    let typ = name.split("::").next().unwrap_or_default();
+    names.get(&format!("{}.{}", module, typ))?;
+    Some(format!("{}.{}", module, name))
+}
+
+fn rename_expr(expr: &mut Expression, names: &HashMap<String, String>) {
+    if let Some(qualified) = qualified_member(expr, names) {
+        let node = std::mem::replace(&mut expr.node, ExpressionKind::Block { block: vec![] });
+        if let ExpressionKind::Dot { right, .. } = node {
+            expr.node = right.node;
+        }
+        if let ExpressionKind::Struct { name, .. } | ExpressionKind::Ident { value: name } =
+            &mut expr.node
+        {
+            *name = qualified;
+        }
+    }
+    match &mut expr.node {
+        ExpressionKind::Liter { .. } => (),
+        // 只有 T::f 和 E::V 形式的名称包含类型
+        ExpressionKind::Ident { value } => {
+            if value.contains("::") {
+                rename_path(value, names);
+            }
+        }
+        ExpressionKind::Dot { left, right }
+        | ExpressionKind::Index { left, right }
+        | ExpressionKind::And { left, right }
+        | ExpressionKind::Or { left, right } => {
+            rename_expr(left, names);
+            rename_expr(right, names);
+        }
+        ExpressionKind::Call { callee, args } => {
+            rename_expr(callee, names);
+            for arg in args.iter_mut() {
+                rename_expr(arg, names);
+            }
+        }
+        ExpressionKind::Not { expr } => rename_expr(expr, names),
+        ExpressionKind::Struct { name, args } => {
+            rename_path(name, names);
+            for (_, arg) in args.iter_mut() {
+                rename_expr(arg, names);
+            }
+        }
+        ExpressionKind::Function {
+            args,
+            rettyp,
+            block,
+        } => {
+            for (_, typ) in args.iter_mut() {
+                *typ = typ.rename(names);
+            }
+            *rettyp = rettyp.rename(names);
+            rename_stmts(block, names);
+        }
+        ExpressionKind::Question { test, then, orelse } => {
+            rename_expr(test, names);
+            rename_expr(then, names);
+            rename_expr(orelse, names);
+        }
+        ExpressionKind::Match { value, arms } => {
+            rename_expr(value, names);
+            for arm in arms.iter_mut() {
+                rename_pattern(&mut arm.node.pattern, names);
+                if let Some(guard) = &mut arm.node.guard {
+                    rename_expr(guard, names);
+                }
+                rename_expr(&mut arm.node.body, names);
+            }
+        }
+        ExpressionKind::Block { block } => rename_stmts(block, names),
+    }
+}
+
+fn rename_pattern(pattern: &mut Pattern, names: &HashMap<String, String>) {
+    match pattern {
+        Pattern::Struct { name, fields } => {
+            rename_path(name, names);
+            for (_, item) in fields.iter_mut() {
+                rename_pattern(item, names);
+            }
+        }
+        Pattern::Variant { name, items } => {
+            rename_path(name, names);
+            for item in items.iter_mut().flatten() {
+                rename_pattern(item, names);
+            }
+        }
+        Pattern::Tuple(items) | Pattern::Array { items, .. } | Pattern::Or(items) => {
+            for item in items.iter_mut() {
+                rename_pattern(item, names);
+            }
+        }
+        Pattern::Wildcard | Pattern::Binding(_) | Pattern::Literal(_) | Pattern::Range { .. } => (),
+    }
+}
+
+// 编译后的模块
+struct Module {
+    path: String,
+    func_id: usize,
+    // 导出的名称
+    names: HashSet<String>,
+}
+
+pub struct Linker<'a> {
+    loader: &'a dyn ModuleLoader,
+
+    // 已经检查过的模块 path->导出的定义
+    exports: HashMap<String, Rc<Exports>>,
+
+    // 模块导出的类型 path->类型在模块中的名称
+    exported: HashMap<String, Vec<String>>,
+
+    // 按照编译的顺序排列，被依赖的模块在前
+    modules: Vec<Module>,
+
+    // 正在编译的模块，用于检查循环导入
+    loading: Vec<String>,
+}
+
+impl<'a> Linker<'a> {
+    pub fn new(loader: &'a dyn ModuleLoader) -> Self {
+        Linker {
+            loader,
+            exports: HashMap::new(),
+            exported: HashMap::new(),
+            modules: vec![],
+            loading: vec![],
+        }
+    }
+
+    pub fn exports(&self) -> &HashMap<String, Rc<Exports>> {
+        &self.exports
+    }
+
+    // 编译语句中导入的所有模块，然后替换语句中的类型名称
+    // 模块的路径相对于导入者所在的目录，改写成相对于根目录的规范路径
+    pub fn link(&mut self, stmts: &mut StmtList) -> Result<(), String> {
+        for stmt in stmts.iter_mut() {
+            if let StatementKind::Import { path, .. } = &mut stmt.node {
+                let dir = match self.loading.last() {
+                    Some(importer) => Path::new(importer).parent(),
+                    None => None,
+                };
+                let full = dir.unwrap_or_else(|| Path::new("")).join(&path);
+                *path = self.loader.resolve(&full);
+                self.load(path, &stmt.location)?;
+            }
+        }
+        self.qualify(stmts);
+        Ok(())
+    }
+
+    // 模块最外层定义的类型改用唯一的名称，导入的类型通过 别名.名称 使用
+    fn qualify(&self, stmts: &mut StmtList) {
+        let mut names = HashMap::new();
+        if let Some(module) = self.loading.last() {
+            for stmt in stmts.iter().map(|stmt| stmt.definition()) {
+                if let StatementKind::TypeDef { left: name, .. }
+                | StatementKind::Trait { name, .. } = &stmt.node
+                {
+                    names.insert(name.clone(), qualified(module, name));
+                }
+            }
+        }
+        for stmt in stmts.iter() {
+            if let StatementKind::Import { path, name: alias } = &stmt.node {
+                for name in self.exported.get(path).into_iter().flatten() {
+                    names.insert(format!("{}.{}", alias, name), qualified(path, name));
+                }
+            }
+        }
+        rename_stmts(stmts, &names);
+    }
+
+    // 转换语句时使用的类型作用域，包含导入的模块导出的类型
+    pub fn types(&self, stmts: &StmtList) -> Rc<RefCell<TypeScope>> {
+        let mut types = TypeScope::new(None);
+        for stmt in stmts {
+            if let StatementKind::Import { path, .. } = &stmt.node {
+                if let Some(exports) = self.exports.get(path) {
+                    exports.import_types(&mut types);
+                }
+            }
+        }
+        Rc::new(RefCell::new(types))
+    }
+
+    // 依次运行所有模块，模块最外层的作用域登记到global中
+    pub fn run(&self, global: &Rc<RefCell<Scope>>) -> Result<(), String> {
+        for module in self.modules.iter() {
+            let mut runtime = Runtime::new(Some(global.clone()), module.func_id, vec![]);
+            runtime
+                .run()
+                .map_err(|e| format!("{} in {}", e, module.path))?;
+            let obj = object::create_module(&module.path, runtime.scope(), module.names.clone());
+            global.borrow_mut().define_module(&module.path, obj);
+        }
+        Ok(())
+    }
+
+    // 加载的错误发生在导入者中
+    fn load(&mut self, path: &str, location: &Location) -> Result<(), String> {
+        if self.exports.contains_key(path) {
+            return Ok(());
+        }
+        let importer = match self.loading.last() {
+            Some(importer) => format!(" in {}", importer),
+            None => String::new(),
+        };
+        if let Some(start) = self.loading.iter().position(|p| p == path) {
+            let mut cycle = self.loading[start..].to_vec();
+            cycle.push(path.to_string());
+            return Err(format!(
+                "Import Error: cyclic import {}. ({}){}",
+                cycle.join(" -> "),
+                location,
+                importer
+            ));
+        }
+        let code = self
+            .loader
+            .load(path)
+            .map_err(|e| format!("{} ({}){}", e, location, importer))?;
+
+        self.loading.push(path.to_string());
+        let result = self.compile(path, &code);
+        self.loading.pop();
+        result
+    }
+
+    fn compile(&mut self, path: &str, code: &str) -> Result<(), String> {
+        // 每一条错误都标注所在的模块
+        let in_module = |e: String| {
+            e.lines()
+                .map(|line| format!("{} in {}", line, path))
+                .collect::<Vec<String>>()
+                .join("\n")
+        };
+        let mut stmts = parse(code).map_err(in_module)?;
+        let types = stmts
+            .iter()
+            .filter(|stmt| matches!(stmt.node, StatementKind::Export { .. }))
+            .filter_map(|stmt| match &stmt.definition().node {
+                StatementKind::TypeDef { left: name, .. } | StatementKind::Trait { name, .. } => {
+                    Some(name.clone())
+                }
+                _ => None,
+            })
+            .collect();
+        self.exported.insert(path.to_string(), types);
+        self.link(&mut stmts)?;
+        let exports = check(&stmts, &self.exports).map_err(in_module)?;
+        let codes = convert::parse_stmts(&stmts, 0, &self.types(&stmts)).map_err(in_module)?;
+        let func_id = FuncManager::get_ins().register(Function {
+            args: Vec::new(),
+            rettyp: NewTypeKind::Named(String::from("unit")),
+            codes,
+        });
+        let names = stmts
+            .iter()
+            .filter(|stmt| matches!(stmt.node, StatementKind::Export { .. }))
+            .filter_map(|stmt| stmt.defined_name().cloned())
+            .collect();
+        self.exports.insert(path.to_string(), Rc::new(exports));
+        self.modules.push(Module {
+            path: path.to_string(),
+            func_id,
+            names,
+        });
+        Ok(())
+    }
+}
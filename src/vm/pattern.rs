@@ -0,0 +1,442 @@
+// match分支的穷尽性检查，判断一个模式能否匹配之前的模式都不能匹配的值
+use crate::base::ast::Pattern;
+use crate::base::number;
+use crate::base::number::Number;
+use crate::base::types::{NewTypeKind, TypeScope};
+use crate::parser::liter::Literal;
+
+// 模式最外层的构造器
+#[derive(Debug, Clone, PartialEq)]
+enum Ctor {
+    Tuple(usize),
+
+    // 结构体或者结构体形式的枚举变体
+    Struct(String),
+
+    // 元组形式或者没有数据的枚举变体
+    Variant(String),
+
+    // 长度为n的数组
+    Array(usize),
+
+    // 长度不小于n的数组
+    ArrayRest(usize),
+
+    // 字面量和范围，只和相同的模式比较
+    Literal(Pattern),
+}
+
+// 检查match的所有分支，返回无法到达的分支序号和没有覆盖的值
+// 带有guard的分支不算覆盖了它的模式
+pub fn check_arms(
+    types: &TypeScope,
+    typ: &NewTypeKind,
+    arms: &[(Pattern, bool)],
+) -> (Vec<usize>, Option<Pattern>) {
+    let matrix = Matrix { types };
+    let typs = [typ.clone()];
+    let mut rows: Vec<Vec<Pattern>> = vec![];
+    let mut unreachable = vec![];
+    for (i, (pattern, guarded)) in arms.iter().enumerate() {
+        let row = vec![pattern.clone()];
+        if matrix.useful(&rows, &row, &typs).is_none() {
+            unreachable.push(i);
+        }
+        if !guarded {
+            rows.push(row);
+        }
+    }
+    let witness = matrix
+        .useful(&rows, &[Pattern::Wildcard], &typs)
+        .map(|mut witness| witness.remove(0));
+    (unreachable, witness)
+}
+
+fn any() -> NewTypeKind {
+    NewTypeKind::name("any")
+}
+
+// 模式的构造器，通配符和绑定没有构造器
+fn head(pattern: &Pattern) -> Option<Ctor> {
+    match pattern {
+        Pattern::Wildcard | Pattern::Binding(_) | Pattern::Or(_) => None,
+        Pattern::Literal(_) | Pattern::Range { .. } => Some(Ctor::Literal(pattern.clone())),
+        Pattern::Tuple(items) => Some(Ctor::Tuple(items.len())),
+        Pattern::Array { items, rest: false } => Some(Ctor::Array(items.len())),
+        Pattern::Array { items, rest: true } => Some(Ctor::ArrayRest(items.len())),
+        Pattern::Struct { name, .. } => Some(Ctor::Struct(name.clone())),
+        Pattern::Variant { name, .. } => Some(Ctor::Variant(name.clone())),
+    }
+}
+
+// 展开第一列的或模式，每个选项作为单独的一行
+fn expand(row: &[Pattern]) -> Vec<Vec<Pattern>> {
+    match &row[0] {
+        Pattern::Or(items) => items
+            .iter()
+            .flat_map(|item| {
+                let mut v = vec![item.clone()];
+                v.extend_from_slice(&row[1..]);
+                expand(&v)
+            })
+            .collect(),
+        _ => vec![row.to_vec()],
+    }
+}
+
+// 按照构造器展开模式，构造器不同时返回None
+fn specialize(
+    pattern: &Pattern,
+    ctor: &Ctor,
+    fields: &[(String, NewTypeKind)],
+) -> Option<Vec<Pattern>> {
+    match (pattern, ctor) {
+        (Pattern::Wildcard, _) | (Pattern::Binding(_), _) => {
+            Some(vec![Pattern::Wildcard; fields.len()])
+        }
+        (Pattern::Tuple(items), Ctor::Tuple(n)) if items.len() == *n => Some(items.clone()),
+        (Pattern::Array { items, rest: false }, Ctor::Array(n)) if items.len() == *n => {
+            Some(items.clone())
+        }
+        // [a, ..] 在长度更长的数组中补充通配符
+        (Pattern::Array { items, rest: true }, Ctor::Array(n))
+        | (Pattern::Array { items, rest: true }, Ctor::ArrayRest(n))
+            if items.len() <= *n =>
+        {
+            let mut v = items.clone();
+            v.resize(*n, Pattern::Wildcard);
+            Some(v)
+        }
+        // 没有列出的字段作为通配符
+        (
+            Pattern::Struct {
+                name,
+                fields: values,
+            },
+            Ctor::Struct(target),
+        ) if name == target => Some(
+            fields
+                .iter()
+                .map(|(key, _)| {
+                    values
+                        .iter()
+                        .find(|(k, _)| k == key)
+                        .map_or(Pattern::Wildcard, |(_, p)| p.clone())
+                })
+                .collect(),
+        ),
+        (Pattern::Variant { name, items }, Ctor::Variant(target)) if name == target => {
+            let items = items.clone().unwrap_or_default();
+            if items.len() == fields.len() {
+                Some(items)
+            } else {
+                None
+            }
+        }
+        (_, Ctor::Literal(target)) if pattern == target => Some(vec![]),
+        _ => None,
+    }
+}
+
+// 由构造器和展开的模式重新组成模式
+fn rebuild(ctor: &Ctor, fields: &[(String, NewTypeKind)], items: Vec<Pattern>) -> Pattern {
+    match ctor {
+        Ctor::Tuple(_) => Pattern::Tuple(items),
+        Ctor::Array(_) => Pattern::Array { items, rest: false },
+        Ctor::ArrayRest(_) => Pattern::Array { items, rest: true },
+        Ctor::Struct(name) => Pattern::Struct {
+            name: name.clone(),
+            fields: fields.iter().map(|f| f.0.clone()).zip(items).collect(),
+        },
+        Ctor::Variant(name) => Pattern::Variant {
+            name: name.clone(),
+            items: if items.is_empty() { None } else { Some(items) },
+        },
+        Ctor::Literal(pattern) => pattern.clone(),
+    }
+}
+
+// 覆盖长度从start开始的所有数组
+fn array_ctors(start: usize, max: usize) -> Vec<Ctor> {
+    let mut ctors = (start..=max).map(Ctor::Array).collect::<Vec<Ctor>>();
+    ctors.push(Ctor::ArrayRest(max + 1));
+    ctors
+}
+
+// 整数相对于类型最小值的偏移，有符号整数和u128可以统一比较
+fn int_offset(typ: &NewTypeKind, literal: &Literal) -> Option<u128> {
+    let (signed, bits) = typ.int_kind()?;
+    let value = match literal {
+        Literal::Integer(text) => Number::of(&number::int_literal(text).ok()?)?,
+        _ => return None,
+    };
+    number::create_number(typ, value)?;
+    if signed {
+        let min = i128::MIN >> (128 - bits);
+        Some((value.to_i128()? as u128).wrapping_sub(min as u128))
+    } else {
+        value.to_u128()
+    }
+}
+
+// 偏移对应的整数字面量
+fn int_literal(typ: &NewTypeKind, offset: u128) -> Literal {
+    let text = match typ.int_kind() {
+        Some((true, bits)) => {
+            let min = i128::MIN >> (128 - bits);
+            (offset.wrapping_add(min as u128) as i128).to_string()
+        }
+        _ => offset.to_string(),
+    };
+    Literal::Integer(text)
+}
+
+// 整数类型的模式能匹配的值的区间，通配符匹配类型的所有值
+fn int_interval(typ: &NewTypeKind, pattern: &Pattern) -> Option<(u128, u128)> {
+    let (_, bits) = typ.int_kind()?;
+    match pattern {
+        Pattern::Wildcard | Pattern::Binding(_) => Some((0, u128::MAX >> (128 - bits))),
+        Pattern::Literal(value) => int_offset(typ, value).map(|v| (v, v)),
+        Pattern::Range {
+            start,
+            end,
+            inclusive,
+        } => {
+            let start = int_offset(typ, start)?;
+            let end = int_offset(typ, end)?;
+            let end = if *inclusive { end } else { end.checked_sub(1)? };
+            if start <= end {
+                Some((start, end))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+struct Matrix<'a> {
+    types: &'a TypeScope,
+}
+
+impl<'a> Matrix<'a> {
+    // 构造器的字段名称和类型，元组和数组的字段没有名称，结构体的字段按名称排序
+    fn fields(&self, ctor: &Ctor, typ: &NewTypeKind) -> Vec<(String, NewTypeKind)> {
+        let positional = |items: Vec<NewTypeKind>| {
+            items
+                .into_iter()
+                .map(|item| (String::new(), item))
+                .collect::<Vec<(String, NewTypeKind)>>()
+        };
+        let named = |mut fields: Vec<(String, NewTypeKind)>| {
+            fields.sort_by(|a, b| a.0.cmp(&b.0));
+            fields
+        };
+        match ctor {
+            Ctor::Tuple(n) => match typ {
+                NewTypeKind::Tuple(items) if items.len() == *n => positional(items.clone()),
+                _ => positional(vec![any(); *n]),
+            },
+            Ctor::Array(n) | Ctor::ArrayRest(n) => match typ {
+                NewTypeKind::Array(item) => positional(vec![*item.clone(); *n]),
+                _ => positional(vec![any(); *n]),
+            },
+            Ctor::Struct(name) => match self.types.get_variant(name) {
+                Some((_, NewTypeKind::Struct(fields))) => named(fields),
+                _ => named(self.types.get_struct(name).unwrap_or_default()),
+            },
+            Ctor::Variant(name) => match self.types.get_variant(name) {
+                Some((_, NewTypeKind::Tuple(items))) => positional(items),
+                _ => vec![],
+            },
+            Ctor::Literal(_) => vec![],
+        }
+    }
+
+    // 类型的所有构造器，无法列出时返回None
+    fn signature(&self, typ: &NewTypeKind, heads: &[Ctor]) -> Option<Vec<Ctor>> {
+        match typ {
+            NewTypeKind::Named(name) if name == "bool" => Some(vec![
+                Ctor::Literal(Pattern::Literal(Literal::True)),
+                Ctor::Literal(Pattern::Literal(Literal::False)),
+            ]),
+            NewTypeKind::Named(name) if name == "unit" => {
+                Some(vec![Ctor::Literal(Pattern::Literal(Literal::Unit))])
+            }
+            NewTypeKind::Tuple(items) => Some(vec![Ctor::Tuple(items.len())]),
+            NewTypeKind::Generic(name, _) => Some(vec![Ctor::Struct(name.clone())]),
+            // 数组按照模式中出现的最大长度划分
+            NewTypeKind::Array(_) => {
+                let max = heads
+                    .iter()
+                    .filter_map(|ctor| match ctor {
+                        Ctor::Array(n) | Ctor::ArrayRest(n) => Some(*n),
+                        _ => None,
+                    })
+                    .max()?;
+                Some(array_ctors(0, max))
+            }
+            NewTypeKind::Named(name) => {
+                if let Some(variants) = self.types.get_enum(name) {
+                    Some(
+                        variants
+                            .into_iter()
+                            .map(|(variant, data)| {
+                                let name = format!("{}::{}", name, variant);
+                                match data {
+                                    NewTypeKind::Struct(_) => Ctor::Struct(name),
+                                    _ => Ctor::Variant(name),
+                                }
+                            })
+                            .collect(),
+                    )
+                } else if self.types.get_struct(name).is_some() {
+                    Some(vec![Ctor::Struct(name.clone())])
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // 判断q能否匹配rows都不能匹配的值，能匹配时返回这样的值
+    fn useful(
+        &self,
+        rows: &[Vec<Pattern>],
+        q: &[Pattern],
+        typs: &[NewTypeKind],
+    ) -> Option<Vec<Pattern>> {
+        if q.is_empty() {
+            return if rows.is_empty() { Some(vec![]) } else { None };
+        }
+        if let Pattern::Or(items) = &q[0] {
+            return items.iter().find_map(|item| {
+                let mut v = q.to_vec();
+                v[0] = item.clone();
+                self.useful(rows, &v, typs)
+            });
+        }
+        let rows = rows
+            .iter()
+            .flat_map(|row| expand(row))
+            .collect::<Vec<Vec<Pattern>>>();
+        // 整数按照模式中的区间划分
+        if let Some(target) = int_interval(&typs[0], &q[0]) {
+            return self.useful_int(&rows, q, typs, target);
+        }
+        let heads = rows
+            .iter()
+            .filter_map(|row| head(&row[0]))
+            .collect::<Vec<Ctor>>();
+        match head(&q[0]) {
+            // [a, ..] 需要分别考虑各个长度的数组
+            Some(Ctor::ArrayRest(n)) => {
+                let max = heads
+                    .iter()
+                    .filter_map(|ctor| match ctor {
+                        Ctor::Array(n) | Ctor::ArrayRest(n) => Some(*n),
+                        _ => None,
+                    })
+                    .fold(n, usize::max);
+                array_ctors(n, max)
+                    .iter()
+                    .find_map(|ctor| self.useful_ctor(&rows, q, typs, ctor))
+            }
+            Some(ctor) => self.useful_ctor(&rows, q, typs, &ctor),
+            None => match self.signature(&typs[0], &heads) {
+                Some(ctors) => ctors
+                    .iter()
+                    .find_map(|ctor| self.useful_ctor(&rows, q, typs, ctor)),
+                // 构造器无法列出时只有通配符开头的行能覆盖其余的值
+                None => {
+                    let rest = rows
+                        .iter()
+                        .filter(|row| head(&row[0]).is_none())
+                        .map(|row| row[1..].to_vec())
+                        .collect::<Vec<Vec<Pattern>>>();
+                    let mut witness = self.useful(&rest, &q[1..], &typs[1..])?;
+                    witness.insert(0, Pattern::Wildcard);
+                    Some(witness)
+                }
+            },
+        }
+    }
+
+    // 只考虑构造器为ctor的值
+    fn useful_ctor(
+        &self,
+        rows: &[Vec<Pattern>],
+        q: &[Pattern],
+        typs: &[NewTypeKind],
+        ctor: &Ctor,
+    ) -> Option<Vec<Pattern>> {
+        let fields = self.fields(ctor, &typs[0]);
+        let specialize_row = |row: &[Pattern]| {
+            let mut v = specialize(&row[0], ctor, &fields)?;
+            v.extend_from_slice(&row[1..]);
+            Some(v)
+        };
+        let rows = rows
+            .iter()
+            .filter_map(|row| specialize_row(row))
+            .collect::<Vec<Vec<Pattern>>>();
+        let q = specialize_row(q)?;
+        let mut sub_typs = fields
+            .iter()
+            .map(|f| f.1.clone())
+            .collect::<Vec<NewTypeKind>>();
+        sub_typs.extend_from_slice(&typs[1..]);
+        let mut witness = self.useful(&rows, &q, &sub_typs)?;
+        let rest = witness.split_off(fields.len());
+        let mut result = vec![rebuild(ctor, &fields, witness)];
+        result.extend(rest);
+        Some(result)
+    }
+
+    // 用各行区间的边界划分target，每一段要么被一行完全覆盖，要么完全不覆盖
+    fn useful_int(
+        &self,
+        rows: &[Vec<Pattern>],
+        q: &[Pattern],
+        typs: &[NewTypeKind],
+        target: (u128, u128),
+    ) -> Option<Vec<Pattern>> {
+        let intervals = rows
+            .iter()
+            .map(|row| int_interval(&typs[0], &row[0]))
+            .collect::<Vec<Option<(u128, u128)>>>();
+        let mut points = vec![target.0];
+        for (start, end) in intervals.iter().flatten() {
+            points.push(*start);
+            points.extend(end.checked_add(1));
+        }
+        points.retain(|p| *p >= target.0 && *p <= target.1);
+        points.sort_unstable();
+        points.dedup();
+        points.iter().enumerate().find_map(|(i, start)| {
+            let end = points.get(i + 1).map_or(target.1, |next| next - 1);
+            let rest = rows
+                .iter()
+                .zip(&intervals)
+                .filter(|(_, interval)| matches!(interval, Some((s, e)) if s <= start && *e >= end))
+                .map(|(row, _)| row[1..].to_vec())
+                .collect::<Vec<Vec<Pattern>>>();
+            let mut witness = self.useful(&rest, &q[1..], &typs[1..])?;
+            // 没有被划分时仍然用通配符表示
+            let pattern = if int_interval(&typs[0], &Pattern::Wildcard) == Some((*start, end)) {
+                Pattern::Wildcard
+            } else if *start == end {
+                Pattern::Literal(int_literal(&typs[0], end))
+            } else {
+                Pattern::Range {
+                    start: int_literal(&typs[0], *start),
+                    end: int_literal(&typs[0], end),
+                    inclusive: true,
+                }
+            };
+            witness.insert(0, pattern);
+            Some(witness)
+        })
+    }
+}
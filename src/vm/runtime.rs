@@ -1,23 +1,50 @@
+use crate::base::ast::Pattern;
 use crate::base::func::FuncManager;
 use crate::base::func::Function;
+use crate::base::iter::WesoIter;
+use crate::base::number;
 use crate::base::object;
-use crate::base::object::WesoObject;
+use crate::base::object::{ObjectValue, WesoObject};
 use crate::base::opcode::{InnerInstruction, Instruction, Operand};
 use crate::base::scope::Scope;
+use crate::base::types::NewTypeKind;
+use crate::parser::lexer::Location;
+use crate::parser::liter::Literal;
 // use crate::parser::token::TypeToken;
+use crate::vm::builtin;
 use crate::vm::global::WesoSTD;
 use std::cell::Cell;
 use std::cell::RefCell;
-// use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::sync::Arc;
 
+// 函数调用的最大深度，每一层调用都会占用宿主的栈空间，超过时报错而不是栈溢出
+const MAX_DEPTH: usize = 1000;
+
+thread_local! {
+    // 当前的函数调用深度
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
 #[derive(Debug, Clone)]
 pub enum StackValue {
     // 一个值
     Object(WesoObject),
-    // 一个指针，dot的时候使用
-    Pointer(WesoObject, String),
+    // 一个指针，dot的时候使用，最后一项表示能否通过该指针修改属性
+    Pointer(WesoObject, String, bool),
+    // map中键对应的位置，键可以是任意能哈希的对象
+    Entry(WesoObject, WesoObject, bool),
+}
+
+// try语句登记的错误处理，记录进入try时的状态，出错时恢复并跳转到catch
+struct Handler {
+    addr: usize,
+    // try语句块的作用域
+    scope: Rc<RefCell<Scope>>,
+    stack: usize,
+    iters: usize,
 }
 
 pub struct Runtime {
@@ -25,9 +52,12 @@ pub struct Runtime {
     func: Arc<Function>,
     pc: Cell<usize>,
     scope: Rc<RefCell<Scope>>,
+    iters: RefCell<Vec<WesoIter>>,
+    handlers: RefCell<Vec<Handler>>,
 }
 
 impl Runtime {
+    // parent为定义函数时所在的作用域，而不是调用者的作用域
     pub fn new(
         parent: Option<Rc<RefCell<Scope>>>,
         func_id: usize,
@@ -41,8 +71,9 @@ impl Runtime {
         for i in 0..args.len() {
             let arg = &args[i];
             let param = (&params[i]).clone();
-            scope.borrow_mut().define_variable(&arg.0, false, &arg.1);
-            scope.borrow_mut().set_variable(&arg.0, param);
+            scope.borrow_mut().define_param(&arg.0, &arg.1);
+            // 参数刚刚定义，第一次赋值不会失败
+            let _ = scope.borrow_mut().set_variable(&arg.0, param);
         }
 
         Runtime {
@@ -50,9 +81,16 @@ impl Runtime {
             pc: Cell::new(0),
             func,
             scope,
+            iters: RefCell::new(Vec::new()),
+            handlers: RefCell::new(Vec::new()),
         }
     }
 
+    // 函数的作用域，模块运行结束后作为模块的作用域
+    pub fn scope(&self) -> Rc<RefCell<Scope>> {
+        self.scope.clone()
+    }
+
     #[inline]
     fn goto(&self, pc: usize) {
         self.pc.set(pc - 1); // 每次循环都会执行一次next加一
@@ -69,6 +107,25 @@ impl Runtime {
         self.func.codes.get(self.pc.get())
     }
 
+    fn leave(&mut self) -> Result<(), String> {
+        // 离开try语句块时错误处理随之失效
+        let inside = matches!(
+            self.handlers.borrow().last(),
+            Some(handler) if Rc::ptr_eq(&handler.scope, &self.scope)
+        );
+        if inside {
+            self.handlers.borrow_mut().pop();
+        }
+        let parent = self.scope.borrow().parent();
+        match parent {
+            Some(scope) => {
+                self.scope = scope;
+                Ok(())
+            }
+            None => Err("Runtime Error: Scope damage.".to_string()),
+        }
+    }
+
     // 对栈进行操作
     fn push_obj(&self, obj: WesoObject) {
         self.stack.borrow_mut().push(StackValue::Object(obj));
@@ -86,7 +143,17 @@ impl Runtime {
     pub fn get_stack_obj(&self, value: &StackValue) -> Result<WesoObject, String> {
         match value {
             StackValue::Object(obj) => Ok(obj.clone()),
-            StackValue::Pointer(name, key) => match name.get_attr(key) {
+            StackValue::Pointer(obj, key, _) if obj.is_array() || obj.is_tuple() => {
+                obj.get_item(key).map_err(|e| match self.fetch() {
+                    Some(ins) => format!("{} ({})", e, ins.location),
+                    None => e,
+                })
+            }
+            StackValue::Entry(obj, key, _) => obj.get_entry(key).map_err(|e| match self.fetch() {
+                Some(ins) => format!("{} ({})", e, ins.location),
+                None => e,
+            }),
+            StackValue::Pointer(name, key, _) => match name.get_attr(key) {
                 Some(o) => Ok(o),
                 None => Err(format!("Attribute Error: Struct does not contain {}.", key)),
             },
@@ -106,14 +173,23 @@ impl Runtime {
     // 通过操作数获取具体的对象
     fn get_value(&self, op: &Operand) -> Result<WesoObject, String> {
         match op {
-            Operand::Stack => match self.stack.borrow().last() {
-                Some(obj) => self.get_stack_obj(obj),
+            // 栈上的操作数使用后即出栈
+            Operand::Stack => match self.stack.borrow_mut().pop() {
+                Some(obj) => self.get_stack_obj(&obj),
                 None => Err(format!("Stack Error: Invalid operation.")),
             },
-            Operand::Var(name) => match self.scope.borrow().get_object(name) {
-                Ok(obj) => Ok(obj.clone()),
-                Err(e) => Err(e),
-            },
+            Operand::Var(name) => {
+                // 找不到变量时，名称可能是没有数据的枚举变体
+                let found = Scope::get_object(&self.scope, name)
+                    .or_else(|e| self.create_variant(name, None).unwrap_or(Err(e)));
+                match found {
+                    Ok(obj) => Ok(obj),
+                    Err(e) => match self.fetch() {
+                        Some(ins) => Err(format!("{} ({})", e, ins.location)),
+                        None => Err(e),
+                    },
+                }
+            }
             _ => match object::create_literal(op) {
                 Some(obj) => Ok(obj),
                 None => Err(format!("Runtime Error: Cannot create literal.")),
@@ -121,236 +197,965 @@ impl Runtime {
         }
     }
 
-    pub fn run(&mut self) -> Result<WesoObject, String> {
-        while let Some(ins) = self.fetch() {
-            match &ins.node {
-                // 变量定义
-                InnerInstruction::DefVar { mutable, name, typ } => {
-                    self.scope
-                        .borrow_mut()
-                        .define_variable(&name.unwrap(), *mutable, typ);
+    // 构造枚举的变体，None表示名称不是枚举的变体
+    // args为None时变体作为值使用，只有没有数据的变体可以这样使用
+    fn create_variant(
+        &self,
+        name: &str,
+        args: Option<Vec<WesoObject>>,
+    ) -> Option<Result<WesoObject, String>> {
+        let (typ, data) = self.scope.borrow().get_variant(name)?;
+        let variant = &name[typ.len() + 2..];
+        let result = match (data, args) {
+            (NewTypeKind::Tuple(items), Some(args)) => {
+                let accepted = items.len() == args.len()
+                    && items
+                        .iter()
+                        .zip(args.iter())
+                        .all(|(t, a)| self.scope.borrow().accepts(t, a));
+                if accepted {
+                    items
+                        .iter()
+                        .zip(args.iter())
+                        .map(|(t, a)| number::cast(a, t))
+                        .collect::<Result<Vec<WesoObject>, String>>()
+                        .map(|v| object::create_enum(&typ, variant, Some(object::create_tuple(v))))
+                } else {
+                    Err(format!(
+                        "Type Error: variant {} does not accept {}.",
+                        name,
+                        Self::func_sign(&args)
+                    ))
                 }
+            }
+            (NewTypeKind::Tuple(items), None) => Err(format!(
+                "Type Error: variant {} expects {}.",
+                name,
+                NewTypeKind::Tuple(items)
+            )),
+            (NewTypeKind::Struct(fields), _) => {
+                let mut keys = fields.iter().map(|f| f.0.clone()).collect::<Vec<String>>();
+                keys.sort();
+                Err(format!(
+                    "Type Error: variant {} expects fields {}.",
+                    name,
+                    keys.join(",")
+                ))
+            }
+            (_, None) => Ok(object::create_enum(&typ, variant, None)),
+            (_, Some(_)) => Err(format!("Type Error: {} is not callable.", name)),
+        };
+        Some(result)
+    }
 
-                // 定义函数
-                InnerInstruction::DefFunc { name, id, sign } => {
-                    self.scope
-                        .borrow_mut()
-                        .define_func(name.to_string(), sign.to_string(), *id);
+    // 检查对象是否匹配模式，绑定的变量放入bindings中
+    fn match_pattern(
+        &self,
+        pattern: &Pattern,
+        obj: &WesoObject,
+        bindings: &mut Vec<(String, WesoObject)>,
+    ) -> Result<bool, String> {
+        let literal = |value: &Literal| {
+            object::create_literal(&value.to_operand())
+                .ok_or_else(|| String::from("Runtime Error: Cannot create literal."))
+        };
+        match pattern {
+            Pattern::Wildcard => Ok(true),
+            Pattern::Binding(name) => {
+                bindings.push((name.clone(), obj.clone()));
+                Ok(true)
+            }
+            Pattern::Literal(value) => {
+                let result = builtin::std_equal(&vec![obj.clone(), literal(value)?])?;
+                Ok(Arc::ptr_eq(&result, &object::OBJ_TRUE))
+            }
+            Pattern::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let lower = number::compare(obj, &literal(start)?);
+                let upper = number::compare(obj, &literal(end)?);
+                Ok(match (lower, upper) {
+                    (Some(lower), Some(upper)) => {
+                        lower != Ordering::Less
+                            && (upper == Ordering::Less || (*inclusive && upper == Ordering::Equal))
+                    }
+                    _ => false,
+                })
+            }
+            Pattern::Tuple(items) => match obj.get_value() {
+                ObjectValue::Tuple { value } => {
+                    self.match_items(items, &value.borrow(), false, bindings)
                 }
-
-                // 对象赋值
-                InnerInstruction::Assign { lhs, rhs } => {
-                    let rhs_obj = match self.get_value(rhs) {
-                        Ok(value) => value,
-                        Err(e) => return Err(e),
-                    };
-                    if lhs.is_variable() {
-                        self.scope.borrow_mut().set_variable(lhs.unwrap(), rhs_obj);
-                    } else if lhs.is_stack() {
-                        // 处理dot的操作
-                        let stack_value = match self.pop() {
-                            Ok(t) => t,
-                            Err(e) => return Err(e),
-                        };
-                        match &stack_value {
-                            StackValue::Object(_) => {
-                                return Err(format!(
-                                    "Runtime Error: left-hand value could not be modified."
-                                ))
-                            }
-                            StackValue::Pointer(obj, key) => {
-                                if obj.is_struct() {
-                                    if obj.has_attr(key) {
-                                        obj.set_attr(key, rhs_obj);
-                                    } else {
-                                        return Err(format!(
-                                            "Attribute Error: Struct does not contain {}.",
-                                            key
-                                        ));
-                                    }
-                                } else {
-                                    return Err(format!("Runtime Error: Operand is not a struct."));
-                                }
+                _ => Ok(false),
+            },
+            Pattern::Array { items, rest } => match obj.get_value() {
+                ObjectValue::Array { value } => {
+                    self.match_items(items, &value.borrow(), *rest, bindings)
+                }
+                _ => Ok(false),
+            },
+            Pattern::Struct { name, fields } => {
+                // 结构体形式的枚举变体匹配变体的数据
+                let target = match obj.get_value() {
+                    ObjectValue::Enum {
+                        variant,
+                        data: Some(data),
+                    } if format!("{}::{}", obj.get_typ(), variant) == *name => data.clone(),
+                    ObjectValue::Struct { .. } if obj.get_typ().to_string() == *name => obj.clone(),
+                    ObjectValue::Struct { .. } if name.is_empty() => obj.clone(),
+                    _ => return Ok(false),
+                };
+                for (key, item) in fields {
+                    match target.get_attr(key) {
+                        Some(value) if self.match_pattern(item, &value, bindings)? => {}
+                        _ => return Ok(false),
+                    }
+                }
+                Ok(true)
+            }
+            Pattern::Variant { name, items } => match obj.get_value() {
+                ObjectValue::Enum { variant, data }
+                    if format!("{}::{}", obj.get_typ(), variant) == *name =>
+                {
+                    match (items, data) {
+                        (None, None) => Ok(true),
+                        (Some(items), Some(data)) => match data.get_value() {
+                            ObjectValue::Tuple { value } => {
+                                self.match_items(items, &value.borrow(), false, bindings)
                             }
-                        };
-                    } else {
-                        // 左值不可变的错误
-                        return Err(format!(
-                            "Runtime Error: left-hand value could not be modified."
-                        ));
+                            _ => Ok(false),
+                        },
+                        _ => Ok(false),
                     }
                 }
+                _ => Ok(false),
+            },
+            Pattern::Or(items) => {
+                for item in items {
+                    let mut found = vec![];
+                    if self.match_pattern(item, obj, &mut found)? {
+                        bindings.append(&mut found);
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+        }
+    }
 
-                // 函数调用
-                InnerInstruction::Call { value, num } => {
-                    // 检查操作数是不是变量, 或者在栈上
-                    match value {
-                        Operand::Stack | Operand::Var(_) => (),
-                        _ => return Err(format!("Runtime Error: literal is not callable.")),
-                    };
-                    // 逆序获取所有参数
-                    let mut args = vec![];
-                    for _ in 0..*num {
-                        let arg = match self.pop() {
-                            Ok(v) => match self.get_stack_obj(&v) {
-                                Ok(o) => o,
-                                Err(e) => return Err(e),
-                            },
-                            Err(e) => return Err(e),
-                        };
-                        args.push(arg.clone());
+    // 依次匹配元组或者数组的元素，rest为true时只匹配开头的元素
+    fn match_items(
+        &self,
+        items: &[Pattern],
+        values: &[WesoObject],
+        rest: bool,
+        bindings: &mut Vec<(String, WesoObject)>,
+    ) -> Result<bool, String> {
+        if values.len() < items.len() || (!rest && values.len() != items.len()) {
+            return Ok(false);
+        }
+        for (item, value) in items.iter().zip(values.iter()) {
+            if !self.match_pattern(item, value, bindings)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // 逆序获取所有参数
+    fn pop_args(&self, num: usize) -> Result<Vec<WesoObject>, String> {
+        let mut args = vec![];
+        for _ in 0..num {
+            let arg = self.pop()?;
+            args.push(self.get_stack_obj(&arg)?);
+        }
+        args.reverse();
+        Ok(args)
+    }
+
+    // 调用方法，结构体中同名的字段优先，方法以 T::name 的名称定义
+    fn call_method(
+        &self,
+        receiver: &WesoObject,
+        name: &String,
+        args: Vec<WesoObject>,
+        location: &Location,
+    ) -> Result<WesoObject, String> {
+        // 模块只能调用导出的函数，在模块的作用域中查找
+        if let ObjectValue::Module {
+            path,
+            scope,
+            exports,
+        } = receiver.get_value()
+        {
+            if !exports.contains(name) {
+                return Err(format!(
+                    "Attribute Error: module {} does not export {}. ({})",
+                    path, name, location
+                ));
+            }
+            return self.call_name(scope, name, args, location);
+        }
+        if let Some(field) = receiver.get_attr(name) {
+            return self.call_value(&field, args, location);
+        }
+        let typ = receiver.get_typ().to_string();
+        let method = format!("{}::{}", typ, name);
+        let mut params = vec![receiver.clone()];
+        params.extend(args);
+        let func_sign = Self::func_sign(&params);
+        // 从模块导入的类型，方法也可以定义在模块中
+        let origin = self.scope.borrow().get_origin(&typ);
+        for scope in std::iter::once(self.scope.clone()).chain(origin) {
+            if let Some((func_id, parent)) = Scope::get_func(&scope, &method, &func_sign) {
+                return Self::invoke(parent, func_id, params, location);
+            }
+            // 参数可以是trait类型，按照实参的类型匹配
+            let matched = Scope::match_func(&scope, &method, &params, &self.scope.borrow())
+                .map_err(|e| format!("{} ({})", e, location))?;
+            if let Some(func) = matched.and_then(|(id, scope)| object::create_function(id, scope)) {
+                return self.call_value(&func, params, location);
+            }
+        }
+        Err(format!(
+            "Type Error: {} has no method {}. ({})",
+            typ, name, location
+        ))
+    }
+
+    // 根据名称在作用域scope中查找并调用函数
+    fn call_name(
+        &self,
+        scope: &Rc<RefCell<Scope>>,
+        func_name: &String,
+        args: Vec<WesoObject>,
+        location: &Location,
+    ) -> Result<WesoObject, String> {
+        let func_sign = Self::func_sign(&args);
+        // 优先在作用域内查找函数，函数体的父作用域是定义函数的作用域
+        if let Some((func_id, parent)) = Scope::get_func(scope, func_name, &func_sign) {
+            return Self::invoke(parent, func_id, args, location);
+        }
+        // 没有签名完全一致的函数时，按照参数的转换代价选择重载的函数
+        let matched = Scope::match_func(scope, func_name, &args, &self.scope.borrow())
+            .map_err(|e| format!("{} ({})", e, location))?;
+        if let Some(func) = matched.and_then(|(id, scope)| object::create_function(id, scope)) {
+            return self.call_value(&func, args, location);
+        }
+        // 变量中保存的函数值
+        let found = Scope::get_object(scope, func_name);
+        if let Ok(obj) = &found {
+            if obj.is_function() {
+                return self.call_value(obj, args, location);
+            }
+        }
+        // 枚举的变体 E::V(...)
+        if let Some(result) = self.create_variant(func_name, Some(args.clone())) {
+            return result.map_err(|e| format!("{} ({})", e, location));
+        }
+        // 在内建函数库中寻找
+        match (WesoSTD::get_ins().get_func(func_name), found) {
+            (Some(func), _) => func(&args).map_err(|e| format!("{} ({})", e, location)),
+            (None, Ok(obj)) => self.call_value(&obj, args, location),
+            (None, Err(_)) => Err(format!("Variable Error: {} is not defined.", func_name)),
+        }
+    }
+
+    // 调用函数值，参数按照声明的类型检查并转换
+    fn call_value(
+        &self,
+        callee: &WesoObject,
+        args: Vec<WesoObject>,
+        location: &Location,
+    ) -> Result<WesoObject, String> {
+        let func = match callee.get_value() {
+            ObjectValue::Function { id, scope } => FuncManager::get_ins()
+                .get_func(*id)
+                .map(|func| (*id, scope.clone(), func)),
+            _ => None,
+        };
+        let (id, parent, func) = match func {
+            Some(v) => v,
+            None => {
+                return Err(format!(
+                    "Type Error: {} is not callable. ({})",
+                    callee.get_typ(),
+                    location
+                ))
+            }
+        };
+        let not_accept = || {
+            format!(
+                "Type Error: function {} does not accept {}. ({})",
+                callee.get_typ(),
+                Self::func_sign(&args),
+                location
+            )
+        };
+        if func.args.len() != args.len() {
+            return Err(not_accept());
+        }
+        let mut params = vec![];
+        for ((_, typ), arg) in func.args.iter().zip(args.iter()) {
+            if !self.scope.borrow().accepts(typ, arg) {
+                return Err(not_accept());
+            }
+            params.push(number::cast(arg, typ).map_err(|e| format!("{} ({})", e, location))?);
+        }
+        Self::invoke(parent, id, params, location)
+    }
+
+    // 在新的运行时中执行函数，超过最大调用深度时报错，try可以捕获该错误
+    fn invoke(
+        parent: Rc<RefCell<Scope>>,
+        func_id: usize,
+        params: Vec<WesoObject>,
+        location: &Location,
+    ) -> Result<WesoObject, String> {
+        let depth = DEPTH.with(Cell::get);
+        if depth >= MAX_DEPTH {
+            return Err(format!(
+                "Runtime Error: maximum call depth {} exceeded. ({})",
+                MAX_DEPTH, location
+            ));
+        }
+        DEPTH.with(|d| d.set(depth + 1));
+        let result = Runtime::new(Some(parent), func_id, params).run();
+        DEPTH.with(|d| d.set(depth));
+        result
+    }
+
+    // 将对象赋值给左值，左值可以是变量或者栈上的指针
+    fn assign(
+        &self,
+        lhs: &Operand,
+        rhs_obj: WesoObject,
+        location: &Location,
+    ) -> Result<(), String> {
+        if lhs.is_variable() {
+            let name = lhs.unwrap();
+            let res = self.scope.borrow_mut().set_variable(name, rhs_obj);
+            if let Err(e) = res {
+                return Err(format!("{} ({})", e, location));
+            }
+        } else if lhs.is_stack() {
+            // 处理dot的操作
+            let stack_value = self.pop()?;
+            match &stack_value {
+                StackValue::Object(_) => {
+                    return Err(format!(
+                        "Runtime Error: left-hand value could not be modified."
+                    ))
+                }
+                StackValue::Entry(_, key, false) => {
+                    return Err(format!(
+                        "Variable Error: {} belongs to a const and cannot be modified. ({})",
+                        key.to_string(),
+                        location
+                    ));
+                }
+                StackValue::Entry(obj, key, true) => {
+                    if let Err(e) = obj.set_entry(key.clone(), rhs_obj) {
+                        return Err(format!("{} ({})", e, location));
                     }
-                    args.reverse();
-
-                    // 创建一个running time
-                    let func_name = value.unwrap(); // 获取函数名
-                    let func_sign = Self::func_sign(&args);
-                    // 优先在作用域内查找函数
-                    if let Some(func_id) = self.scope.borrow().get_func(func_name, &func_sign) {
-                        // let func = FuncManager::get_ins().get_func(func_id).unwrap();
-                        let parent = Some(self.scope.clone());
-                        let mut runtime = Runtime::new(parent, func_id, args);
-                        match runtime.run() {
-                            Ok(res) => self.push_obj(res),
-                            Err(e) => return Err(e),
-                        }
-                    } else {
-                        // 在内建函数库中寻找
-                        match WesoSTD::get_ins().get_func(func_name) {
-                            Some(func) => match func(&args) {
-                                Ok(o) => self.push_obj(o),
-                                Err(e) => return Err(e),
-                            },
-                            None => {
-                                return Err(format!(
-                                    "Variable Error: {} is not defined.",
-                                    func_name
-                                ))
-                            }
-                        };
+                }
+                StackValue::Pointer(obj, _, _) if obj.is_tuple() => {
+                    return Err(format!("Type Error: tuple is immutable. ({})", location));
+                }
+                StackValue::Pointer(_, key, false) => {
+                    return Err(format!(
+                        "Variable Error: {} belongs to a const and cannot be modified. ({})",
+                        key, location
+                    ));
+                }
+                StackValue::Pointer(obj, key, true) if obj.is_array() => {
+                    if let Err(e) = obj.set_item(key, rhs_obj) {
+                        return Err(format!("{} ({})", e, location));
                     }
                 }
-
-                // 点操作
-                InnerInstruction::Dot { lhs, rhs } => {
-                    // 左值是name，右值是key左值
-                    // 必须是struct结构体，右值必须是能转换成str类型的
-                    // 左值可能是在栈中，左值可能是变量名
-                    let left = match self.get_value(lhs) {
-                        Ok(obj) => obj,
-                        Err(e) => return Err(e),
-                    };
-                    if !left.is_struct() {
+                StackValue::Pointer(obj, key, true) => {
+                    if !obj.is_struct() {
                         return Err(format!("Runtime Error: Operand is not a struct."));
                     }
-                    let mut key: String = String::new();
-                    if rhs.can_unwrap() {
-                        key = rhs.unwrap().clone();
-                    } else if rhs.is_stack() {
-                        match self.pop() {
-                            Ok(t) => match self.get_stack_obj(&t) {
-                                Err(e) => return Err(e),
-                                Ok(obj) => {
-                                    if obj.is_float() || obj.is_integer() || obj.is_string() {
-                                        key = obj.to_string();
-                                    }
-                                }
-                            },
-                            Err(e) => return Err(e),
-                        };
-                    } else {
-                        return Err(format!("Attribute Error: Invalid attribute."));
+                    if !obj.has_attr(key) {
+                        return Err(format!("Attribute Error: Struct does not contain {}.", key));
                     }
-                    // 将一个这样的指针放入栈中
-                    self.stack.borrow_mut().push(StackValue::Pointer(left, key));
+                    obj.set_attr(key, rhs_obj);
                 }
+            };
+        } else {
+            // 左值不可变的错误
+            return Err(format!(
+                "Runtime Error: left-hand value could not be modified."
+            ));
+        }
+        Ok(())
+    }
+
+    // 出错时交给最近的try处理，没有try时向调用者传递
+    pub fn run(&mut self) -> Result<WesoObject, String> {
+        loop {
+            match self.execute() {
+                Err(e) => self.recover(e)?,
+                result => return result,
+            }
+        }
+    }
 
-                // InnerInstruction::Move { lhs, rhs } => {}
-
-                // 将操作数做取反，再放入栈中
-                InnerInstruction::Not { value } => {
-                    let obj = match self.get_value(value) {
-                        Ok(o) => o,
-                        Err(e) => return Err(e),
-                    };
-                    if obj.is_bool() {
-                        if Arc::ptr_eq(&obj, &object::OBJ_TRUE.clone()) {
-                            self.push_obj(object::OBJ_FALSE.clone());
-                        } else {
-                            self.push_obj(object::OBJ_TRUE.clone());
+    // 恢复到进入try之前的状态，错误对象放入栈中，从catch继续执行
+    fn recover(&mut self, e: String) -> Result<(), String> {
+        let handler = match self.handlers.borrow_mut().pop() {
+            Some(handler) => handler,
+            None => return Err(e),
+        };
+        self.scope = handler.scope;
+        self.leave()?;
+        self.stack.borrow_mut().truncate(handler.stack);
+        self.iters.borrow_mut().truncate(handler.iters);
+        self.push_obj(object::parse_error(&e));
+        self.pc.set(handler.addr);
+        Ok(())
+    }
+
+    // 以下较大的指令单独放在不内联的函数中，避免execute的栈帧过大，减少每层调用占用的栈空间
+    // 点操作，取属性、下标或者map的键，指针放入栈中
+    #[inline(never)]
+    fn dot(&self, lhs: &Operand, rhs: &Operand, location: &Location) -> Result<(), String> {
+        // 左值是name，右值是key左值
+        // 必须是struct结构体，右值必须是能转换成str类型的
+        // 左值可能是在栈中，左值可能是变量名
+        // 两者都在栈中时，右值在栈顶，需要先取出
+        let mut key: String = String::new();
+        let mut key_obj = None;
+        if rhs.can_unwrap() {
+            key = rhs.unwrap().clone();
+        } else if rhs.is_stack() {
+            match self.pop() {
+                Ok(t) => match self.get_stack_obj(&t) {
+                    Err(e) => return Err(e),
+                    Ok(obj) => {
+                        if obj.is_float() || obj.is_integer() || obj.is_string() {
+                            key = obj.to_string();
                         }
-                    } else {
-                        return Err(format!("Type Error: Expression should be a boolean."));
+                        key_obj = Some(obj);
+                    }
+                },
+                Err(e) => return Err(e),
+            };
+        } else {
+            return Err(format!("Attribute Error: Invalid attribute."));
+        }
+        // 通过常量取到的属性也不能修改
+        let mutable = match lhs {
+            Operand::Var(name) => self.scope.borrow().is_mutable(name),
+            Operand::Stack => !matches!(
+                self.stack.borrow().last(),
+                Some(StackValue::Pointer(_, _, false)) | Some(StackValue::Entry(_, _, false))
+            ),
+            _ => true,
+        };
+        let left = match self.get_value(lhs) {
+            Ok(obj) => obj,
+            Err(e) => return Err(e),
+        };
+        // map的键在取值和赋值时再检查
+        let pointer = match key_obj {
+            Some(obj) if left.is_map() => StackValue::Entry(left, obj, mutable),
+            // 切片是新的对象，不能通过切片修改原来的数组
+            Some(obj) if obj.as_range().is_some() => {
+                let range = obj.as_range().unwrap();
+                match left.get_slice(range) {
+                    Ok(slice) => StackValue::Object(slice),
+                    Err(e) => return Err(format!("{} ({})", e, location)),
+                }
+            }
+            _ if left.is_array() || left.is_tuple() => {
+                // 数组和元组的下标必须是整数
+                if let Some(obj) = key_obj.filter(|o| !o.is_integer()) {
+                    return Err(format!(
+                        "Type Error: index should be an integer, found {}. ({})",
+                        obj.get_typ(),
+                        location
+                    ));
+                }
+                StackValue::Pointer(left, key, mutable)
+            }
+            _ if left.is_struct() || left.is_module() => StackValue::Pointer(left, key, mutable),
+            _ => return Err(format!("Runtime Error: Operand is not a struct.")),
+        };
+        // 将一个这样的指针放入栈中
+        self.stack.borrow_mut().push(pointer);
+        Ok(())
+    }
+
+    // 匹配成功时在当前作用域中绑定变量，结果放入栈中
+    #[inline(never)]
+    fn match_value(
+        &self,
+        value: &Operand,
+        pattern: &Pattern,
+        location: &Location,
+    ) -> Result<(), String> {
+        let obj = self.get_value(value)?;
+        let mut bindings = vec![];
+        let matched = self
+            .match_pattern(pattern, &obj, &mut bindings)
+            .map_err(|e| format!("{} ({})", e, location))?;
+        if matched {
+            let mut scope = self.scope.borrow_mut();
+            for (name, obj) in bindings {
+                scope.define_variable(&name, false, obj.get_typ());
+                scope.set_variable(&name, obj)?;
+            }
+            self.push_obj(object::OBJ_TRUE.clone());
+        } else {
+            self.push_obj(object::OBJ_FALSE.clone());
+        }
+        Ok(())
+    }
+
+    // 解构对象，绑定的变量和没有声明类型的let变量一样，数值类型由绑定的值确定
+    #[inline(never)]
+    fn destruct(
+        &self,
+        mutable: bool,
+        value: &Operand,
+        pattern: &Pattern,
+        location: &Location,
+    ) -> Result<(), String> {
+        let obj = self.get_value(value)?;
+        let mut bindings = vec![];
+        let matched = self
+            .match_pattern(pattern, &obj, &mut bindings)
+            .map_err(|e| format!("{} ({})", e, location))?;
+        if !matched {
+            return Err(format!(
+                "Runtime Error: pattern {} does not match value {}. ({})",
+                pattern,
+                obj.get_value(),
+                location
+            ));
+        }
+        let mut scope = self.scope.borrow_mut();
+        for (name, obj) in bindings {
+            scope.define_untyped(&name, mutable);
+            scope.set_variable(&name, obj)?;
+        }
+        Ok(())
+    }
+
+    // 绑定模块，模块最外层的类型都加入当前作用域，导出的函数可能使用没有导出的类型
+    #[inline(never)]
+    fn import(&self, path: &str, name: &String, location: &Location) -> Result<(), String> {
+        let module = match self.scope.borrow().get_module(path) {
+            Some(module) => module,
+            None => {
+                return Err(format!(
+                    "Import Error: module {} is not loaded. ({})",
+                    path, location
+                ))
+            }
+        };
+        if let ObjectValue::Module { scope: origin, .. } = module.get_value() {
+            let mut scope = self.scope.borrow_mut();
+            let defined = origin.borrow();
+            for key in defined.type_names() {
+                if let Some(typ) = defined.get_type(&key) {
+                    // 模块从其他模块导入的类型，方法仍然在原来的模块中查找
+                    let home = defined.get_origin(&key).unwrap_or_else(|| origin.clone());
+                    scope.define_type(&key, &typ);
+                    scope.define_origin(&key, home);
+                    for trait_name in defined.impls_of(&key) {
+                        scope.define_impl(&key, &trait_name);
                     }
                 }
+            }
+            scope.define_variable(name, false, module.get_typ());
+            scope.set_variable(name, module.clone())?;
+        }
+        Ok(())
+    }
+
+    // 创建结构体字面量，结构体变体 E::V @ {...} 的字段由枚举定义
+    #[inline(never)]
+    fn create_struct(
+        &self,
+        value: &String,
+        keys: &[String],
+        location: &Location,
+    ) -> Result<(), String> {
+        let variant = self.scope.borrow().get_variant(value);
+        let found = match &variant {
+            Some((_, data)) => Some(data.clone()),
+            None => self.scope.borrow().get_type(value),
+        };
+        let fields = match found {
+            Some(NewTypeKind::Struct(fields)) => fields.into_iter().collect::<HashMap<_, _>>(),
+            Some(_) => {
+                return Err(format!(
+                    "Type Error: {} is not a struct. ({})",
+                    value, location
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "Type Error: {} is undefined. ({})",
+                    value, location
+                ))
+            }
+        };
+        // 逆序获取所有字段的值
+        let mut hash = HashMap::new();
+        for key in keys.iter().rev() {
+            let obj = self.pop()?;
+            let obj = self.get_stack_obj(&obj)?;
+            if hash.insert(key.clone(), obj).is_some() {
+                return Err(format!(
+                    "Type Error: field {} is specified more than once. ({})",
+                    key, location
+                ));
+            }
+        }
+        // 检查字段是否与结构体定义一致，数值按照字段的类型转换
+        for (key, obj) in hash.iter_mut() {
+            match fields.get(key) {
+                None => {
+                    return Err(format!(
+                        "Type Error: struct {} has no field {}. ({})",
+                        value, key, location
+                    ))
+                }
+                Some(typ) if !self.scope.borrow().accepts(typ, obj) => {
+                    return Err(format!(
+                        "Type Error: field {}.{} expects {}, found {}. ({})",
+                        value,
+                        key,
+                        typ,
+                        obj.get_typ(),
+                        location
+                    ))
+                }
+                Some(typ) => match number::cast(obj, typ) {
+                    Ok(v) => *obj = v,
+                    Err(e) => return Err(format!("{} ({})", e, location)),
+                },
+            }
+        }
+        let mut missing = fields
+            .keys()
+            .filter(|key| !hash.contains_key(*key))
+            .cloned()
+            .collect::<Vec<String>>();
+        if !missing.is_empty() {
+            missing.sort();
+            return Err(format!(
+                "Type Error: struct {} is missing field {}. ({})",
+                value,
+                missing.join(", "),
+                location
+            ));
+        }
+        let obj = object::create_struct(value, hash);
+        match variant {
+            Some((typ, _)) => {
+                let name = &value[typ.len() + 2..];
+                self.push_obj(object::create_enum(&typ, name, Some(obj)))
+            }
+            None => self.push_obj(obj),
+        }
+        Ok(())
+    }
+
+    // 函数调用
+    #[inline(never)]
+    fn call(&self, value: &Operand, num: usize, location: &Location) -> Result<(), String> {
+        // 检查操作数是不是变量, 或者在栈上
+        match value {
+            Operand::Stack | Operand::Var(_) => (),
+            _ => return Err(format!("Runtime Error: literal is not callable.")),
+        };
+        let args = self.pop_args(num)?;
+
+        // 被调用的是表达式的结果时，函数值在参数的下面
+        let res = if value.is_stack() {
+            let callee = self.pop()?;
+            let callee = self.get_stack_obj(&callee)?;
+            self.call_value(&callee, args, location)?
+        } else {
+            self.call_name(&self.scope, value.unwrap(), args, location)?
+        };
+        self.push_obj(res);
+        Ok(())
+    }
+
+    // 方法调用
+    #[inline(never)]
+    fn method(&self, name: &String, num: usize, location: &Location) -> Result<(), String> {
+        let args = self.pop_args(num)?;
+        let receiver = self.pop()?;
+        let receiver = self.get_stack_obj(&receiver)?;
+        let res = self.call_method(&receiver, name, args, location)?;
+        self.push_obj(res);
+        Ok(())
+    }
+
+    // 返回值按照声明的返回类型转换
+    #[inline(never)]
+    fn return_value(&self, value: &Operand, location: &Location) -> Result<WesoObject, String> {
+        let obj = self.get_value(value)?;
+        number::cast(&obj, &self.func.rettyp).map_err(|e| format!("{} ({})", e, location))
+    }
+
+    // 执行函数的指令，调用函数的指令在这里处理，其余的指令交给step
+    // 递归调用时每一层都会占用execute的栈帧，所以execute中只保留较小的分支
+    fn execute(&mut self) -> Result<WesoObject, String> {
+        // 执行过程中会切换作用域，指令集单独持有
+        let func = self.func.clone();
+        while let Some(ins) = func.codes.get(self.pc.get()) {
+            match &ins.node {
+                // 函数调用
+                InnerInstruction::Call { value, num } => self.call(value, *num, &ins.location)?,
+
+                // 方法调用
+                InnerInstruction::Method { name, num } => self.method(name, *num, &ins.location)?,
+
+                InnerInstruction::Return { value } => {
+                    return self.return_value(value, &ins.location)
+                }
+
+                _ => self.step(ins)?,
+            }
+            self.next();
+        }
+        Ok(object::OBJ_UNIT.clone())
+    }
 
-                InnerInstruction::Push { value } => {
-                    let obj = match self.get_value(value) {
-                        Ok(o) => o,
-                        Err(e) => return Err(e),
-                    };
-                    self.push_obj(obj);
+    // 执行不会调用函数的指令
+    #[inline(never)]
+    fn step(&mut self, ins: &Instruction) -> Result<(), String> {
+        match &ins.node {
+            // 变量定义
+            InnerInstruction::DefVar { mutable, name, typ } => {
+                let mut scope = self.scope.borrow_mut();
+                match typ {
+                    Some(typ) => scope.define_variable(name.unwrap(), *mutable, typ),
+                    None => scope.define_untyped(name.unwrap(), *mutable),
                 }
+            }
+
+            // 定义函数
+            InnerInstruction::DefFunc { name, id, sign } => {
+                self.scope
+                    .borrow_mut()
+                    .define_func(name.to_string(), sign.to_string(), *id);
+            }
+
+            // 对象赋值
+            InnerInstruction::Assign { lhs, rhs } => {
+                let rhs_obj = match self.get_value(rhs) {
+                    Ok(value) => value,
+                    Err(e) => return Err(e),
+                };
+                self.assign(lhs, rhs_obj, &ins.location)?;
+            }
 
-                InnerInstruction::If { value, addr } => {
-                    let test = match self.get_value(value) {
-                        Ok(o) => o,
-                        Err(e) => return Err(e),
-                    };
-                    if test.is_bool() {
-                        if Arc::ptr_eq(&test, &object::OBJ_FALSE.clone()) {
-                            self.goto(*addr);
+            // 转移所有权，右值变量在此之后失效
+            InnerInstruction::Move { lhs, rhs } => {
+                let rhs_obj = match self.get_value(rhs) {
+                    Ok(value) => value,
+                    Err(e) => return Err(e),
+                };
+                self.assign(lhs, rhs_obj, &ins.location)?;
+                match rhs {
+                    // 自己转移给自己时无需失效
+                    Operand::Var(name) if !(lhs.is_variable() && lhs.unwrap() == name) => {
+                        let res = self.scope.borrow_mut().move_variable(name);
+                        if let Err(e) = res {
+                            return Err(format!("{} ({})", e, ins.location));
                         }
-                    } else {
-                        return Err(format!("Type Error: Expression should be a boolean."));
                     }
+                    _ => {}
                 }
+            }
 
-                InnerInstruction::IfNot { value, addr } => {
-                    let test = match self.get_value(value) {
-                        Ok(o) => o,
-                        Err(e) => return Err(e),
-                    };
-                    if test.is_bool() {
-                        if Arc::ptr_eq(&test, &object::OBJ_TRUE.clone()) {
-                            self.goto(*addr);
-                        }
+            // 创建匿名函数的函数值，捕获当前的作用域
+            InnerInstruction::Lambda { id } => {
+                match object::create_function(*id, self.scope.clone()) {
+                    Some(obj) => self.push_obj(obj),
+                    None => return Err(String::from("Runtime Error: Function damage.")),
+                }
+            }
+
+            // 点操作
+            InnerInstruction::Dot { lhs, rhs } => self.dot(lhs, rhs, &ins.location)?,
+
+            // 将操作数做取反，再放入栈中
+            InnerInstruction::Not { value } => {
+                let obj = match self.get_value(value) {
+                    Ok(o) => o,
+                    Err(e) => return Err(e),
+                };
+                if obj.is_bool() {
+                    if Arc::ptr_eq(&obj, &object::OBJ_TRUE.clone()) {
+                        self.push_obj(object::OBJ_FALSE.clone());
                     } else {
-                        return Err(format!("Type Error: Expression should be a boolean."));
+                        self.push_obj(object::OBJ_TRUE.clone());
                     }
+                } else {
+                    return Err(format!("Type Error: Expression should be a boolean."));
                 }
+            }
 
-                InnerInstruction::Goto { addr } => {
-                    self.goto(*addr);
+            InnerInstruction::Push { value } => {
+                let obj = match self.get_value(value) {
+                    Ok(o) => o,
+                    Err(e) => return Err(e),
+                };
+                self.push_obj(obj);
+            }
+
+            InnerInstruction::If { value, addr } => {
+                let test = match self.get_value(value) {
+                    Ok(o) => o,
+                    Err(e) => return Err(e),
+                };
+                if test.is_bool() {
+                    if Arc::ptr_eq(&test, &object::OBJ_FALSE.clone()) {
+                        self.goto(*addr);
+                    }
+                } else {
+                    return Err(format!("Type Error: Expression should be a boolean."));
                 }
+            }
 
-                InnerInstruction::Return { value } => {
-                    let obj = match self.get_value(value) {
-                        Ok(o) => o,
-                        Err(e) => return Err(e),
-                    };
-                    return Ok(obj);
+            InnerInstruction::IfNot { value, addr } => {
+                let test = match self.get_value(value) {
+                    Ok(o) => o,
+                    Err(e) => return Err(e),
+                };
+                if test.is_bool() {
+                    if Arc::ptr_eq(&test, &object::OBJ_TRUE.clone()) {
+                        self.goto(*addr);
+                    }
+                } else {
+                    return Err(format!("Type Error: Expression should be a boolean."));
                 }
+            }
+
+            InnerInstruction::Goto { addr } => {
+                self.goto(*addr);
+            }
 
-                InnerInstruction::Repeat => {
-                    match self.stack.borrow().last() {
-                        Some(value) => self.stack.borrow_mut().push(value.clone()),
-                        None => return Err(String::from("Runtime Error: Stack damage.")),
-                    };
+            // 复合赋值 a[k] += 1 复制栈顶的指针，先取值再赋值
+            InnerInstruction::Repeat => {
+                let top = self.stack.borrow().last().cloned();
+                match top {
+                    Some(value) => self.stack.borrow_mut().push(value),
+                    None => return Err(String::from("Runtime Error: Stack damage.")),
+                };
+            }
+
+            InnerInstruction::Match { value, pattern } => {
+                self.match_value(value, pattern, &ins.location)?
+            }
+
+            InnerInstruction::Destruct {
+                mutable,
+                value,
+                pattern,
+            } => self.destruct(*mutable, value, pattern, &ins.location)?,
+
+            InnerInstruction::Fail { message } => {
+                return Err(format!("{} ({})", message, ins.location));
+            }
+
+            InnerInstruction::Try { addr } => {
+                self.handlers.borrow_mut().push(Handler {
+                    addr: *addr,
+                    scope: self.scope.clone(),
+                    stack: self.stack.borrow().len(),
+                    iters: self.iters.borrow().len(),
+                });
+            }
+
+            InnerInstruction::Import { path, name } => self.import(path, name, &ins.location)?,
+
+            InnerInstruction::Throw { value } => {
+                let obj = self.get_value(value)?;
+                if *obj.get_typ() != NewTypeKind::name("error") {
+                    return Err(format!(
+                        "Type Error: throw expects error, found {}. ({})",
+                        obj.get_typ(),
+                        ins.location
+                    ));
                 }
+                return Err(object::error_message(&obj, &ins.location.to_string()));
+            }
 
-                // 创建结构体字面量
-                InnerInstruction::Struct { value: _, num: _ } => {}
+            // 创建迭代器
+            InnerInstruction::Iter { value } => {
+                let obj = self.get_value(value)?;
+                let order = match self.scope.borrow().get_type(&obj.get_typ().base_name()) {
+                    Some(NewTypeKind::Struct(fields)) => fields.into_iter().map(|f| f.0).collect(),
+                    _ => vec![],
+                };
+                let iter =
+                    WesoIter::new(&obj, &order).map_err(|e| format!("{} ({})", e, ins.location))?;
+                self.iters.borrow_mut().push(iter);
+            }
 
-                // 销毁变量
-                InnerInstruction::Destroy { value: _ } => {}
+            // 迭代器取值，结束时跳转
+            InnerInstruction::Next { addr } => {
+                let item = match self.iters.borrow_mut().last_mut() {
+                    Some(iter) => iter.next(),
+                    None => return Err(String::from("Runtime Error: Iterator damage.")),
+                };
+                match item {
+                    Some(obj) => self.push_obj(obj),
+                    None => self.goto(*addr),
+                }
+            }
 
-                // Instruction::Break => {}
-                // Instruction::Continue {} => {}
-                _ => return Err(format!("Runtime Error: Unhandled instruction.")),
-            };
-            self.next();
-        }
-        Ok(object::OBJ_UNIT.clone())
+            InnerInstruction::EndIter => {
+                self.iters.borrow_mut().pop();
+            }
+
+            // 定义类型
+            InnerInstruction::DefType { name, typ } => {
+                self.scope.borrow_mut().define_type(name, typ);
+            }
+
+            InnerInstruction::DefImpl { typ, name } => {
+                self.scope.borrow_mut().define_impl(typ, name);
+            }
+
+            // 创建结构体字面量
+            InnerInstruction::Struct { value, keys } => {
+                self.create_struct(value, keys, &ins.location)?
+            }
+
+            // 进入块作用域
+            InnerInstruction::Enter => {
+                let scope = Scope::new(Some(self.scope.clone()));
+                self.scope = Rc::new(RefCell::new(scope));
+            }
+
+            // 离开块作用域，块内定义的变量随之销毁
+            InnerInstruction::Leave => self.leave()?,
+
+            InnerInstruction::Exit { addr, depth } => {
+                for _ in 0..*depth {
+                    self.leave()?;
+                }
+                self.goto(*addr);
+            }
+
+            // Instruction::Break => {}
+            // Instruction::Continue {} => {}
+            _ => return Err(format!("Runtime Error: Unhandled instruction.")),
+        };
+        Ok(())
     }
 }
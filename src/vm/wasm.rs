@@ -1,11 +1,24 @@
 use wasm_bindgen::prelude::*;
 
-use crate::vm::{weso_parse, weso_run};
+use crate::vm::module::MemoryLoader;
+use crate::vm::{weso_parse_with, weso_run_with};
+use std::cell::RefCell;
+
+thread_local! {
+    // wasm中没有文件系统，模块的源码由页面提前添加
+    static MODULES: RefCell<MemoryLoader> = RefCell::new(MemoryLoader::default());
+}
+
+#[wasm_bindgen]
+#[allow(unused)]
+pub fn add_module(path: &str, code: &str) {
+    MODULES.with(|modules| modules.borrow_mut().insert(path, code));
+}
 
 #[wasm_bindgen]
 #[allow(unused)]
 pub fn parse(s: &str) -> String {
-    match weso_parse(s) {
+    match MODULES.with(|modules| weso_parse_with(s, &*modules.borrow())) {
         Ok(s) => s.join("\n"),
         Err(e) => e,
     }
@@ -14,7 +27,7 @@ pub fn parse(s: &str) -> String {
 #[wasm_bindgen]
 #[allow(unused)]
 pub fn run(s: &str) -> String {
-    match weso_run(s) {
+    match MODULES.with(|modules| weso_run_with(s, &*modules.borrow())) {
         Ok(_) => String::new(),
         Err(e) => e,
     }